@@ -0,0 +1,76 @@
+use criterion::{BatchSize, Criterion, black_box, criterion_group, criterion_main};
+use tes3::esp::{Light, LightData, LightFlags};
+
+use s3lightfixes::{
+    Encoding, LightConfig, MergeStrategy,
+    extract::LightSet,
+    transform::{self, batch_to_hsv, process_light},
+};
+
+/// Roughly the number of lights in a large modlist
+const LIGHT_COUNT: usize = 20_000;
+
+/// Lights spread across the hue wheel, so both standard and colored lights are covered
+fn sample_lights() -> Vec<Light> {
+    (0..LIGHT_COUNT)
+        .map(|index| Light {
+            id: format!("bench_light_{index}"),
+            data: LightData {
+                weight: 1.0,
+                value: 5,
+                time: 600,
+                radius: 128 + (index % 256) as u32,
+                color: [
+                    (index * 7 % 256) as u8,
+                    (index * 13 % 256) as u8,
+                    (index * 29 % 256) as u8,
+                    0,
+                ],
+                flags: LightFlags::DYNAMIC | LightFlags::FLICKER,
+            },
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn sample_light_set() -> LightSet {
+    let mut light_set = LightSet::new(Encoding::default(), MergeStrategy::default());
+    light_set.add_plugin("bench.esp".into(), sample_lights(), |_| true);
+    light_set
+}
+
+fn bench_transform(c: &mut Criterion) {
+    let light_config = LightConfig::default();
+
+    c.bench_function("process_light", |b| {
+        b.iter_batched(
+            sample_lights,
+            |mut lights| {
+                for light in &mut lights {
+                    black_box(process_light(&light_config, light));
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("transform::apply", |b| {
+        b.iter_batched(
+            sample_light_set,
+            |mut light_set| black_box(transform::apply(&mut light_set, &light_config)),
+            BatchSize::LargeInput,
+        )
+    });
+
+    let light_data: Vec<LightData> = sample_lights()
+        .into_iter()
+        .map(|light| light.data)
+        .collect();
+
+    c.bench_function("batch_to_hsv", |b| {
+        b.iter(|| black_box(batch_to_hsv(&light_data)))
+    });
+}
+
+criterion_group!(benches, bench_transform);
+criterion_main!(benches);
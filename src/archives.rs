@@ -0,0 +1,110 @@
+use std::{
+    collections::HashMap,
+    fs::{File, read_dir},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// Version number at the start of every Morrowind BSA
+const TES3_BSA_VERSION: u32 = 0x100;
+
+fn read_u32(file: &mut File) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    file.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Lists the files in a Morrowind BSA, as lowercased VFS paths with forward slashes
+/// Archives in any other format are reported as invalid data
+pub fn bsa_file_names(path: &Path) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+
+    if read_u32(&mut file)? != TES3_BSA_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not a Morrowind BSA", path.display()),
+        ));
+    }
+
+    let hash_offset = read_u32(&mut file)? as u64;
+    let file_count = read_u32(&mut file)? as u64;
+
+    // Sizes and offsets, then name offsets, then the names themselves up to the hash table
+    let names_start = file_count * 12;
+    let names_len = hash_offset.checked_sub(names_start).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} has a malformed header", path.display()),
+        )
+    })?;
+
+    file.seek(SeekFrom::Start(12 + names_start))?;
+
+    let mut names = vec![0; names_len as usize];
+    file.read_exact(&mut names)?;
+
+    Ok(names
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            String::from_utf8_lossy(name)
+                .replace('\\', "/")
+                .to_ascii_lowercase()
+        })
+        .collect())
+}
+
+/// Files inside archives which sit in a data directory but aren't listed as fallback-archive,
+/// so OpenMW never sees them
+#[derive(Debug, Default)]
+pub struct UnregisteredArchives {
+    /// Lowercased VFS path of each file, and the archive holding it
+    files: HashMap<String, PathBuf>,
+    pub archives: Vec<PathBuf>,
+}
+
+impl UnregisteredArchives {
+    /// Reads every BSA at the root of the data directories which isn't in `registered`
+    /// Archives which can't be read are logged and left out
+    pub fn scan(directories: &[PathBuf], registered: &[String]) -> UnregisteredArchives {
+        let mut unregistered = UnregisteredArchives::default();
+
+        for directory in directories {
+            let Ok(entries) = read_dir(directory) else {
+                continue;
+            };
+
+            for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+                let Some(name) = path.file_name().map(|name| name.to_string_lossy()) else {
+                    continue;
+                };
+
+                if !name.to_ascii_lowercase().ends_with(".bsa")
+                    || registered
+                        .iter()
+                        .any(|archive| archive.eq_ignore_ascii_case(&name))
+                {
+                    continue;
+                }
+
+                match bsa_file_names(&path) {
+                    Ok(names) => {
+                        for name in names {
+                            unregistered.files.entry(name).or_insert(path.clone());
+                        }
+
+                        unregistered.archives.push(path);
+                    }
+                    Err(err) => log::warn!("Could not read archive {}: {err}", path.display()),
+                }
+            }
+        }
+
+        unregistered
+    }
+
+    /// The unregistered archive holding an asset, given as a lowercased VFS path
+    pub fn find(&self, vfs_path: &str) -> Option<&Path> {
+        self.files.get(vfs_path).map(PathBuf::as_path)
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    fs::{read_dir, remove_file, rename},
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{ESP_PLUGIN_NAME, PLUGIN_NAME, SPLIT_PLUGIN_PREFIX};
+
+const BACKUP_EXTENSION: &str = "bak";
+
+static BACKUP_COUNT: AtomicUsize = AtomicUsize::new(1);
+
+/// How many previous versions of each generated plugin are kept when it's overwritten
+/// Zero turns backups off
+pub fn set_backup_count(count: usize) {
+    BACKUP_COUNT.store(count, Ordering::Relaxed);
+}
+
+/// `S3LightFixes.omwaddon.bak` for the latest backup, then `.bak.2`, `.bak.3`, and so on
+fn backup_path(plugin_path: &Path, index: usize) -> PathBuf {
+    let mut name = plugin_path.as_os_str().to_owned();
+    name.push(".");
+    name.push(BACKUP_EXTENSION);
+
+    if index > 1 {
+        name.push(format!(".{index}"));
+    }
+
+    PathBuf::from(name)
+}
+
+/// Moves the plugin at `plugin_path` aside before it's overwritten, shifting older backups along by one
+/// The oldest backup past the retention count is dropped
+pub fn back_up(plugin_path: &Path) -> io::Result<()> {
+    let count = BACKUP_COUNT.load(Ordering::Relaxed);
+
+    if count == 0 || !plugin_path.is_file() {
+        return Ok(());
+    }
+
+    let _ = remove_file(backup_path(plugin_path, count));
+
+    for index in (1..count).rev() {
+        let _ = rename(
+            backup_path(plugin_path, index),
+            backup_path(plugin_path, index + 1),
+        );
+    }
+
+    rename(plugin_path, backup_path(plugin_path, 1))
+}
+
+/// Puts the latest backup of a plugin back in its place, shifting older backups forward by one
+/// Returns false if there was no backup to restore
+pub fn restore(plugin_path: &Path) -> io::Result<bool> {
+    if !backup_path(plugin_path, 1).is_file() {
+        return Ok(false);
+    }
+
+    rename(backup_path(plugin_path, 1), plugin_path)?;
+
+    let mut index = 2;
+    while backup_path(plugin_path, index).is_file() {
+        rename(
+            backup_path(plugin_path, index),
+            backup_path(plugin_path, index - 1),
+        )?;
+        index += 1;
+    }
+
+    Ok(true)
+}
+
+/// Every plugin in `dir` lightfixes could have generated which has a backup, including split output
+pub fn backed_up_plugins(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut plugins: Vec<PathBuf> = read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let plugin_name = name.strip_suffix(&format!(".{BACKUP_EXTENSION}"))?;
+
+            (plugin_name == PLUGIN_NAME
+                || plugin_name == ESP_PLUGIN_NAME
+                || plugin_name.starts_with(SPLIT_PLUGIN_PREFIX))
+            .then(|| dir.join(plugin_name))
+        })
+        .collect();
+
+    plugins.sort();
+    Ok(plugins)
+}
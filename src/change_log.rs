@@ -0,0 +1,137 @@
+use std::{
+    fs::write,
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use tes3::esp::{AtmosphereData, Light};
+
+use crate::{LogFormat, color, to_io_error};
+
+/// Written next to lightconfig.toml, with the extension of its format
+pub const CHANGE_LOG_NAME: &str = "lightconfig-changes";
+
+/// A field's value in the load order, and in the generated plugin
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Change<T> {
+    pub before: T,
+    pub after: T,
+}
+
+/// Only fields which actually changed are kept, so unchanged ones don't bury the rest
+fn change<T: PartialEq>(before: T, after: T) -> Option<Change<T>> {
+    (before != after).then_some(Change { before, after })
+}
+
+/// What lightfixes changed about one light, and which plugin the original came from
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LightChange {
+    pub id: String,
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius: Option<Change<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<Change<i32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sound: Option<Change<String>>,
+}
+
+impl LightChange {
+    pub fn new(source: &Path, before: &Light, after: &Light) -> LightChange {
+        LightChange {
+            id: after.id.clone(),
+            source: file_name(source),
+            color: change(
+                color::to_hex(before.data.color),
+                color::to_hex(after.data.color),
+            ),
+            radius: change(before.data.radius, after.data.radius),
+            duration: change(before.data.time, after.data.time),
+            flags: change(
+                format!("{:?}", before.data.flags),
+                format!("{:?}", after.data.flags),
+            ),
+            sound: change(before.sound.clone(), after.sound.clone()),
+        }
+    }
+}
+
+/// What lightfixes changed about the lighting of one interior cell, and which plugin the original came from
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CellChange {
+    pub name: String,
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ambient: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sunlight: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fog: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fog_density: Option<Change<f32>>,
+}
+
+impl CellChange {
+    pub fn new(
+        name: &str,
+        source: &Path,
+        before: &AtmosphereData,
+        after: &AtmosphereData,
+    ) -> CellChange {
+        CellChange {
+            name: name.to_string(),
+            source: file_name(source),
+            ambient: change(
+                color::to_hex(before.ambient_color),
+                color::to_hex(after.ambient_color),
+            ),
+            sunlight: change(
+                color::to_hex(before.sunlight_color),
+                color::to_hex(after.sunlight_color),
+            ),
+            fog: change(
+                color::to_hex(before.fog_color),
+                color::to_hex(after.fog_color),
+            ),
+            fog_density: change(before.fog_density, after.fog_density),
+        }
+    }
+}
+
+/// Every record in the generated plugin, as old and new values of the fields lightfixes changed
+/// Much smaller than a dump of the whole plugin, and says where each record came from
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ChangeLog {
+    pub lights: Vec<LightChange>,
+    pub cells: Vec<CellChange>,
+}
+
+impl ChangeLog {
+    /// Writes the log into `dir` as TOML or JSON, returning its path
+    /// The full format is a dump of the plugin itself, and isn't written from here
+    pub fn write(&self, dir: &Path, format: LogFormat) -> io::Result<PathBuf> {
+        let (extension, contents) = match format {
+            LogFormat::Json => ("json", serde_json::to_string_pretty(self)?),
+            LogFormat::Toml | LogFormat::Full => {
+                ("toml", toml::to_string_pretty(self).map_err(to_io_error)?)
+            }
+        };
+
+        let path = dir.join(format!("{CHANGE_LOG_NAME}.{extension}"));
+        write(&path, contents)?;
+
+        Ok(path)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,132 @@
+use std::{
+    collections::BTreeMap,
+    fs::{read_to_string, write},
+    io,
+    path::Path,
+};
+
+use palette::{FromColor, Hsv, rgb::Srgb};
+use serde::{Deserialize, Serialize};
+use tes3::esp::{Light, LightFlags};
+
+use crate::to_io_error;
+
+/// Classes written by `s3lightfixes classify`, kept next to lightconfig.toml so they can be corrected by hand
+pub const CLASSES_NAME: &str = "lightclasses.toml";
+
+/// Words in the ID, model, script, or sound of lights which burn
+const FLAME_WORDS: &[&str] = &[
+    "fire", "flame", "torch", "candle", "lantern", "brazier", "sconce", "campfire", "coal", "ember",
+];
+
+/// Words in the ID, model, or script of lights which glow by magic or nature
+const MAGIC_WORDS: &[&str] = &[
+    "magic", "glow", "crystal", "mushroom", "welkynd", "daedric", "spell", "soul", "ghost",
+];
+
+/// Words in the ID or model of lights standing in for the sun or sky
+const DAYLIGHT_WORDS: &[&str] = &["sunlight", "sunray", "daylight", "window", "skylight"];
+
+/// What kind of source a light stands in for, which decides any class multipliers it gets
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum LightClass {
+    /// Torches, candles, fires, and lanterns
+    Flame,
+    /// Crystals, glowing mushrooms, and enchanted objects
+    Magic,
+    /// Sunlight and light through windows
+    Daylight,
+    /// Anything else, usually lights with no visible source which brighten a room
+    Ambient,
+}
+
+fn mentions_any(fields: &[&str], words: &[&str]) -> bool {
+    fields.iter().any(|field| {
+        let field = field.to_ascii_lowercase();
+        words.iter().any(|word| field.contains(word))
+    })
+}
+
+/// Guesses the class of a light from its names, flags, and color
+/// Names are checked first, as they're the most reliable, then animation, then color
+pub fn classify(light: &Light) -> LightClass {
+    let names = [
+        light.id.as_str(),
+        light.mesh.as_str(),
+        light.script.as_str(),
+        light.sound.as_str(),
+    ];
+
+    if mentions_any(&names, FLAME_WORDS) {
+        return LightClass::Flame;
+    }
+
+    if mentions_any(&names[..3], MAGIC_WORDS) {
+        return LightClass::Magic;
+    }
+
+    if mentions_any(&names[..2], DAYLIGHT_WORDS) {
+        return LightClass::Daylight;
+    }
+
+    if light
+        .data
+        .flags
+        .intersects(LightFlags::FLICKER | LightFlags::FLICKER_SLOW)
+    {
+        return LightClass::Flame;
+    }
+
+    let [red, green, blue, _] = light.data.color;
+    let hsv: Hsv = Hsv::from_color(Srgb::new(red, green, blue).into_format::<f32>());
+    let hue = hsv.hue.into_positive_degrees();
+
+    // Flames sit between red and yellow, so anything well outside that which pulses is most likely magical
+    let is_flame_hue = hsv.saturation > 0.2 && (hue < 64.0 || hue > 345.0);
+
+    if light
+        .data
+        .flags
+        .intersects(LightFlags::PULSE | LightFlags::PULSE_SLOW)
+        && !is_flame_hue
+    {
+        return LightClass::Magic;
+    }
+
+    if hsv.saturation < 0.15 && hsv.value > 0.8 {
+        return LightClass::Daylight;
+    }
+
+    LightClass::Ambient
+}
+
+/// The class of every light, by lowercased ID
+/// Entries found in the file take priority over guesses, so hand corrections stick
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct LightClasses {
+    #[serde(default)]
+    pub lights: BTreeMap<String, LightClass>,
+}
+
+impl LightClasses {
+    /// A missing or unreadable file is treated as empty
+    pub fn load(path: &Path) -> LightClasses {
+        read_to_string(path)
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write(path, toml::to_string_pretty(self).map_err(to_io_error)?)
+    }
+
+    /// The class recorded for a light, or a guess if there's none
+    pub fn class_of(&self, light_id: &str, light: &Light) -> LightClass {
+        self.lights
+            .get(light_id)
+            .copied()
+            .unwrap_or_else(|| classify(light))
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    fs::{read_dir, rename},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{ESP_PLUGIN_NAME, PLUGIN_NAME, canonical_path, is_generated_plugin};
+
+/// Added to the name of each stray output `--clean` finds, so OpenMW no longer sees it but it can still be restored
+pub const DISABLED_SUFFIX: &str = ".disabled";
+
+/// Whether a file is an output of lightfixes: one under its usual names in any case,
+/// or any plugin whose header carries the generated marker, such as a copy renamed by hand
+fn is_output(path: &Path) -> bool {
+    let Some(name) = path.file_name().map(|name| name.to_string_lossy()) else {
+        return false;
+    };
+
+    if name.eq_ignore_ascii_case(PLUGIN_NAME) || name.eq_ignore_ascii_case(ESP_PLUGIN_NAME) {
+        return true;
+    }
+
+    let is_plugin = path.extension().is_some_and(|extension| {
+        ["esp", "esm", "omwaddon", "omwgame"]
+            .iter()
+            .any(|plugin_extension| extension.eq_ignore_ascii_case(plugin_extension))
+    });
+
+    is_plugin && is_generated_plugin(path)
+}
+
+/// Older outputs left at the root of any of `search_dirs`, which would shadow or duplicate the new one
+/// `output_dir` is left out, as everything generated there belongs to this run, split outputs included
+pub fn find_stray_outputs(search_dirs: &[PathBuf], output_dir: &Path) -> Vec<PathBuf> {
+    let output_dir = canonical_path(output_dir);
+    let mut strays: Vec<PathBuf> = Vec::new();
+
+    for directory in search_dirs {
+        if canonical_path(directory) == output_dir {
+            continue;
+        }
+
+        let Ok(entries) = read_dir(directory) else {
+            continue;
+        };
+
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            // The same directory can be listed twice, eg once through a link
+            if path.is_file()
+                && is_output(&path)
+                && !strays
+                    .iter()
+                    .any(|stray| canonical_path(stray) == canonical_path(&path))
+            {
+                strays.push(path);
+            }
+        }
+    }
+
+    strays
+}
+
+/// Renames a stray output so that OpenMW no longer loads it, returning its new path
+pub fn disable_output(path: &Path) -> io::Result<PathBuf> {
+    let mut disabled = path.as_os_str().to_owned();
+    disabled.push(DISABLED_SUFFIX);
+
+    let disabled = PathBuf::from(disabled);
+    rename(path, &disabled)?;
+
+    Ok(disabled)
+}
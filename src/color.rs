@@ -0,0 +1,174 @@
+use palette::{FromColor, Hsv, Lab, RgbHue, color_difference::Ciede2000, rgb::Srgb};
+
+use crate::{ColorRounding, ColorSpace};
+
+/// Approximates the color of a blackbody radiator at the given temperature
+/// Based on Tanner Helland's curve fit, which is accurate enough between 1000K and 40000K
+pub fn kelvin_to_rgb(kelvin: f32) -> Srgb {
+    let temp = kelvin.clamp(1000.0, 40000.0) / 100.0;
+
+    let red = if temp <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (temp - 60.0).powf(-0.133_204_76)
+    };
+
+    let green = if temp <= 66.0 {
+        99.470_8 * temp.ln() - 161.119_57
+    } else {
+        288.122_17 * (temp - 60.0).powf(-0.075_514_85)
+    };
+
+    let blue = if temp >= 66.0 {
+        255.0
+    } else if temp <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (temp - 10.0).ln() - 305.044_8
+    };
+
+    Srgb::new(
+        red.clamp(0.0, 255.0) / 255.0,
+        green.clamp(0.0, 255.0) / 255.0,
+        blue.clamp(0.0, 255.0) / 255.0,
+    )
+}
+
+/// Moves a color toward the blackbody color of the given temperature
+/// The brightness of the original color is kept, and a `strength` of 1.0 replaces its hue and saturation entirely
+pub fn blend_toward_kelvin(color: Hsv, kelvin: f32, strength: f32) -> Hsv {
+    blend_toward(color, kelvin_to_rgb(kelvin), strength)
+}
+
+/// Moves a color toward `target`, keeping the brightness of the original color
+/// A `strength` of 1.0 replaces its hue and saturation entirely
+pub fn blend_toward(color: Hsv, target: Srgb, strength: f32) -> Hsv {
+    let mut target: Hsv = Hsv::from_color(target);
+    target.value = color.value;
+
+    let source: Srgb = Srgb::from_color(color);
+    let target: Srgb = Srgb::from_color(target);
+    let strength = strength.clamp(0.0, 1.0);
+
+    let mix = |from: f32, to: f32| from + (to - from) * strength;
+
+    Hsv::from_color(Srgb::new(
+        mix(source.red, target.red),
+        mix(source.green, target.green),
+        mix(source.blue, target.blue),
+    ))
+}
+
+/// Multiplies the hue, saturation, and value of a color as stored in light and cell records
+pub fn scale_hsv(color: [u8; 4], hue: f32, saturation: f32, value: f32) -> [u8; 4] {
+    let mut hsv: Hsv =
+        Hsv::from_color(Srgb::new(color[0], color[1], color[2]).into_format::<f32>());
+
+    hsv.hue = RgbHue::from_degrees(hsv.hue.into_raw_degrees() * hue);
+    hsv.saturation = (hsv.saturation * saturation).clamp(0.0, 1.0);
+    hsv.value = (hsv.value * value).clamp(0.0, 1.0);
+
+    let scaled: Srgb<u8> = Srgb::from_color(hsv).into_format();
+    [scaled.red, scaled.green, scaled.blue, color[3]]
+}
+
+/// Multiplies the brightness of a color, either on its gamma-encoded value or in linear light
+pub fn scale_value(color: Hsv, multiplier: f32, color_space: ColorSpace) -> Hsv {
+    match color_space {
+        ColorSpace::Srgb => {
+            let mut color = color;
+            color.value *= multiplier;
+            color
+        }
+        ColorSpace::Linear => {
+            let linear = Srgb::from_color(color).into_linear() * multiplier;
+            let mut scaled: Hsv = Hsv::from_color(Srgb::from_linear(linear));
+
+            // Black has no hue of its own, so keep the original in case the light is brightened again later
+            scaled.hue = color.hue;
+            scaled
+        }
+    }
+}
+
+/// Relative luminance of a color, from 0 for black to 1 for white, weighting each channel by how bright it looks
+pub fn relative_luminance(color: Hsv) -> f32 {
+    let linear = Srgb::from_color(color).into_linear();
+    0.2126 * linear.red + 0.7152 * linear.green + 0.0722 * linear.blue
+}
+
+/// Brightens a color in linear light until its relative luminance reaches `min_luminance`, keeping its hue
+/// Deep blues and reds can't get bright enough before reaching full value, so they stop there
+pub fn raise_to_luminance(color: Hsv, min_luminance: f32) -> Hsv {
+    let luminance = relative_luminance(color);
+
+    // Black has no color to brighten, and is how lights are turned off
+    if luminance >= min_luminance || luminance <= 0.0 {
+        return color;
+    }
+
+    let mut raised = scale_value(color, min_luminance / luminance, ColorSpace::Linear);
+    raised.value = raised.value.min(1.0);
+    raised
+}
+
+/// Converts an adjusted color into the 8-bit form stored in light records
+/// `seed` should identify the light, so that dithering rounds it the same way on every run
+pub fn quantize(color: Hsv, rounding: ColorRounding, seed: &str) -> [u8; 4] {
+    let rgb: Srgb = Srgb::from_color(color);
+
+    let offset = match rounding {
+        ColorRounding::Nearest => 0.5,
+        ColorRounding::Truncate => 0.0,
+        ColorRounding::Dither => dither_offset(seed),
+    };
+
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0 + offset).floor().min(255.0) as u8;
+
+    [channel(rgb.red), channel(rgb.green), channel(rgb.blue), 0]
+}
+
+/// A stable value in 0..1 for each seed, using FNV-1a
+fn dither_offset(seed: &str) -> f32 {
+    let hash = seed.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    });
+
+    (hash >> 8) as f32 / (1u32 << 24) as f32
+}
+
+/// Perceptual difference (CIEDE2000 delta-E) between two 8-bit colors as stored in light records
+/// Differences below ~2.0 are generally imperceptible
+pub fn delta_e(before: [u8; 4], after: [u8; 4]) -> f32 {
+    let to_lab = |color: [u8; 4]| -> Lab {
+        Lab::from_color(Srgb::new(color[0], color[1], color[2]).into_format::<f32>())
+    };
+
+    to_lab(before).difference(to_lab(after))
+}
+
+/// Formats a color as stored in light and cell records as a hex triplet, like #FFB46E
+pub fn to_hex(color: [u8; 4]) -> String {
+    format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2])
+}
+
+/// A block of the color for truecolor terminals, as stored in light and cell records
+pub fn ansi_swatch(color: [u8; 4]) -> String {
+    format!(
+        "\x1b[48;2;{};{};{}m      \x1b[0m",
+        color[0], color[1], color[2]
+    )
+}
+
+/// Parses a hex triplet such as #FFB46E, with or without the leading #
+pub fn from_hex(hex: &str) -> Option<[u8; 3]> {
+    let digits = hex.trim().trim_start_matches('#');
+
+    if digits.len() != 6 || !digits.is_ascii() {
+        return None;
+    }
+
+    let channel = |index: usize| u8::from_str_radix(&digits[index..index + 2], 16).ok();
+
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
@@ -0,0 +1,197 @@
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+
+use crate::{LightConfig, OutputFormat, SplitOutput};
+
+/// Multipliers above this are allowed, but are more often a slipped decimal point than a choice
+pub const LARGE_MULTIPLIER: f32 = 3.0;
+
+/// Most suggestions listed for a single unknown setting
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Captures the field names a derived Deserialize impl asks for, then stops
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("only the field names were needed"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Names of the settings a struct accepts, read from its Deserialize impl so that they can't drift from the struct
+pub(crate) fn field_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// Number of single-character insertions, deletions, and substitutions turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Known settings close enough to `key` to be what was meant, closest first
+fn near_misses(key: &str, known: &[&'static str]) -> Vec<&'static str> {
+    let key = key.to_ascii_lowercase();
+    // Roughly one typo per four characters, so short names don't match everything
+    let max_distance = (key.len() / 4).max(2);
+
+    let mut candidates: Vec<(usize, &'static str)> = known
+        .iter()
+        .map(|name| (edit_distance(&key, name), *name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+
+    candidates.sort();
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Describes each top-level setting in a lightconfig.toml document which lightfixes doesn't know,
+/// along with the known settings it most likely meant
+/// Documents which aren't valid TOML return nothing, and are left for the parser to report
+pub fn unknown_settings(config_contents: &str) -> Vec<String> {
+    let Ok(document) = config_contents.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+
+    let known = field_names::<LightConfig>();
+
+    document
+        .keys()
+        .filter(|key| !known.contains(&key.as_str()))
+        .map(|key| match near_misses(key, known).as_slice() {
+            [] => format!("Unknown setting `{key}`."),
+            suggestions => format!(
+                "Unknown setting `{key}`. Did you mean {}?",
+                suggestions
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            ),
+        })
+        .collect()
+}
+
+impl LightConfig {
+    /// Every multiplier which can be set, by the name used in lightconfig.toml
+    fn multipliers(&self) -> Vec<(String, f32)> {
+        let mut multipliers: Vec<(String, f32)> = [
+            ("standard_hue", self.standard_hue),
+            ("standard_saturation", self.standard_saturation),
+            ("standard_value", self.standard_value),
+            ("standard_radius", self.standard_radius),
+            ("colored_hue", self.colored_hue),
+            ("colored_saturation", self.colored_saturation),
+            ("colored_value", self.colored_value),
+            ("colored_radius", self.colored_radius),
+            ("duration_mult", self.duration_mult),
+            ("exterior_radius", self.exterior_radius),
+            ("interior_radius", self.interior_radius),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+
+        for (bucket_name, bucket) in [
+            ("red", self.colored.red),
+            ("green", self.colored.green),
+            ("blue", self.colored.blue),
+            ("purple", self.colored.purple),
+        ] {
+            let Some(bucket) = bucket else {
+                continue;
+            };
+
+            for (name, value) in [
+                ("hue", bucket.hue),
+                ("saturation", bucket.saturation),
+                ("value", bucket.value),
+                ("radius", bucket.radius),
+            ] {
+                if let Some(value) = value {
+                    multipliers.push((format!("colored.{bucket_name}.{name}"), value));
+                }
+            }
+        }
+
+        multipliers
+    }
+
+    /// Describes each multiplier set below zero, which would flip colors or radii rather than scale them
+    pub fn negative_multipliers(&self) -> Vec<String> {
+        self.multipliers()
+            .into_iter()
+            .filter(|(_, value)| *value < 0.0)
+            .map(|(name, value)| format!("{name} is {value}, but multipliers can't be negative."))
+            .collect()
+    }
+
+    /// Describes each pair of settings which can't be used together
+    pub fn conflicting_settings(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+
+        if self.split_output == SplitOutput::PerPlugin {
+            if self.output_format != OutputFormat::Omwaddon {
+                conflicts.push(
+                    "split_output = \"per_plugin\" only writes omwaddons, so output_format must be \"omwaddon\"."
+                        .to_string(),
+                );
+            }
+
+            if self.preserve_edits {
+                conflicts.push(
+                    "preserve_edits only works with a single generated plugin, so it can't be used with split_output = \"per_plugin\"."
+                        .to_string(),
+                );
+            }
+        }
+
+        conflicts
+    }
+
+    /// Returns a warning for each multiplier above LARGE_MULTIPLIER
+    pub fn large_multipliers(&self) -> Vec<String> {
+        self.multipliers()
+            .into_iter()
+            .filter(|(_, value)| *value > LARGE_MULTIPLIER)
+            .map(|(name, value)| {
+                format!("{name} is {value}, which is unusually large. Check that it isn't a typo, such as a missing decimal point.")
+            })
+            .collect()
+    }
+}
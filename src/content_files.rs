@@ -0,0 +1,148 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{canonicalize, read_dir},
+    path::{Path, PathBuf},
+};
+
+use crate::Warning;
+
+/// Extensions tried, in order, for content entries written without one
+const CONTENT_EXTENSIONS: [&str; 4] = ["esm", "esp", "omwgame", "omwaddon"];
+
+/// The path a file or directory really lives at, with every symlink and junction along the way resolved
+/// Paths which can't be resolved, eg because they don't exist, are returned as they are
+pub fn canonical_path(path: &Path) -> PathBuf {
+    canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Where each content file named in a load order physically lives
+/// Content entries are matched case-insensitively, and a later data directory overrides an earlier one, as in OpenMW.
+/// On case-sensitive filesystems a single directory can also hold files differing only in case, like clock.esp and Clock.ESP.
+/// Of those, the name which sorts first byte-wise wins, so the choice doesn't depend on the order the filesystem lists them in.
+#[derive(Debug, Default)]
+pub struct ContentFiles {
+    /// Every file at the root of a data directory, keyed by its lowercased name, with the winner first
+    candidates: HashMap<String, Vec<PathBuf>>,
+}
+
+impl ContentFiles {
+    /// Lists the files at the root of each data directory, given in the order openmw.cfg has them
+    /// Directories which can't be read are left out
+    pub fn scan(directories: &[PathBuf]) -> ContentFiles {
+        let mut candidates: HashMap<String, Vec<PathBuf>> = HashMap::new();
+
+        // Later directories win, so they're listed first
+        for directory in directories.iter().rev() {
+            let Ok(entries) = read_dir(directory) else {
+                continue;
+            };
+
+            let mut files: Vec<PathBuf> = entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect();
+
+            files.sort();
+
+            for file in files {
+                if let Some(name) = file.file_name() {
+                    let name = name.to_string_lossy().to_ascii_lowercase();
+                    let candidates = candidates.entry(name).or_default();
+
+                    // A directory reached through a link can list the very same file again, which shadows nothing
+                    let canonical = canonical_path(&file);
+                    if !candidates
+                        .iter()
+                        .any(|candidate| canonical_path(candidate) == canonical)
+                    {
+                        candidates.push(file);
+                    }
+                }
+            }
+        }
+
+        ContentFiles { candidates }
+    }
+
+    /// The candidates a content entry refers to
+    /// Entries with stray whitespace around them, or missing their extension, are matched as well
+    fn candidates_for(&self, content_file: &str) -> Option<(&str, &[PathBuf])> {
+        let name = content_file.to_ascii_lowercase();
+
+        if let Some((key, candidates)) = self.candidates.get_key_value(&name) {
+            return Some((key, candidates));
+        }
+
+        let trimmed = name.trim();
+
+        std::iter::once(trimmed.to_string())
+            .chain(
+                CONTENT_EXTENSIONS
+                    .iter()
+                    .map(|extension| format!("{trimmed}.{extension}")),
+            )
+            .find_map(|name| self.candidates.get_key_value(&name))
+            .map(|(key, candidates)| (key.as_str(), candidates.as_slice()))
+    }
+
+    /// The file a content entry resolves to, if any data directory has it
+    pub fn resolve(&self, content_file: &str) -> Option<&Path> {
+        let (_, candidates) = self.candidates_for(content_file)?;
+        candidates.first().map(PathBuf::as_path)
+    }
+
+    /// A warning for each entry in `load_order` which only resolves after trimming it or adding an extension
+    pub fn fuzzy_matches(&self, load_order: &[String]) -> Vec<Warning> {
+        load_order
+            .iter()
+            .filter_map(|content_file| {
+                let (key, _) = self.candidates_for(content_file)?;
+
+                (!key.eq_ignore_ascii_case(content_file)).then(|| Warning::FuzzyMatch {
+                    content_file: content_file.to_owned(),
+                    matched: key.to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// Leaves out content entries which resolve to the same file as a later entry, eg through a symlink farm,
+    /// so that the file isn't loaded twice and listed as a master under both names
+    /// The later entry is kept, as that's where OpenMW would load the file's records from last
+    pub fn dedupe_links(&self, load_order: Vec<String>) -> Vec<String> {
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+
+        let mut deduped: Vec<String> = load_order
+            .into_iter()
+            .rev()
+            .filter(|content_file| {
+                let Some(path) = self.resolve(content_file) else {
+                    return true;
+                };
+
+                let canonical = canonical_path(path);
+                let first_seen = seen.insert(canonical.clone());
+
+                if !first_seen {
+                    log::info!(
+                        "Content entry {content_file} is the same file as a later entry, {}, so it's only loaded once",
+                        canonical.display()
+                    );
+                }
+
+                first_seen
+            })
+            .collect();
+
+        deduped.reverse();
+        deduped
+    }
+
+    /// Files matching a content entry which lost to the one it resolves to, winner excluded
+    pub fn shadowed(&self, content_file: &str) -> &[PathBuf] {
+        self.candidates_for(content_file)
+            .and_then(|(_, candidates)| candidates.get(1..))
+            .unwrap_or_default()
+    }
+}
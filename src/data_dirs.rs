@@ -0,0 +1,75 @@
+use std::{
+    fs::{canonicalize, read_to_string},
+    path::{Path, PathBuf},
+};
+
+/// Reads every `fallback-archive` line out of the given openmw.cfg files, in the order they're given
+/// Later archives override earlier ones, as in OpenMW
+pub fn fallback_archives_from_openmw_cfgs<P: AsRef<Path>>(paths: &[P]) -> Vec<String> {
+    paths
+        .iter()
+        .filter_map(|path| read_to_string(path).ok())
+        .flat_map(|contents| {
+            contents
+                .lines()
+                .filter_map(|line| {
+                    let (key, value) = line.split_once('=')?;
+                    (key.trim() == "fallback-archive").then(|| value.trim().to_owned())
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn same_directory(a: &Path, b: &Path) -> bool {
+    match (canonicalize(a), canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Puts the data directories in the order OpenMW searches them, later ones taking priority
+/// data-local always comes last, after every `data` line, and skipped directories are left out entirely
+pub fn arrange_data_dirs(
+    mut directories: Vec<PathBuf>,
+    data_local: Option<PathBuf>,
+    skipped: &[PathBuf],
+) -> Vec<PathBuf> {
+    if let Some(data_local) = data_local {
+        directories.retain(|dir| !same_directory(dir, &data_local));
+        directories.push(data_local);
+    }
+
+    // The same directory listed twice, eg once through a symlink, is only searched at its last position
+    let canonical_dirs: Vec<PathBuf> = directories
+        .iter()
+        .map(|dir| canonicalize(dir).unwrap_or_else(|_| dir.clone()))
+        .collect();
+    let mut index = 0;
+    directories.retain(|dir| {
+        let listed_later = canonical_dirs[index + 1..].contains(&canonical_dirs[index]);
+        index += 1;
+
+        if listed_later {
+            log::debug!(
+                "Data directory {} is listed again later, as {}",
+                dir.display(),
+                canonical_dirs[index - 1].display()
+            );
+        }
+
+        !listed_later
+    });
+
+    directories.retain(|dir| {
+        let skip = skipped.iter().any(|skipped| same_directory(dir, skipped));
+
+        if skip {
+            log::debug!("Skipping data directory {}", dir.display());
+        }
+
+        !skip
+    });
+
+    directories
+}
@@ -34,10 +34,51 @@ pub fn colored_radius() -> f32 {
     1.1
 }
 
+/// Previous versions of each generated plugin kept as .bak files
+pub fn backup_count() -> usize {
+    1
+}
+
+/// Author written into the header of the generated plugin
+pub fn author() -> String {
+    "S3".into()
+}
+
 pub fn duration_mult() -> f32 {
     2.5
 }
 
+pub fn exterior_radius() -> f32 {
+    1.0
+}
+
+pub fn interior_radius() -> f32 {
+    1.0
+}
+
+/// Roughly the color of a candle or torch flame
+pub fn target_kelvin() -> f32 {
+    2400.0
+}
+
+pub fn kelvin_strength() -> f32 {
+    1.0
+}
+
+/// Square root, so a light at half value keeps about 70% of its radius
+pub fn radius_curve() -> f32 {
+    0.5
+}
+
+pub fn target_color_strength() -> f32 {
+    1.0
+}
+
+/// Roughly the size of a lantern model
+pub fn mesh_reference_radius() -> f32 {
+    25.0
+}
+
 pub fn disable_flicker() -> bool {
     true
 }
@@ -54,6 +95,10 @@ pub fn auto_enable() -> bool {
     false
 }
 
+pub fn decorative_radius() -> u32 {
+    64
+}
+
 pub fn excluded_plugins() -> Vec<String> {
     vec![
         // Unable to resolve moved reference (1, 7028) for cell Sadrith Mora (18, 4)
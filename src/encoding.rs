@@ -0,0 +1,70 @@
+use std::{fs::read_to_string, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Code page used by the content files of a Morrowind edition
+/// Matches the values accepted by the `encoding` setting in openmw.cfg
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// Western European editions, including English, German, and French
+    #[default]
+    Win1252,
+    /// Central and Eastern European editions, such as Polish
+    Win1250,
+    /// Cyrillic editions, such as Russian
+    Win1251,
+}
+
+impl Encoding {
+    fn code_page(self) -> &'static encoding_rs::Encoding {
+        match self {
+            Self::Win1252 => encoding_rs::WINDOWS_1252,
+            Self::Win1250 => encoding_rs::WINDOWS_1250,
+            Self::Win1251 => encoding_rs::WINDOWS_1251,
+        }
+    }
+
+    /// Reads the last `encoding` line out of the given openmw.cfg files, in the order they're given
+    /// Unknown values are ignored, like OpenMW falls back to win1252 for them
+    pub fn from_openmw_cfgs<P: AsRef<Path>>(paths: &[P]) -> Option<Encoding> {
+        paths
+            .iter()
+            .filter_map(|path| read_to_string(path).ok())
+            .flat_map(|contents| {
+                contents
+                    .lines()
+                    .filter_map(|line| {
+                        let (key, value) = line.split_once('=')?;
+                        (key.trim() == "encoding").then(|| value.trim().to_ascii_lowercase())
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter_map(|value| match value.as_str() {
+                "win1252" => Some(Self::Win1252),
+                "win1250" => Some(Self::Win1250),
+                "win1251" => Some(Self::Win1251),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Record strings are always read as windows-1252, which maps every byte to a character,
+    /// so they can be turned back into the original bytes and decoded with the real code page
+    /// The records themselves are left alone, so they're written back out byte for byte
+    pub fn decode(self, record_string: &str) -> String {
+        if self == Self::Win1252 {
+            return record_string.to_owned();
+        }
+
+        let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode(record_string);
+        let (decoded, _, _) = self.code_page().decode(&bytes);
+
+        decoded.into_owned()
+    }
+
+    /// Lowercased, decoded form of a record ID, used to match it against user-provided patterns
+    pub fn record_id(self, record_string: &str) -> String {
+        self.decode(record_string).to_lowercase()
+    }
+}
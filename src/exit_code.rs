@@ -0,0 +1,53 @@
+/// Process exit codes, so that wrappers can tell failures apart without reading messages
+/// These values are a stable contract: add new codes rather than renumbering existing ones
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ExitCode {
+    Success = 0,
+    /// Anything not covered below, including unexpected I/O errors
+    Failure = 1,
+    /// openmw.cfg could not be found
+    ConfigNotFound = 2,
+    /// openmw.cfg has no content files
+    NoPlugins = 3,
+    /// Every plugin which should have been fixed failed to load
+    ParseFailuresOnly = 4,
+    /// The generated plugin, openmw.cfg, or another output could not be written
+    WriteFailure = 5,
+    /// openmw.cfg or lightconfig.toml could not be read, or contains invalid settings
+    InvalidConfig = 6,
+    /// The output directory does not exist or could not be determined
+    BadOutputPath = 7,
+    /// No lights or cells needed fixing, so there was nothing to write
+    NothingGenerated = 8,
+    /// The generated plugin failed validation, and was not written
+    InvalidPlugin = 9,
+    /// A plugin failed to load, and on_plugin_error is set to abort, or the prompt was declined
+    PluginLoadFailed = 10,
+    /// At least one of several profiles failed, with its own code reported alongside it
+    ProfilesFailed = 11,
+    /// The command line could not be parsed
+    BadArguments = 64,
+}
+
+impl ExitCode {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Shown at the end of --help
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0   Success
+  1   Unexpected failure
+  2   openmw.cfg could not be found
+  3   openmw.cfg has no content files
+  4   Every plugin to be fixed failed to load
+  5   An output could not be written
+  6   openmw.cfg or lightconfig.toml is unreadable or invalid
+  7   The output directory does not exist or could not be determined
+  8   Nothing needed fixing, so no plugin was written
+  9   The generated plugin failed validation, and was not written
+  10  A plugin failed to load, and on_plugin_error stopped the run
+  11  At least one of several profiles failed
+  64  The command line could not be parsed";
@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    fs::{read_to_string, write},
+    io,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::color;
+
+/// Nights in Morrowind aren't stored in plugins, but in the weather settings OpenMW reads from openmw.cfg
+/// Lines between these markers in the user openmw.cfg are written by lightfixes, and replaced on every run
+const BLOCK_START: &str = "# S3LightFixes exterior nights: begin";
+const BLOCK_END: &str = "# S3LightFixes exterior nights: end";
+
+const WEATHERS: [&str; 10] = [
+    "Clear",
+    "Cloudy",
+    "Foggy",
+    "Overcast",
+    "Rain",
+    "Thunderstorm",
+    "Ashstorm",
+    "Blight",
+    "Snow",
+    "Blizzard",
+];
+
+/// Multipliers for the brightness of exterior nights, in every weather
+/// Anything left unset is kept as the game files have it
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ExteriorNights {
+    pub ambient: Option<f32>,
+    pub sunlight: Option<f32>,
+    pub fog: Option<f32>,
+}
+
+/// Removes the lines lightfixes wrote on a previous run
+fn strip_block(contents: &str) -> String {
+    let mut in_block = false;
+    let mut stripped = String::new();
+
+    for line in contents.lines() {
+        match line.trim() {
+            BLOCK_START => in_block = true,
+            BLOCK_END => in_block = false,
+            _ if in_block => {}
+            _ => {
+                stripped.push_str(line);
+                stripped.push('\n');
+            }
+        }
+    }
+
+    stripped
+}
+
+/// Reads every `fallback=Key,r,g,b` color, with later lines taking priority like they do in OpenMW
+fn fallback_colors<'a>(contents: impl IntoIterator<Item = &'a str>) -> HashMap<String, [u8; 4]> {
+    let mut colors = HashMap::new();
+
+    for line in contents.into_iter().flat_map(str::lines) {
+        let Some(fallback) = line.trim().strip_prefix("fallback=") else {
+            continue;
+        };
+
+        let mut parts = fallback.split(',').map(str::trim);
+        let Some(key) = parts.next() else {
+            continue;
+        };
+
+        let channels: Vec<u8> = parts.filter_map(|part| part.parse().ok()).collect();
+        if let [red, green, blue] = channels[..] {
+            colors.insert(key.to_owned(), [red, green, blue, 0]);
+        }
+    }
+
+    colors
+}
+
+impl ExteriorNights {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Writes darkened or brightened night colors into the user openmw.cfg, based on the colors from
+    /// the root openmw.cfg and the rest of the user one, so running again never compounds the change
+    /// Weathers without a color in either file are skipped, returning how many colors were written
+    pub fn apply(&self, root_cfg: &Path, user_cfg: &Path) -> io::Result<usize> {
+        // The root and user openmw.cfg may be the same file
+        let root_contents = strip_block(&read_to_string(root_cfg).unwrap_or_default());
+        let user_contents = strip_block(&read_to_string(user_cfg).unwrap_or_default());
+        let colors = fallback_colors([root_contents.as_str(), user_contents.as_str()]);
+
+        let mut block = vec![BLOCK_START.to_owned()];
+
+        for weather in WEATHERS {
+            for (setting, multiplier) in [
+                ("Ambient", self.ambient),
+                ("Sunlight", self.sunlight),
+                ("Fog", self.fog),
+            ] {
+                let key = format!("Weather_{weather}_Night_{setting}_Color");
+
+                if let (Some(multiplier), Some(original)) = (multiplier, colors.get(&key)) {
+                    let [red, green, blue, _] = color::scale_hsv(*original, 1.0, 1.0, multiplier);
+                    block.push(format!("fallback={key},{red:03},{green:03},{blue:03}"));
+                }
+            }
+        }
+
+        let written = block.len() - 1;
+        block.push(BLOCK_END.to_owned());
+
+        write(user_cfg, format!("{user_contents}{}\n", block.join("\n")))?;
+
+        Ok(written)
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+use tes3::esp::{Light, ObjectFlags, Plugin};
+use vfstool_lib::VFS;
+
+use crate::{Encoding, MasterStrategy, MergeStrategy, Warning, is_fixable_plugin, merge_lights};
+
+/// Masters shipped with the game, which lights can be attributed to under `MasterStrategy::Minimal`
+pub const BASE_GAME_MASTERS: [&str; 3] = ["Morrowind.esm", "Tribunal.esm", "Bloodmoon.esm"];
+
+/// Whether the plugin at `path` is one of the base game masters
+pub fn is_base_game_master(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| {
+        BASE_GAME_MASTERS
+            .iter()
+            .any(|master| name.eq_ignore_ascii_case(master))
+    })
+}
+
+/// Record IDs which have already been handled, compared case-insensitively like the engine does
+/// IDs should be decoded first, eg with `Encoding::record_id`
+#[derive(Clone, Debug, Default)]
+pub struct UsedIds(HashSet<String>);
+
+impl UsedIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns false if the ID was already used
+    pub fn insert(&mut self, record_id: &str) -> bool {
+        self.0.insert(record_id.to_lowercase())
+    }
+
+    pub fn contains(&self, record_id: &str) -> bool {
+        self.0.contains(&record_id.to_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for UsedIds {
+    fn from_iter<I: IntoIterator<Item = S>>(record_ids: I) -> Self {
+        let mut used_ids = Self::new();
+
+        for record_id in record_ids {
+            used_ids.insert(record_id.as_ref());
+        }
+
+        used_ids
+    }
+}
+
+/// The version of each light which wins in a load order, along with the plugins they came from
+/// This is everything lightfixes knows about a load order before changing any lights,
+/// and doesn't depend on any of its color settings
+#[derive(Debug, Default)]
+pub struct LightSet {
+    /// Plugins which were added, in reverse load order
+    pub plugins: Vec<PathBuf>,
+    /// The winning version of each light, with the index in `plugins` of the plugin it belongs to
+    pub lights: Vec<(usize, Light)>,
+    /// Lights whose winning version deletes them, with the index in `plugins` of the plugin deleting them
+    /// Earlier versions of these are never collected, so they aren't brought back by the generated plugin
+    pub deleted: Vec<(usize, Light)>,
+    /// Problems met while collecting, such as plugins which couldn't be read
+    pub warnings: Vec<Warning>,
+
+    encoding: Encoding,
+    merge_strategy: MergeStrategy,
+    master_strategy: MasterStrategy,
+    /// Lights which are never collected, no matter which plugin defines them
+    used_ids: UsedIds,
+    winners: HashMap<String, usize>,
+    deleted_ids: HashSet<String>,
+    /// Earlier versions of each winning light, latest first, only collected when merging
+    earlier_versions: HashMap<usize, Vec<Light>>,
+}
+
+impl LightSet {
+    pub fn new(encoding: Encoding, merge_strategy: MergeStrategy) -> Self {
+        Self {
+            encoding,
+            merge_strategy,
+            ..Default::default()
+        }
+    }
+
+    /// Pre-seeds the IDs of lights to leave out, eg because an embedder handles them itself
+    pub fn with_used_ids(mut self, used_ids: UsedIds) -> Self {
+        self.used_ids = used_ids;
+        self
+    }
+
+    /// Attributes lights to base game masters where possible, with `MasterStrategy::Minimal`
+    pub fn with_master_strategy(mut self, master_strategy: MasterStrategy) -> Self {
+        self.master_strategy = master_strategy;
+        self
+    }
+
+    /// Adds the lights of one plugin, returning its index in `plugins`
+    /// Plugins must be added in reverse load order, so that the first version of each light seen is the one which wins
+    /// Lights which were pre-seeded as used, or whose lowercased ID fails `is_wanted`, are left out entirely
+    pub fn add_plugin(
+        &mut self,
+        path: PathBuf,
+        lights: impl IntoIterator<Item = Light>,
+        is_wanted: impl Fn(&str) -> bool,
+    ) -> usize {
+        let plugin_index = self.plugins.len();
+        let rebase_onto =
+            self.master_strategy == MasterStrategy::Minimal && is_base_game_master(&path);
+        self.plugins.push(path);
+
+        for light in lights {
+            let light_id = self.encoding.record_id(&light.id);
+
+            if self.deleted_ids.contains(&light_id) {
+                continue;
+            }
+
+            if let Some(&winner) = self.winners.get(&light_id) {
+                // Compatibility patches often carry byte-identical copies of a light.
+                // Attribute the record to the earliest plugin defining it so the copy
+                // isn't dragged into the master list for no reason.
+                let (owner, winning_light) = &mut self.lights[winner];
+                if *winning_light == light {
+                    *owner = plugin_index;
+                } else if rebase_onto
+                    && winning_light.data == light.data
+                    && !light.flags.contains(ObjectFlags::DELETED)
+                {
+                    // Everything lightfixes writes comes out the same as the base game's version would,
+                    // so only the master holding that version is needed
+                    *owner = plugin_index;
+                }
+
+                // A deleted version has nothing worth merging
+                if self.merge_strategy == MergeStrategy::Merge
+                    && !light.flags.contains(ObjectFlags::DELETED)
+                {
+                    self.earlier_versions.entry(winner).or_default().push(light);
+                }
+            } else if light.flags.contains(ObjectFlags::DELETED) {
+                self.deleted_ids.insert(light_id);
+                self.deleted.push((plugin_index, light));
+            } else if !self.used_ids.contains(&light_id) && is_wanted(&light_id) {
+                self.winners.insert(light_id, self.lights.len());
+                self.lights.push((plugin_index, light));
+            }
+        }
+
+        plugin_index
+    }
+
+    /// Leaves out every winning light with a script attached, returning how many there were
+    /// Call after `finish`, as merging can take the script from any version of a light
+    pub fn remove_scripted(&mut self) -> usize {
+        let before = self.lights.len();
+        self.lights.retain(|(_, light)| light.script.is_empty());
+
+        let encoding = self.encoding;
+        self.winners = self
+            .lights
+            .iter()
+            .enumerate()
+            .map(|(index, (_, light))| (encoding.record_id(&light.id), index))
+            .collect();
+
+        before - self.lights.len()
+    }
+
+    /// Combines the versions of each light when merging, once every plugin has been added
+    pub fn finish(&mut self) {
+        for (winner, mut versions) in self.earlier_versions.drain() {
+            let (_, winning_light) = &mut self.lights[winner];
+
+            versions.reverse();
+            versions.push(winning_light.clone());
+
+            if let Some(merged) = merge_lights(&versions) {
+                *winning_light = merged;
+            }
+        }
+    }
+}
+
+/// Loads the lights of every plugin in `load_order` found in the VFS, and keeps the winning version of each
+/// Plugins which can't be read are left out, with a warning for each in `warnings`
+pub fn collect_lights(vfs: &VFS, load_order: &[String]) -> LightSet {
+    let plugins: Vec<(PathBuf, Result<Plugin, Warning>)> = load_order
+        .par_iter()
+        .rev()
+        .filter_map(|plugin| {
+            let path = vfs.get_file(plugin)?.path().to_path_buf();
+
+            if !is_fixable_plugin(&path) {
+                return None;
+            }
+
+            let plugin = Plugin::from_path_filtered(&path, |tag| matches!(&tag, Light::TAG))
+                .map_err(|err| Warning::PluginSkipped {
+                    path: path.clone(),
+                    reason: err.to_string(),
+                });
+            Some((path, plugin))
+        })
+        .collect();
+
+    let mut light_set = LightSet::default();
+
+    for (path, plugin) in plugins {
+        match plugin {
+            Ok(plugin) => {
+                light_set.add_plugin(path, plugin.into_objects_of_type::<Light>(), |_| true);
+            }
+            Err(warning) => light_set.warnings.push(warning),
+        }
+    }
+
+    light_set.finish();
+    light_set
+}
@@ -0,0 +1,245 @@
+use std::{
+    fs::{File, read},
+    io::Read,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tes3::esp::{FixedString, Plugin, TES3Object};
+
+use crate::LightConfig;
+
+/// Optional sidecar written next to the generated plugin, holding the same details as its description
+pub const METADATA_NAME: &str = "S3LightFixes.meta.json";
+
+/// Start of the header description of every plugin lightfixes writes, so that its output can be recognized under any name
+pub const GENERATED_MARKER: &str = "Plugin generated by s3-lightfixes";
+
+const GENERATED_AT_PREFIX: &str = "Generated at: ";
+
+/// Header version of the generated plugin, which is what Tribunal and Bloodmoon save and every OpenMW release expects
+pub const HEADER_VERSION: f32 = 1.3;
+
+/// Longest author a plugin header holds, in bytes
+const AUTHOR_BYTES: usize = 32;
+
+/// Longest description a plugin header holds, in bytes
+const DESCRIPTION_BYTES: usize = 256;
+
+/// The start of `text` which fits in `max_bytes`, without splitting a character
+fn truncate_to_bytes(text: &str, max_bytes: usize) -> &str {
+    let mut end = text.len().min(max_bytes);
+
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    &text[..end]
+}
+
+/// The configured author, cut short to fit in the plugin header
+pub fn header_author(author: &str) -> String {
+    truncate_to_bytes(author, AUTHOR_BYTES).to_string()
+}
+
+/// Puts a free-text description ahead of the details lightfixes generates, cutting it short so that the details always fit
+/// The details start with GENERATED_MARKER, which must survive for the plugin to be recognized
+pub fn header_description(description: Option<&str>, details: String) -> String {
+    match description.filter(|description| !description.is_empty()) {
+        Some(description) => {
+            let room = DESCRIPTION_BYTES.saturating_sub(details.len() + 2);
+            format!("{}\r\n{details}", truncate_to_bytes(description, room))
+        }
+        None => details,
+    }
+}
+
+/// The header record, including its full description, always fits within this many bytes at the start of a plugin
+const HEADER_PEEK_BYTES: u64 = 512;
+
+/// Details of how a plugin was generated, so that the settings behind a patch found in a load order can be traced
+#[derive(Debug, Serialize)]
+pub struct GenerationMetadata {
+    pub version: &'static str,
+    /// Seconds since the unix epoch
+    pub generated_at: u64,
+    /// FNV-1a hash of the full config used, including overrides and exclusions, with every table in sorted order
+    pub config_hash: String,
+    pub masters: usize,
+    /// Free text from the description setting, kept ahead of these details in the header
+    #[serde(skip)]
+    pub description: Option<String>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Writes a TOML value with the keys of every table sorted, so that HashMap fields come out the same in every process
+fn write_canonical(value: &toml::Value, out: &mut String) {
+    match value {
+        toml::Value::Table(table) => {
+            let mut keys: Vec<&String> = table.keys().collect();
+            keys.sort();
+
+            out.push('{');
+            for key in keys {
+                out.push_str(&format!("{key:?}="));
+                write_canonical(&table[key], out);
+                out.push(',');
+            }
+            out.push('}');
+        }
+        toml::Value::Array(values) => {
+            out.push('[');
+            for value in values {
+                write_canonical(value, out);
+                out.push(',');
+            }
+            out.push(']');
+        }
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+fn config_hash(light_config: &LightConfig) -> String {
+    let mut serialized = String::new();
+
+    if let Ok(value) = toml::Value::try_from(light_config) {
+        write_canonical(&value, &mut serialized);
+    }
+
+    // Overrides and exclusions only remain as compiled patterns by now, which aren't serialized with the rest
+    for (pattern, light_data) in &light_config.light_regexes {
+        serialized.push_str(&format!(
+            "\nlight_overrides {} {light_data:?}",
+            pattern.as_str()
+        ));
+    }
+
+    for (pattern, ambient_data) in &light_config.ambient_regexes {
+        serialized.push_str(&format!(
+            "\nambient_overrides {} {ambient_data:?}",
+            pattern.as_str()
+        ));
+    }
+
+    for (name, patterns) in [
+        ("excluded_ids", &light_config.excluded_id_regexes),
+        ("excluded_plugins", &light_config.excluded_plugin_regexes),
+        ("decorative_ids", &light_config.decorative_id_regexes),
+    ] {
+        for pattern in patterns {
+            serialized.push_str(&format!("\n{name} {}", pattern.as_str()));
+        }
+    }
+
+    let hash = serialized
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+
+    format!("{hash:016x}")
+}
+
+/// Whether the header of the plugin at `path` carries the marker lightfixes stamps into its output,
+/// such as a previous output which was renamed and left in the load order
+/// Only the start of the file is read, so this is cheap enough to check for every plugin
+pub fn is_generated_plugin(path: &Path) -> bool {
+    let mut header = Vec::new();
+
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+
+    if file
+        .take(HEADER_PEEK_BYTES)
+        .read_to_end(&mut header)
+        .is_err()
+    {
+        return false;
+    }
+
+    header
+        .windows(GENERATED_MARKER.len())
+        .any(|window| window == GENERATED_MARKER.as_bytes())
+}
+
+impl GenerationMetadata {
+    pub fn new(light_config: &LightConfig, masters: usize) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            generated_at: now(),
+            config_hash: config_hash(light_config),
+            masters,
+            description: light_config.description.clone(),
+        }
+    }
+
+    /// Text for the header description, which holds at most 256 bytes
+    pub fn description(&self) -> String {
+        header_description(
+            self.description.as_deref(),
+            format!(
+                "{GENERATED_MARKER} {}\r\n{GENERATED_AT_PREFIX}{}\r\nConfig hash: {}\r\nMasters: {}",
+                self.version, self.generated_at, self.config_hash, self.masters
+            ),
+        )
+    }
+
+    /// Writes the description into the header of `plugin`
+    pub fn stamp(&self, plugin: &mut Plugin) {
+        for object in &mut plugin.objects {
+            if let TES3Object::Header(header) = object {
+                header.description = FixedString(self.description());
+            }
+        }
+    }
+
+    /// Stamps `plugin`, keeping the timestamp of the plugin at `previous_path` if that is the only difference,
+    /// so that regenerating an identical plugin doesn't change the file
+    /// `as_written` converts `plugin` to the form saved at `previous_path`
+    pub fn stamp_unless_unchanged(
+        &mut self,
+        plugin: &mut Plugin,
+        previous_path: &Path,
+        as_written: impl Fn(&Plugin) -> Plugin,
+    ) {
+        let previous_generated_at = read(previous_path).ok().and_then(|bytes| {
+            let mut previous = Plugin::new();
+            previous.load_bytes(&bytes).ok()?;
+
+            let generated_at = previous.objects.iter().find_map(|object| match object {
+                TES3Object::Header(header) => header
+                    .description
+                    .0
+                    .lines()
+                    .find_map(|line| line.strip_prefix(GENERATED_AT_PREFIX)?.parse().ok()),
+                _ => None,
+            })?;
+
+            Some((generated_at, bytes))
+        });
+
+        if let Some((generated_at, previous_bytes)) = previous_generated_at {
+            let current_generated_at = self.generated_at;
+            self.generated_at = generated_at;
+            self.stamp(plugin);
+
+            if as_written(plugin)
+                .save_bytes()
+                .is_ok_and(|bytes| bytes == previous_bytes)
+            {
+                return;
+            }
+
+            self.generated_at = current_generated_at;
+        }
+
+        self.stamp(plugin);
+    }
+}
@@ -0,0 +1,84 @@
+use std::{collections::BTreeMap, fs::read_to_string, io, path::Path};
+
+use tes3::esp::{Light, Plugin, TES3Object};
+
+use crate::{GENERATED_MARKER, METADATA_NAME, color, to_io_error};
+
+/// How many lights `inspect` prints values for
+const LIGHT_SAMPLE_SIZE: usize = 10;
+
+/// Describes a plugin without reading any load order: its masters, how many records of each type it holds,
+/// how it was generated, and the values of a few of its lights
+/// Meant for support, where the generated plugin is often the only file a user sends
+pub fn inspect_plugin(path: &Path) -> io::Result<String> {
+    let plugin = Plugin::from_path(path).map_err(to_io_error)?;
+    let mut lines = vec![path.display().to_string()];
+
+    if let Some(header) = plugin.objects.iter().find_map(|object| match object {
+        TES3Object::Header(header) => Some(header),
+        _ => None,
+    }) {
+        lines.push(format!("\nAuthor: {}", header.author.0));
+
+        if header.description.0.contains(GENERATED_MARKER) {
+            lines.push("Description:".to_string());
+            lines.extend(header.description.0.lines().map(|line| format!("  {line}")));
+        } else {
+            lines.push("No generation details in the header, so this plugin wasn't written by S3LightFixes, or its header was edited since".to_string());
+        }
+
+        lines.push(format!("\nMasters ({}):", header.masters.len()));
+        lines.extend(
+            header
+                .masters
+                .iter()
+                .map(|(name, size)| format!("  {name} ({size} bytes)")),
+        );
+    }
+
+    // Only present if it was written alongside the plugin, but holds the same details in a readable form
+    if let Some(metadata) = path
+        .parent()
+        .and_then(|dir| read_to_string(dir.join(METADATA_NAME)).ok())
+    {
+        lines.push(format!("\n{METADATA_NAME}:\n{}", metadata.trim_end()));
+    }
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for object in &plugin.objects {
+        if !matches!(object, TES3Object::Header(_)) {
+            *counts.entry(object.tag_str()).or_default() += 1;
+        }
+    }
+
+    lines.push("\nRecords:".to_string());
+    lines.extend(
+        counts
+            .iter()
+            .map(|(tag, count)| format!("  {tag}: {count}")),
+    );
+
+    let mut lights: Vec<&Light> = plugin.objects_of_type::<Light>().collect();
+    lights.sort_by_key(|light| light.id.to_ascii_lowercase());
+
+    if !lights.is_empty() {
+        lines.push(format!(
+            "\nFirst {} of {} lights:",
+            lights.len().min(LIGHT_SAMPLE_SIZE),
+            lights.len()
+        ));
+    }
+
+    lines.extend(lights.iter().take(LIGHT_SAMPLE_SIZE).map(|light| {
+        format!(
+            "  {}: color {}, radius {}, duration {}, flags {:?}",
+            light.id,
+            color::to_hex(light.data.color),
+            light.data.radius,
+            light.data.time,
+            light.data.flags,
+        )
+    }));
+
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,155 @@
+use std::{
+    fs::{read_to_string, write},
+    io::{self, BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+/// Expansions shipped alongside Morrowind.esm, in the order they load
+const EXPANSIONS: [&str; 2] = ["Tribunal.esm", "Bloodmoon.esm"];
+
+/// Where the stores put Morrowind, relative to the root of a drive
+#[cfg(windows)]
+const DRIVE_INSTALL_DIRS: [&str; 6] = [
+    "Program Files (x86)\\Steam\\steamapps\\common\\Morrowind",
+    "SteamLibrary\\steamapps\\common\\Morrowind",
+    "GOG Games\\Morrowind",
+    "Program Files (x86)\\GOG Galaxy\\Games\\Morrowind",
+    "XboxGames\\The Elder Scrolls III- Morrowind\\Content",
+    "Program Files (x86)\\Bethesda Softworks\\Morrowind",
+];
+
+/// Where the stores put Morrowind, relative to the home directory
+/// Only Steam (through Proton) and GOG (through Wine or Heroic) are found outside Windows
+#[cfg(not(windows))]
+const HOME_INSTALL_DIRS: [&str; 6] = [
+    ".steam/steam/steamapps/common/Morrowind",
+    ".local/share/Steam/steamapps/common/Morrowind",
+    ".var/app/com.valvesoftware.Steam/.local/share/Steam/steamapps/common/Morrowind",
+    "Library/Application Support/Steam/steamapps/common/Morrowind",
+    "GOG Games/Morrowind",
+    "Games/Heroic/Morrowind",
+];
+
+/// Extra Steam libraries listed in libraryfolders.vdf, which is where Steam records every drive it installs to
+fn steam_libraries(steam_dir: &Path) -> Vec<PathBuf> {
+    let Ok(contents) = read_to_string(steam_dir.join("steamapps").join("libraryfolders.vdf"))
+    else {
+        return Vec::new();
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('"').filter(|field| !field.trim().is_empty());
+            (fields.next()? == "path").then(|| PathBuf::from(fields.next()?.replace("\\\\", "\\")))
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+fn install_candidates() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = ('C'..='Z')
+        .map(|drive| PathBuf::from(format!("{drive}:\\")))
+        .filter(|drive| drive.is_dir())
+        .flat_map(|drive| DRIVE_INSTALL_DIRS.iter().map(move |dir| drive.join(dir)))
+        .collect();
+
+    candidates.extend(
+        steam_libraries(Path::new("C:\\Program Files (x86)\\Steam"))
+            .into_iter()
+            .map(|library| library.join("steamapps").join("common").join("Morrowind")),
+    );
+
+    candidates
+}
+
+#[cfg(not(windows))]
+fn install_candidates() -> Vec<PathBuf> {
+    let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<PathBuf> = HOME_INSTALL_DIRS.iter().map(|dir| home.join(dir)).collect();
+
+    for steam_dir in [".steam/steam", ".local/share/Steam"] {
+        candidates.extend(
+            steam_libraries(&home.join(steam_dir))
+                .into_iter()
+                .map(|library| library.join("steamapps").join("common").join("Morrowind")),
+        );
+    }
+
+    candidates
+}
+
+/// The Data Files directory of the first Morrowind install found in the usual Steam, GOG, or Game Pass locations
+pub fn find_morrowind_data_files() -> Option<PathBuf> {
+    install_candidates()
+        .into_iter()
+        .map(|install| install.join("Data Files"))
+        .find(|data_files| data_files.join("Morrowind.esm").is_file())
+}
+
+/// Quotes a path the way openmw.cfg expects, where & escapes both quotes and itself
+fn quote_cfg_path(path: &Path) -> String {
+    format!(
+        "\"{}\"",
+        path.to_string_lossy()
+            .replace('&', "&&")
+            .replace('"', "&\"")
+    )
+}
+
+/// Writes an openmw.cfg loading the base game and whichever expansions are present in `data_files`
+/// Only the lines lightfixes reads are written, so OpenMW itself still needs its own setup
+pub fn write_minimal_cfg(cfg_path: &Path, data_files: &Path) -> io::Result<()> {
+    let mut lines = vec![
+        "# Generated by S3LightFixes, as no openmw.cfg could be found".to_string(),
+        format!("data={}", quote_cfg_path(data_files)),
+        "content=Morrowind.esm".to_string(),
+        "fallback-archive=Morrowind.bsa".to_string(),
+    ];
+
+    for expansion in EXPANSIONS {
+        if data_files.join(expansion).is_file() {
+            lines.push(format!("content={expansion}"));
+            lines.push(format!(
+                "fallback-archive={}",
+                expansion.replace(".esm", ".bsa")
+            ));
+        }
+    }
+
+    write(cfg_path, lines.join("\n") + "\n")
+}
+
+/// Looks for a Morrowind install when there's no openmw.cfg, and offers to write a minimal one for it
+/// into the working directory, where later runs will find it too
+/// Only offered on an interactive terminal, as there's nobody to answer otherwise
+pub fn offer_minimal_cfg() -> io::Result<Option<PathBuf>> {
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+
+    let Some(data_files) = find_morrowind_data_files() else {
+        return Ok(None);
+    };
+
+    print!(
+        "Couldn't find openmw.cfg, but Morrowind is installed at {}. Generate a minimal openmw.cfg for it? [Y/n]: ",
+        data_files.display()
+    );
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().lock().read_line(&mut input)?;
+
+    if !matches!(input.trim().to_ascii_lowercase().as_str(), "" | "y" | "yes") {
+        return Ok(None);
+    }
+
+    let cfg_path = std::env::current_dir()?.join("openmw.cfg");
+    write_minimal_cfg(&cfg_path, &data_files)?;
+
+    Ok(Some(cfg_path))
+}
@@ -0,0 +1,135 @@
+use std::{collections::HashMap, fs::File, io, path::Path};
+
+use palette::{FromColor, Hsv, rgb::Srgb};
+use serde::Deserialize;
+use tes3::esp::{Light, Plugin};
+
+use crate::{
+    CustomLightData, color, light_override::LightFlag, light_table::LightRow, to_io_error,
+};
+
+/// Output of other light patchers which can be turned into light overrides
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ForeignFormat {
+    /// A plugin generated by Waza_lightfixes, or any other plugin whose lights should be kept as they are
+    Plugin,
+    /// The lightfixes.json used by Modding-OpenMW.com lists
+    MomwJson,
+}
+
+impl ForeignFormat {
+    /// Guesses the format from the file extension, returning None for anything unrecognized
+    pub fn from_path(path: &Path) -> Option<ForeignFormat> {
+        let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+
+        match extension.as_str() {
+            "esp" | "esm" | "omwaddon" => Some(Self::Plugin),
+            "json" => Some(Self::MomwJson),
+            _ => None,
+        }
+    }
+}
+
+/// A color written either as a hex string or as an array of channels
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ForeignColor {
+    Hex(String),
+    Channels([u8; 3]),
+}
+
+/// One entry of a lightfixes.json, keyed by the ID of the light it changes
+/// Any field left out is kept as the load order has it
+#[derive(Debug, Deserialize)]
+struct MomwLight {
+    color: Option<ForeignColor>,
+    radius: Option<u32>,
+    #[serde(alias = "time")]
+    duration: Option<f32>,
+    flag: Option<LightFlag>,
+}
+
+/// lightfixes.json either lists lights at the top level or under a `lights` key
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MomwFile {
+    Nested { lights: HashMap<String, MomwLight> },
+    Flat(HashMap<String, MomwLight>),
+}
+
+fn exact_pattern(id: &str) -> String {
+    format!("^{}$", regex::escape(id))
+}
+
+impl MomwLight {
+    fn to_override(&self, id: &str) -> io::Result<(String, CustomLightData)> {
+        let rgb = match &self.color {
+            Some(ForeignColor::Hex(hex)) => Some(color::from_hex(hex).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid color `{hex}` for light {id}"),
+                )
+            })?),
+            Some(ForeignColor::Channels(channels)) => Some(*channels),
+            None => None,
+        };
+
+        let mut light_data = CustomLightData {
+            radius: self.radius,
+            duration: self.duration,
+            flag: self.flag.to_owned(),
+            ..Default::default()
+        };
+
+        if let Some(rgb) = rgb {
+            let [red, green, blue] = rgb;
+            let hsv: Hsv = Hsv::from_color(Srgb::new(red, green, blue).into_format::<f32>());
+
+            light_data.hue = Some(hsv.hue.into_positive_degrees().round() as u32);
+            light_data.saturation = Some(hsv.saturation);
+            light_data.value = Some(hsv.value);
+        }
+
+        Ok((exact_pattern(id), light_data))
+    }
+}
+
+/// Every light in the plugin becomes an override pinning it to its color, radius, duration, and flag there
+fn import_plugin(path: &Path) -> io::Result<HashMap<String, CustomLightData>> {
+    let plugin = Plugin::from_path_filtered(path, |tag| matches!(&tag, Light::TAG))?;
+    let plugin_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    plugin
+        .into_objects_of_type::<Light>()
+        .map(|light| LightRow::new(&plugin_name, &light, &light).to_override())
+        .collect()
+}
+
+fn import_momw_json(path: &Path) -> io::Result<HashMap<String, CustomLightData>> {
+    let lights = match serde_json::from_reader(File::open(path)?).map_err(to_io_error)? {
+        MomwFile::Nested { lights } | MomwFile::Flat(lights) => lights,
+    };
+
+    lights
+        .iter()
+        .map(|(id, light)| light.to_override(id))
+        .collect()
+}
+
+/// Reads the output of another light patcher and converts it into light overrides
+pub fn import_foreign_overrides(path: &Path) -> io::Result<HashMap<String, CustomLightData>> {
+    match ForeignFormat::from_path(path) {
+        Some(ForeignFormat::Plugin) => import_plugin(path),
+        Some(ForeignFormat::MomwJson) => import_momw_json(path),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Don't know how to import overrides from {}. Expected a plugin or a lightfixes.json",
+                path.display()
+            ),
+        )),
+    }
+}
@@ -0,0 +1,79 @@
+use std::{collections::HashMap, io, path::Path};
+
+use tes3::esp::{Creature, LeveledItem, Npc, Plugin, TES3Object};
+
+use crate::{Encoding, extract::UsedIds};
+
+/// Loads only the records which hand out items, for swap_inventory_lights
+pub fn load_inventory_records(path: &Path) -> io::Result<Plugin> {
+    Plugin::from_path_filtered(path, |tag| {
+        matches!(&tag, LeveledItem::TAG | Npc::TAG | Creature::TAG)
+    })
+}
+
+/// Replaces `item_id` if it's one of the swapped lights, returning whether it was
+fn swap_item(item_id: &mut String, swaps: &HashMap<String, String>, encoding: Encoding) -> bool {
+    match swaps.get(&encoding.record_id(item_id)) {
+        Some(replacement) => {
+            *item_id = replacement.to_owned();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Swaps lights in the leveled lists, NPCs, and creatures of one plugin, using lowercased IDs as keys of `swaps`
+/// Plugins must be given in reverse load order, so only the winning version of each record is looked at
+/// Records which hand out any swapped light are returned, and every record seen is added to `used_ids`
+pub fn swap_inventory_lights(
+    plugin: Plugin,
+    swaps: &HashMap<String, String>,
+    encoding: Encoding,
+    used_ids: &mut UsedIds,
+) -> Vec<TES3Object> {
+    let mut swapped = Vec::new();
+
+    for object in plugin.objects {
+        let (record_id, changed, object) = match object {
+            TES3Object::LeveledItem(mut leveled_item) => {
+                let changed = leveled_item
+                    .items
+                    .iter_mut()
+                    .fold(false, |changed, (item_id, _)| {
+                        swap_item(item_id, swaps, encoding) | changed
+                    });
+
+                (leveled_item.id.clone(), changed, leveled_item.into())
+            }
+            TES3Object::Npc(mut npc) => {
+                let changed = npc
+                    .inventory
+                    .iter_mut()
+                    .fold(false, |changed, (_, item_id)| {
+                        swap_item(&mut item_id.0, swaps, encoding) | changed
+                    });
+
+                (npc.id.clone(), changed, npc.into())
+            }
+            TES3Object::Creature(mut creature) => {
+                let changed = creature
+                    .inventory
+                    .iter_mut()
+                    .fold(false, |changed, (_, item_id)| {
+                        swap_item(&mut item_id.0, swaps, encoding) | changed
+                    });
+
+                (creature.id.clone(), changed, creature.into())
+            }
+            _ => continue,
+        };
+
+        let record_id = encoding.record_id(&record_id);
+
+        if used_ids.insert(&record_id) && changed {
+            swapped.push(object);
+        }
+    }
+
+    swapped
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs::{File, metadata, read_to_string},
+    io::{self, Read},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Extra entries for the known plugin table, read from next to lightconfig.toml
+pub const KNOWN_PLUGINS_NAME: &str = "known_plugins.toml";
+
+const OPENMW_GAME_FILES_URL: &str =
+    "https://openmw.readthedocs.io/en/latest/manuals/installation/install-game-files.html";
+
+/// Sizes in bytes of the English 1.6.1820 masters, as shipped in the Game of the Year edition on disc, Steam, and GOG
+/// These are known to load, so a master of any other size which fails to is most likely a localized release
+const ENGLISH_MASTER_SIZES: [(&str, u64); 3] = [
+    ("Morrowind.esm", 79_837_557),
+    ("Tribunal.esm", 4_565_686),
+    ("Bloodmoon.esm", 9_631_798),
+];
+
+/// A plugin which is known not to load, and what to do about it
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KnownPlugin {
+    /// File name of the plugin, compared case-insensitively
+    pub name: String,
+    /// FNV-1a hash of the whole file, as printed when a plugin fails to load
+    /// Entries without a hash match every version of the plugin
+    pub hash: Option<String>,
+    /// Sizes in bytes of releases which are known to load, which the entry never matches
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub known_good_sizes: Vec<u64>,
+    pub explanation: String,
+    pub link: Option<String>,
+}
+
+/// Plugins which are known not to load, so their failures can be explained
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct KnownPlugins {
+    #[serde(default)]
+    pub plugins: Vec<KnownPlugin>,
+}
+
+fn localized_master((name, english_size): (&str, u64)) -> KnownPlugin {
+    KnownPlugin {
+        name: name.to_owned(),
+        hash: None,
+        known_good_sizes: vec![english_size],
+        explanation: "Some localized releases of the Morrowind masters, such as the German Game of the Year edition, store records in a layout which can't be read. Lights from this master won't be fixed. Reinstalling the game files from an English release avoids this.".to_owned(),
+        link: Some(OPENMW_GAME_FILES_URL.to_owned()),
+    }
+}
+
+/// FNV-1a hash of a whole file, as hex
+pub fn plugin_hash(path: &Path) -> io::Result<String> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    Ok(format!("{hash:016x}"))
+}
+
+impl KnownPlugins {
+    /// The built-in table, followed by any entries in known_plugins.toml in `config_dir`
+    /// Entries from the file are checked first, so they can replace built-in explanations
+    /// A missing or unreadable file only leaves the built-in table
+    pub fn load(config_dir: &Path) -> KnownPlugins {
+        let mut known_plugins: KnownPlugins = read_to_string(config_dir.join(KNOWN_PLUGINS_NAME))
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        known_plugins
+            .plugins
+            .extend(ENGLISH_MASTER_SIZES.into_iter().map(localized_master));
+
+        known_plugins
+    }
+
+    /// Finds the entry explaining why the plugin at `path` failed to load, if any
+    /// The file is only hashed if an entry with a matching name needs it
+    pub fn find(&self, path: &Path) -> Option<&KnownPlugin> {
+        let name = path.file_name()?.to_string_lossy();
+        let size = metadata(path).ok().map(|metadata| metadata.len());
+        let mut hash: Option<Option<String>> = None;
+
+        self.plugins.iter().find(|known| {
+            if !known.name.eq_ignore_ascii_case(&name) {
+                return false;
+            }
+
+            if size.is_some_and(|size| known.known_good_sizes.contains(&size)) {
+                return false;
+            }
+
+            match &known.hash {
+                None => true,
+                Some(known_hash) => hash
+                    .get_or_insert_with(|| plugin_hash(path).ok())
+                    .as_ref()
+                    .is_some_and(|hash| hash.eq_ignore_ascii_case(known_hash)),
+            }
+        })
+    }
+}
@@ -1,62 +1,359 @@
 use std::{
-    env::current_dir,
-    fs::{create_dir_all, metadata},
-    io,
+    collections::HashSet,
+    env::{current_dir, current_exe, var_os},
+    fs::{OpenOptions, create_dir_all, metadata, read, read_to_string, write},
+    io::{self, BufRead, Write},
     path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 pub use openmw_config::OpenMWConfiguration;
 pub use tes3::esp::Plugin;
+use tes3::esp::{Cell, CellFlags, EditorId, Light, ObjectFlags, TES3Object, types::FileType};
+
+pub mod archives;
+
+pub mod backup;
+
+mod classify;
+pub use classify::{CLASSES_NAME, LightClass, LightClasses, classify};
+
+pub mod cleanup;
+
+pub mod change_log;
+
+pub mod color;
+
+mod config_check;
+pub use config_check::{LARGE_MULTIPLIER, unknown_settings};
+
+mod content_files;
+pub use content_files::{ContentFiles, canonical_path};
+
+mod data_dirs;
+pub use data_dirs::{arrange_data_dirs, fallback_archives_from_openmw_cfgs};
 
 pub mod default;
 
+pub mod encoding;
+pub use encoding::Encoding;
+
+pub mod exit_code;
+pub use exit_code::{EXIT_CODES_HELP, ExitCode};
+
+pub mod extract;
+
+mod generation;
+pub use generation::{
+    GENERATED_MARKER, GenerationMetadata, HEADER_VERSION, METADATA_NAME, header_author,
+    header_description, is_generated_plugin,
+};
+
+mod exterior_nights;
+pub use exterior_nights::ExteriorNights;
+
+pub mod install_scan;
+
+mod inspect;
+pub use inspect::inspect_plugin;
+
+pub mod interop;
+
+pub mod inventory;
+
+mod known_plugins;
+pub use known_plugins::{KNOWN_PLUGINS_NAME, KnownPlugin, KnownPlugins, plugin_hash};
+
+pub mod logging;
+
 pub mod light_args;
-pub use light_args::LightArgs;
+pub use light_args::{LightArgs, LightCommand};
 
 mod light_config;
-pub use light_config::LightConfig;
+pub use light_config::{
+    CellRecordContents, ColorRounding, ColorSpace, ColoredBuckets, ColoredMultipliers, InteriorFog,
+    LightConfig, LogFormat, MasterStrategy, MixedPlacementRadius, OutputFormat, OverridePack,
+    PluginErrorPolicy, QuasiExterior, RadiusMode, RecordCategories, SplitOutput, TargetEngine,
+    TransformMode,
+};
+
+pub mod light_table;
+
+pub mod lua_package;
+pub use lua_package::{LUA_PACKAGE_NAME, write_lua_package};
+
+pub mod light_override;
+pub use light_override::{
+    CarriedLightFlags, Carryable, CustomCellAmbient, CustomLightData, FlagPolicy, TypedLightColor,
+};
+
+mod merge;
+pub use merge::{MergeStrategy, merge_lights};
+
+pub mod mesh;
+
+pub mod mo2;
+
+pub mod messages;
+pub use messages::{Language, Message, language, set_language};
+
+pub mod notify;
+pub use notify::{
+    Notifier, NotifyBackend, notify_backend, reserve_stdout, set_notify_backend, stdout_reserved,
+};
+
+mod preserve;
+pub use preserve::{CACHE_NAME, preserve_manual_edits};
+
+mod preset;
+pub use preset::{BUILTIN_PRESETS, Preset};
+
+mod regions;
+pub use regions::{ExtraMultipliers, LightPlacements, LightRegions, Placement};
+
+pub mod openmw_settings;
+
+mod profiles;
+pub use profiles::{read_profiles, strip_profile_args};
+
+pub mod shaders;
+
+mod skip_list;
+pub use skip_list::{SKIP_LIST_NAME, SkipList, SkippedPlugin};
 
-mod light_override;
-pub use light_override::{CustomCellAmbient, CustomLightData};
+pub mod telemetry;
+
+pub mod transform;
+
+pub mod update;
+
+mod validate;
+pub use validate::{MAX_VALID_RADIUS, ValidationError, validate_plugin};
+
+mod warnings;
+pub use warnings::Warning;
 
 pub const DEFAULT_CONFIG_NAME: &str = "lightconfig.toml";
 pub const LOG_NAME: &str = "lightconfig.log";
+pub const OVERRIDES_NAME: &str = "lightoverrides.toml";
 pub const PLUGIN_NAME: &str = "S3LightFixes.omwaddon";
+pub const ESP_PLUGIN_NAME: &str = "S3LightFixes.esp";
+/// Start of the name of each addon written by `--split-output per-plugin`, followed by the name of its master
+pub const SPLIT_PLUGIN_PREFIX: &str = "S3LF_";
+pub const CONFIG_PATH_VAR: &str = "S3L_OPENMW_CFG";
+/// Directory next to the user openmw.cfg which --install writes the plugin into
+pub const INSTALL_DIR_NAME: &str = "s3lightfixes";
+
+/// Directory containing the running executable
+/// In portable mode, all state (lightconfig.toml, logs, and the generated plugin) lives here
+pub fn portable_dir() -> io::Result<PathBuf> {
+    let exe_path = current_exe()?;
+
+    exe_path.parent().map(Path::to_path_buf).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "Could not resolve the directory containing {}",
+                exe_path.display()
+            ),
+        )
+    })
+}
+
+/// Where the OpenMW Android port keeps openmw.cfg, for both release and nightly builds
+/// Older versions used shared storage, newer ones use app-specific storage
+#[cfg(target_os = "android")]
+const ANDROID_CONFIG_CANDIDATES: [&str; 4] = [
+    "/storage/emulated/0/Android/data/is.xyz.omw/files/config/openmw.cfg",
+    "/storage/emulated/0/Android/data/is.xyz.omw_nightly/files/config/openmw.cfg",
+    "/storage/emulated/0/omw/config/openmw.cfg",
+    "/storage/emulated/0/omw_nightly/config/openmw.cfg",
+];
+
+/// Where the Flatpak build of OpenMW keeps openmw.cfg, relative to the home directory
+#[cfg(target_os = "linux")]
+const FLATPAK_CONFIG_PATH: &str = ".var/app/org.openmw.OpenMW/config/openmw/openmw.cfg";
+
+/// Where the Snap build of OpenMW keeps openmw.cfg, relative to the home directory
+#[cfg(target_os = "linux")]
+const SNAP_CONFIG_PATH: &str = "snap/openmw/current/.config/openmw/openmw.cfg";
+
+/// openmw.cfg of a containerized OpenMW install, if one exists
+/// Flatpak is preferred over Snap when both are installed
+#[cfg(target_os = "linux")]
+fn containerized_config_path() -> Option<PathBuf> {
+    let home = PathBuf::from(var_os("HOME")?);
+
+    [FLATPAK_CONFIG_PATH, SNAP_CONFIG_PATH]
+        .iter()
+        .map(|path| home.join(path))
+        .find(|path| path.is_file())
+}
+
+/// Asks for the location of openmw.cfg on the terminal
+/// Used on Android, where there are no dialogs and Termux users struggle with environment variables
+#[cfg(target_os = "android")]
+fn prompt_for_config_path() -> io::Result<PathBuf> {
+    print!("Couldn't find openmw.cfg! Enter the path to it, or the directory containing it: ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().lock().read_line(&mut input)?;
+    let path = PathBuf::from(input.trim());
+
+    if path.is_file() || path.join("openmw.cfg").is_file() {
+        Ok(path)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No openmw.cfg could be found at {}", path.display()),
+        ))
+    }
+}
+
+/// Resolves which openmw.cfg to use, in order of priority:
+/// The --openmw-cfg argument, the S3L_OPENMW_CFG environment variable,
+/// an openmw.cfg in the working directory, one next to the executable in portable mode,
+/// the Flatpak install with --flatpak, and finally the platform default location
+/// On Linux, Flatpak and Snap installs are used when there is nothing at the default location
+/// On Android, the known locations used by the OpenMW port are checked instead of the default,
+/// and the user is asked for the path if none of them exist
+pub fn get_config_path(args: &mut LightArgs) -> io::Result<PathBuf> {
+    let requested_path = match args.openmw_cfg.first() {
+        Some(path) => Some(path.to_owned()),
+        None => var_os(CONFIG_PATH_VAR).map(PathBuf::from),
+    };
 
-pub fn get_config_path(args: &mut LightArgs) -> PathBuf {
-    if let Some(path) = &args.openmw_cfg {
+    if let Some(path) = requested_path {
         let absolute_path = if path.is_relative() {
-            path.canonicalize().unwrap()
+            path.canonicalize().map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!(
+                        "Could not resolve openmw.cfg path {}: {err}",
+                        path.display()
+                    ),
+                )
+            })?
         } else {
-            path.to_owned()
+            path
         };
 
         if absolute_path.is_dir() && absolute_path.join("openmw.cfg").is_file() {
-            return absolute_path;
+            return Ok(absolute_path);
         } else if absolute_path.is_file() {
-            return absolute_path;
+            return Ok(absolute_path);
         }
 
-        panic!("This shit should never ever happen!");
-    } else {
-        let cwd_cfg = current_dir()
-            .expect("Failed to get current directory")
-            .join("openmw.cfg");
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "The requested openmw.cfg location {} is neither an openmw.cfg file nor a directory containing one!",
+                absolute_path.display()
+            ),
+        ));
+    }
+
+    let cwd_cfg = current_dir()?.join("openmw.cfg");
+
+    if cwd_cfg.is_file() {
+        return Ok(cwd_cfg);
+    }
 
-        if cwd_cfg.is_file() {
-            return cwd_cfg;
+    if args.portable {
+        let portable_cfg = portable_dir()?.join("openmw.cfg");
+
+        if portable_cfg.is_file() {
+            return Ok(portable_cfg);
         }
     }
 
-    openmw_config::default_config_path()
+    #[cfg(target_os = "linux")]
+    if args.flatpak {
+        let flatpak_cfg = var_os("HOME")
+            .map(|home| PathBuf::from(home).join(FLATPAK_CONFIG_PATH))
+            .filter(|path| path.is_file());
+
+        return flatpak_cfg.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No Flatpak openmw.cfg was found at ~/{FLATPAK_CONFIG_PATH}"),
+            )
+        });
+    }
+
+    let default_path = default_config_path();
+
+    #[cfg(target_os = "linux")]
+    {
+        let default_exists = default_path
+            .as_ref()
+            .is_ok_and(|path| path.is_file() || path.join("openmw.cfg").is_file());
+
+        if let Some(containerized_cfg) = containerized_config_path().filter(|_| !default_exists) {
+            return Ok(containerized_cfg);
+        }
+    }
+
+    default_path
+}
+
+/// The location the OpenMW port keeps openmw.cfg in,
+/// asking the user for the path if none of the known ones exist
+#[cfg(target_os = "android")]
+fn default_config_path() -> io::Result<PathBuf> {
+    match ANDROID_CONFIG_CANDIDATES
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+    {
+        Some(android_cfg) => Ok(android_cfg),
+        None => prompt_for_config_path(),
+    }
+}
+
+/// The platform default location of openmw.cfg
+#[cfg(not(target_os = "android"))]
+fn default_config_path() -> io::Result<PathBuf> {
+    // openmw_config panics if the platform's home/config directories can't be resolved,
+    // which happens in sandboxes and for service users without a HOME
+    // Only that panic is kept quiet: any other thread panicking meanwhile is still reported as usual
+    let caller = std::thread::current().id();
+    let previous_hook: std::sync::Arc<dyn Fn(&std::panic::PanicHookInfo) + Send + Sync> =
+        std::panic::take_hook().into();
+
+    let hook = previous_hook.clone();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().id() != caller {
+            hook(info);
+        }
+    }));
+
+    let default_path = std::panic::catch_unwind(openmw_config::default_config_path);
+    std::panic::set_hook(Box::new(move |info| previous_hook(info)));
+
+    default_path.map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "Unable to determine the default openmw.cfg location, most likely because no home directory is set for this user. Pass its location with --openmw-cfg or the {CONFIG_PATH_VAR} environment variable instead."
+            ),
+        )
+    })
 }
 
 pub fn is_fixable_plugin(plug_path: &Path) -> bool {
+    // Links are followed, so that a link to the lightfixes plugin under another name is still recognized
+    let canonical = canonical_path(plug_path);
+
     // If path doesn't exist
     if metadata(plug_path).is_err() {
         return false;
-    // If path is the lightfixes plugin
-    } else if plug_path.to_string_lossy().contains(PLUGIN_NAME) {
+    // If path is the lightfixes plugin, under its usual names
+    } else if [plug_path, canonical.as_path()].iter().any(|path| {
+        path.to_string_lossy().contains(PLUGIN_NAME)
+            || path.to_string_lossy().contains(ESP_PLUGIN_NAME)
+    }) {
         return false;
     } else {
         // Don't match extensionless files
@@ -64,7 +361,8 @@ pub fn is_fixable_plugin(plug_path: &Path) -> bool {
         match plug_path.extension() {
             None => return false,
             Some(ext) => match ext.to_ascii_lowercase().to_str().unwrap_or_default() {
-                "esp" | "esm" | "omwaddon" | "omwgame" => return true,
+                // Renamed copies of earlier outputs would otherwise feed back into the new one
+                "esp" | "esm" | "omwaddon" | "omwgame" => return !is_generated_plugin(plug_path),
                 _ => return false,
             },
         }
@@ -72,48 +370,312 @@ pub fn is_fixable_plugin(plug_path: &Path) -> bool {
 }
 
 /// Displays a notification taking title and message as argument
-pub fn notification_box(title: &str, message: &str, no_notifications: bool) {
-    #[cfg(target_os = "android")]
-    println!("{}", message);
-
-    #[cfg(not(target_os = "android"))]
-    if !no_notifications {
-        let _ = native_dialog::DialogBuilder::message()
-            .set_title(title)
-            .set_text(message)
-            .alert()
-            .show();
-    } else {
-        println!("{}", message);
+/// Messages go to whichever backend was chosen with set_notify_backend,
+/// which NotifyBackend::resolve picks from --notify and the older no_notifications and desktop_notifications switches
+pub fn notification_box(title: &str, message: &str) {
+    let backend = notify_backend();
+
+    log::info!(target: logging::NOTIFICATION_TARGET, "{title}: {message}");
+
+    // JSON logs already carry the notification, and plain text would break them
+    if backend == NotifyBackend::Stdout && logging::is_json() {
+        return;
     }
+
+    backend.notifier().notify(title, message);
 }
 
-pub fn save_plugin(output_dir: &PathBuf, generated_plugin: &mut Plugin) -> io::Result<()> {
-    let mut plugin_path = output_dir.join(PLUGIN_NAME);
+static PAUSE_ON_EXIT: AtomicBool = AtomicBool::new(false);
 
-    match metadata(output_dir) {
-        Ok(metadata) if !metadata.is_dir() => {
-            let cwd =
-                current_dir().expect("CRITICAL FAILURE: FAILED TO READ CURRENT WORKING DIRECTORY!");
+/// Whether the process owns its console window, meaning it was started by double-clicking
+/// and the window will disappear as soon as we exit
+#[cfg(windows)]
+pub fn launched_without_console() -> bool {
+    unsafe extern "system" {
+        fn GetConsoleProcessList(process_list: *mut u32, process_count: u32) -> u32;
+    }
+
+    let mut process_list = [0u32; 2];
+    let attached = unsafe { GetConsoleProcessList(process_list.as_mut_ptr(), 2) };
+
+    attached == 1
+}
+
+#[cfg(not(windows))]
+pub fn launched_without_console() -> bool {
+    false
+}
 
-            eprintln!(
-                "WARNING: Couldn't use {} as an output directory, as it isn't a directory. Using the current working directory, {}, instead!",
-                output_dir.display(),
-                cwd.display()
-            );
+/// Requests that the console stay open until the user presses Enter before the process ends
+pub fn set_pause_on_exit(pause: bool) {
+    PAUSE_ON_EXIT.store(pause, Ordering::Relaxed);
+}
 
-            plugin_path = cwd.join(PLUGIN_NAME);
+/// Blocks on a "Press Enter to close" prompt, if a pause was requested
+pub fn wait_for_enter() {
+    if !PAUSE_ON_EXIT.load(Ordering::Relaxed) {
+        return;
+    }
+
+    print!("Press Enter to close . . .");
+    let _ = io::stdout().flush();
+    let _ = io::stdin().lock().read_line(&mut String::new());
+}
+
+/// Terminates the process with the given code
+/// Waits for the user first, if a pause was requested
+pub fn exit(code: ExitCode) -> ! {
+    wait_for_enter();
+    std::process::exit(code.code())
+}
+
+/// Flags for a record copied into the generated plugin
+/// Only persistence, blocking, and deletion are carried over from the source record,
+/// so that nothing else a plugin happened to leave set changes how the copy is loaded
+pub fn output_record_flags(source: ObjectFlags, mark_modified: bool) -> ObjectFlags {
+    let mut flags =
+        source & (ObjectFlags::PERSISTENT | ObjectFlags::BLOCKED | ObjectFlags::DELETED);
+    flags.set(ObjectFlags::MODIFIED, mark_modified);
+    flags
+}
+
+/// Whether a content file can only be loaded by OpenMW
+pub fn is_openmw_only(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".omwaddon") || name.ends_with(".omwgame")
+}
+
+/// Identifies a record by its type and lowercase id, as a plugin holds at most one of each
+pub fn record_key(object: &TES3Object) -> (String, String) {
+    (
+        object.tag_str().to_owned(),
+        object.editor_id().to_ascii_lowercase(),
+    )
+}
+
+/// Makes a copy of the generated plugin which the original engine can load
+/// Vanilla Morrowind and MWSE can't load OpenMW-only content files, so any .omwaddon or .omwgame masters
+/// are dropped from the header, along with the records in `openmw_only_records` which came from them
+pub fn to_esp_plugin(
+    generated_plugin: &Plugin,
+    openmw_only_records: &HashSet<(String, String)>,
+) -> Plugin {
+    let mut esp_plugin = generated_plugin.clone();
+
+    esp_plugin.objects.retain(|object| {
+        matches!(object, TES3Object::Header(_))
+            || !openmw_only_records.contains(&record_key(object))
+    });
+
+    // Everything but the header
+    let num_objects = esp_plugin.objects.len().saturating_sub(1) as u32;
+
+    for object in &mut esp_plugin.objects {
+        if let TES3Object::Header(header) = object {
+            header.file_type = FileType::Esp;
+            header.masters.retain(|(name, _)| !is_openmw_only(name));
+            header.num_objects = num_objects;
+        }
+    }
+
+    esp_plugin
+}
+
+/// Clears everything but the name, flags, and lighting of a cell
+/// OpenMW only overwrites the fields a later cell record has, so the rest are kept from the plugins loaded before
+pub fn strip_to_atmosphere(cell: &mut Cell) {
+    cell.region = None;
+    cell.map_color = None;
+    cell.water_height = None;
+    cell.references.clear();
+}
+
+/// Drops everything lightfixes never looks at from a freshly loaded plugin: exterior cells,
+/// and the references of interiors, which make up nearly all of a large master's cell data
+/// Only lights and interior cells with atmosphere data, or which are deleted, are kept
+fn trim_light_records(plugin: &mut Plugin) {
+    plugin.objects.retain_mut(|object| match object {
+        TES3Object::Light(_) => true,
+        TES3Object::Cell(cell) => {
+            let keep = cell.data.flags.contains(CellFlags::IS_INTERIOR)
+                && (cell.atmosphere_data.is_some() || cell.flags.contains(ObjectFlags::DELETED));
+
+            if keep {
+                cell.references.clear();
+            }
+
+            keep
+        }
+        _ => false,
+    });
+}
+
+/// Loads the cells and lights of a plugin, reading only those two record types from the file
+/// Anything lightfixes doesn't need from them is dropped straight away, so that many plugins can be held at once
+/// Unless `strict`, a plugin which fails to load is read again for lights and cells separately,
+/// so that a malformed or localized record of one type doesn't take the other down with it
+/// `inspect` sees the plugin before anything is dropped, eg to count the references of exterior cells
+/// Returns whether only part of the plugin could be loaded
+pub fn load_light_records(
+    path: &Path,
+    strict: bool,
+    mut inspect: impl FnMut(&Plugin),
+) -> io::Result<(Plugin, bool)> {
+    let err = match Plugin::from_path_filtered(path, |tag| matches!(&tag, Cell::TAG | Light::TAG)) {
+        Ok(mut plugin) => {
+            inspect(&plugin);
+            trim_light_records(&mut plugin);
+            return Ok((plugin, false));
+        }
+        Err(err) if strict => return Err(err),
+        Err(err) => err,
+    };
+
+    let lights = Plugin::from_path_filtered(path, |tag| matches!(&tag, Light::TAG));
+    let cells = Plugin::from_path_filtered(path, |tag| matches!(&tag, Cell::TAG));
+
+    match (lights, cells) {
+        (Err(_), Err(_)) => Err(err),
+        (lights, cells) => {
+            let mut plugin = Plugin::new();
+
+            for partial in [lights, cells].into_iter().flatten() {
+                plugin.objects.extend(partial.objects);
+            }
+
+            inspect(&plugin);
+            trim_light_records(&mut plugin);
+            Ok((plugin, true))
+        }
+    }
+}
+
+/// Where a plugin was saved, and whether the file on disk actually changed
+#[derive(Debug)]
+pub struct SavedPlugin {
+    pub path: PathBuf,
+    pub changed: bool,
+    /// Set when the plugin had to be saved somewhere other than the output directory
+    pub warnings: Vec<Warning>,
+}
+
+/// Directories tried, in order, when the output directory can't be written to:
+/// the user openmw.cfg directory, the user data directory, and the working directory
+pub fn default_fallback_dirs(user_config_dir: &Path) -> Vec<PathBuf> {
+    let data_home = var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+        .or_else(|| var_os("LOCALAPPDATA").map(PathBuf::from));
+
+    [
+        Some(user_config_dir.to_path_buf()),
+        data_home.map(|dir| dir.join(INSTALL_DIR_NAME)),
+        current_dir().ok(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Errors which mean another directory should be tried, rather than giving up
+fn is_unwritable(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::PermissionDenied
+            | io::ErrorKind::ReadOnlyFilesystem
+            | io::ErrorKind::NotADirectory
+    )
+}
+
+/// Saves the plugin into output_dir, or the first of fallback_dirs which can be written to
+/// if output_dir is read-only or not a directory
+/// Identical output is not rewritten, so the existing file keeps its modification time
+pub fn save_plugin(
+    output_dir: &PathBuf,
+    fallback_dirs: &[PathBuf],
+    plugin_name: &str,
+    generated_plugin: &mut Plugin,
+) -> io::Result<SavedPlugin> {
+    let bytes = generated_plugin.save_bytes()?;
+    let mut last_error = None;
+
+    for dir in std::iter::once(output_dir).chain(fallback_dirs) {
+        match write_plugin_bytes(dir, plugin_name, &bytes) {
+            Ok(mut saved) => {
+                if dir != output_dir {
+                    saved.warnings.push(Warning::FallbackPath {
+                        plugin_name: plugin_name.to_owned(),
+                        output_dir: output_dir.to_owned(),
+                        saved_in: dir.to_owned(),
+                    });
+                }
+
+                return Ok(saved);
+            }
+            Err(err) if is_unwritable(&err) => last_error = Some(err),
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not writable", output_dir.display()),
+        )
+    }))
+}
+
+fn write_plugin_bytes(dir: &Path, plugin_name: &str, bytes: &[u8]) -> io::Result<SavedPlugin> {
+    match metadata(dir) {
+        Ok(metadata) if !metadata.is_dir() => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                format!("{} is not a directory", dir.display()),
+            ));
         }
         Ok(_) => {}
         Err(err) if err.kind() == io::ErrorKind::NotFound => {
-            create_dir_all(output_dir)?;
+            create_dir_all(dir)?;
         }
         Err(err) => return Err(err),
     }
 
-    generated_plugin.save_path(plugin_path)?;
+    let plugin_path = dir.join(plugin_name);
+
+    if read(&plugin_path).is_ok_and(|existing| existing == bytes) {
+        return Ok(SavedPlugin {
+            path: plugin_path,
+            changed: false,
+            warnings: Vec::new(),
+        });
+    }
+
+    backup::back_up(&plugin_path)?;
+    write(&plugin_path, bytes)?;
+
+    Ok(SavedPlugin {
+        path: plugin_path,
+        changed: true,
+        warnings: Vec::new(),
+    })
+}
+
+/// Appends a data= line for `data_dir` to the end of an openmw.cfg, giving it the highest priority
+/// Done by hand, after any other changes to the file have been saved
+pub fn append_data_directory(cfg_path: &Path, data_dir: &Path) -> io::Result<()> {
+    let needs_newline = read_to_string(cfg_path)
+        .is_ok_and(|contents| !contents.is_empty() && !contents.ends_with('\n'));
+
+    let mut cfg = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(cfg_path)?;
+
+    if needs_newline {
+        writeln!(cfg)?;
+    }
 
-    Ok(())
+    writeln!(cfg, "data=\"{}\"", data_dir.display())
 }
 
 pub fn to_io_error<E: std::fmt::Display>(err: E) -> std::io::Error {
@@ -1,80 +1,329 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use crate::default;
 
 #[derive(Parser, Debug)]
 #[command(
     name = "S3 Lightfixes",
-    about = "A tool for modifying light values globally across an OpenMW installation.\nPlease note that arguments provided here, which also exist in lightConfig.toml, will override any values in lightConfig.toml when used.\nAdditionally, if the lightConfig.toml does not exist, the used values will be saved into the new lightConfig.toml."
+    after_help = crate::EXIT_CODES_HELP,
+    about = "A tool for modifying light values globally across an OpenMW installation.\nPlease note that arguments provided here, which also exist in lightConfig.toml, will override any values in lightConfig.toml when used.\nAdditionally, if the lightConfig.toml does not exist, the used values will be saved into the new lightConfig.toml.\nEvery argument may also be given as an S3L_ environment variable, such as S3L_STANDARD_RADIUS=1.5 or S3L_AUTO_ENABLE=true, with arguments on the command line taking priority."
 )]
 pub struct LightArgs {
+    #[command(subcommand)]
+    pub command: Option<LightCommand>,
+
     /// Path to openmw.cfg
     /// By default, uses the system paths defined by:
     /// https://openmw.readthedocs.io/en/latest/reference/modding/paths.html
     /// Can be the literal path to an openmw.cfg file (including not literally being called openmw.cfg)
     /// Or the directory in which an openmw.cfg file lives.
+    /// May also be provided through the S3L_OPENMW_CFG environment variable.
+    /// May be repeated to fix several profiles in one run, each getting its own plugin in its own data-local directory.
     #[arg(short = 'c', long = "openmw-cfg")]
-    pub openmw_cfg: Option<PathBuf>,
+    pub openmw_cfg: Vec<PathBuf>,
+
+    /// Text file listing openmw.cfg paths, or directories holding one, one per line.
+    /// Each profile is fixed in turn, as if it had been given with --openmw-cfg.
+    /// Lines starting with # are skipped, and relative paths are taken from the file's own directory.
+    #[arg(
+        long = "profiles",
+        env = "S3L_PROFILES",
+        conflicts_with_all = ["openmw_cfg", "content", "output", "stdout"]
+    )]
+    pub profiles: Option<PathBuf>,
+
+    /// Path to a Mod Organizer 2 profile directory, eg `MO2/profiles/Default`.
+    /// Every mod enabled in its modlist.txt is added to the data directories from openmw.cfg,
+    /// so lightfixes sees the same files the game does.
+    /// Detected automatically when lightfixes is run from inside an MO2 instance.
+    #[arg(long = "mo2-profile", env = "S3L_MO2_PROFILE")]
+    pub mo2_profile: Option<PathBuf>,
+
+    /// Directory to use as data-local, replacing the one from openmw.cfg.
+    /// Like in OpenMW, it takes priority over every other data directory.
+    #[arg(long = "data-local", env = "S3L_DATA_LOCAL")]
+    pub data_local: Option<PathBuf>,
+
+    /// Data directory to leave out, even though openmw.cfg or MO2 lists it. May be repeated.
+    #[arg(long = "skip-data-dir", env = "S3L_SKIP_DATA_DIR")]
+    pub skip_data_dir: Vec<PathBuf>,
+
+    /// Archive to search for assets, such as meshes for mesh-aware radii. May be repeated, in load order.
+    /// When given, replaces the fallback-archive lines of openmw.cfg.
+    #[arg(long = "fallback-archive", env = "S3L_FALLBACK_ARCHIVE")]
+    pub fallback_archive: Vec<String>,
+
+    /// Keep all state next to the executable.
+    /// lightconfig.toml, the plugin log, and the generated plugin are all read from and written to
+    /// the directory containing s3lightfixes, and an openmw.cfg placed there is preferred over the system one.
+    #[arg(short = 'P', long = "portable", env = "S3L_PORTABLE")]
+    pub portable: bool,
+
+    /// Use the openmw.cfg of the Flatpak build of OpenMW, in ~/.var/app/org.openmw.OpenMW.
+    /// Flatpak and Snap installs are also used automatically when there is no openmw.cfg in the default location.
+    /// Only available on Linux.
+    #[arg(long = "flatpak", env = "S3L_FLATPAK")]
+    pub flatpak: bool,
+
+    /// Content file to fix, instead of the load order in openmw.cfg. May be repeated, in load order.
+    /// When given, openmw.cfg is not read at all, so lightfixes can be run against a single plugin without an OpenMW install.
+    #[arg(
+        long = "content",
+        env = "S3L_CONTENT",
+        conflicts_with_all = ["openmw_cfg", "flatpak", "auto_enable", "install"]
+    )]
+    pub content: Vec<String>,
+
+    /// Data directory to find the content files given with --content in. May be repeated.
+    /// Later directories take priority, as in openmw.cfg. Defaults to the current working directory.
+    #[arg(long = "data", env = "S3L_DATA", requires = "content")]
+    pub data: Vec<PathBuf>,
 
     /// Enables classic mode using vtastek shaders.
     /// ONLY for openmw 0.47. Relevant shaders can be found in the OpenMW discord:
     /// https://discord.com/channels/260439894298460160/718892786157617163/966468825321177148
-    #[arg(short = '7', long = "classic")]
+    #[arg(short = '7', long = "classic", env = "S3L_CLASSIC")]
     pub use_classic: bool,
 
     /// Output directory.
     /// The plugin may be saved to any location, but its name will always be `S3Lightfixes.omwaddon`.
     /// Accepts relative and absolute terms.
-    #[arg(short = 'o', long = "output")]
-    pub output: Option<PathBuf>,
+    /// May be repeated to save copies of the plugin in several places at once, with the first being the main output.
+    #[arg(short = 'o', long = "output", env = "S3L_OUTPUT")]
+    pub output: Vec<PathBuf>,
+
+    /// Which kind of plugin to write.
+    /// `esp` writes S3LightFixes.esp for vanilla Morrowind and MWSE, leaving out any OpenMW-only masters.
+    /// The ESP copy is never automatically enabled.
+    #[arg(short = 'F', long = "format", env = "S3L_FORMAT")]
+    pub output_format: Option<crate::OutputFormat>,
+
+    /// Save one addon per plugin records were changed from, such as S3LF_Morrowind.omwaddon, each with only that plugin as a master.
+    /// Mods can then be removed mid-playthrough along with their addon, instead of breaking one patch with hundreds of masters.
+    /// Only omwaddons are written this way.
+    #[arg(long = "split-output", env = "S3L_SPLIT_OUTPUT")]
+    pub split_output: Option<crate::SplitOutput>,
+
+    /// Generate only lights or only interior cells, eg to keep another mod's interior ambient lighting.
+    /// Run once with each and a different --output to write the two parts separately.
+    #[arg(long = "only", env = "S3L_ONLY")]
+    pub only: Option<crate::RecordCategories>,
+
+    /// What to do when a plugin can't be loaded: skip it with a warning, abort without writing anything,
+    /// or prompt whether to carry on without it.
+    /// Abort also counts plugins left out because they failed on an earlier run.
+    #[arg(long = "on-plugin-error", env = "S3L_ON_PLUGIN_ERROR")]
+    pub on_plugin_error: Option<crate::PluginErrorPolicy>,
+
+    /// Keep any records edited by hand in the previously generated plugin, instead of overwriting them.
+    /// Edits are found by comparing against a copy of each generated plugin kept next to lightConfig.toml.
+    #[arg(short = 'K', long = "preserve-edits", env = "S3L_PRESERVE_EDITS")]
+    pub preserve_edits: bool,
+
+    /// Write S3LightFixes.meta.json next to the plugin.
+    /// It holds the same version, timestamp, config hash, and master count written into the plugin description.
+    #[arg(long = "write-metadata", env = "S3L_WRITE_METADATA")]
+    pub write_metadata: bool,
+
+    /// Author written into the header of the generated plugin, such as the name of the modlist shipping it.
+    /// Defaults to S3, and is cut short past 32 bytes.
+    #[arg(long = "author", env = "S3L_AUTHOR")]
+    pub author: Option<String>,
+
+    /// Text written into the header description of the generated plugin.
+    /// The version, generation time, config hash, and master count are always written after it, so it's cut short if they wouldn't fit.
+    #[arg(long = "description", env = "S3L_DESCRIPTION")]
+    pub description: Option<String>,
 
-    /// Whether to save a text form of the generated plugin.
-    /// Extremely verbose!
-    /// You probably don't want to enable this unless asked specifically to do so.
-    #[arg(short = 'l', long = "write-log")]
-    pub write_log: bool,
+    /// Previous versions of each generated plugin to keep as .bak files when it changes, for `s3lightfixes rollback`.
+    /// Zero turns backups off. Defaults to 1.
+    #[arg(long = "backup-count", env = "S3L_BACKUP_COUNT")]
+    pub backup_count: Option<usize>,
+
+    /// Set the modified flag on every record in the generated plugin, as the Construction Set does for edited records.
+    /// Persistent, blocked, and deleted flags are always kept from the original records.
+    #[arg(long = "mark-modified", env = "S3L_MARK_MODIFIED")]
+    pub mark_modified: bool,
+
+    /// Copy lights and cells deleted by the last plugin to touch them into the generated plugin, still deleted.
+    /// Otherwise they're only left out, so that earlier versions aren't brought back.
+    #[arg(long = "emit-deletions", env = "S3L_EMIT_DELETIONS")]
+    pub emit_deletions: bool,
+
+    /// What generated cells hold: `full` copies every field of the cell the lighting came from,
+    /// while `atmosphere` writes only its name, flags, and lighting, so OpenMW keeps the region, water height, and map color
+    /// of whichever plugin would otherwise have won them.
+    #[arg(long = "cell-records", env = "S3L_CELL_RECORDS")]
+    pub cell_records: Option<crate::CellRecordContents>,
+
+    /// The oldest OpenMW release the generated files have to work in. Defaults to 0.49.
+    /// Older targets write full cell records even with `--cell-records atmosphere`, and skip the Lua package.
+    #[arg(long = "target-engine", env = "S3L_TARGET_ENGINE")]
+    pub target_engine: Option<crate::TargetEngine>,
+
+    /// Lower radius multipliers to what the lighting method in settings.cfg can show without lights popping in, instead of only warning about them.
+    /// Only legacy lighting is affected.
+    #[arg(long = "fit-lighting-method", env = "S3L_FIT_LIGHTING_METHOD")]
+    pub fit_lighting_method: bool,
+
+    /// Look for a newer release on GitHub during this run, and print a notice at the end if there is one.
+    /// The check happens in the background and is dropped if it hasn't finished by the end of the run.
+    #[arg(long = "check-update", env = "S3L_CHECK_UPDATE")]
+    pub check_update: bool,
+
+    /// Save a log of every record lightfixes changed, with its old and new values and the plugin it came from.
+    /// `toml` and `json` write lightconfig-changes.toml or .json, while `full` writes a text form of the entire generated plugin, which is extremely verbose.
+    /// Without a format, the log_format from lightconfig.toml is used, which defaults to `toml`.
+    #[arg(
+        short = 'l',
+        long = "write-log",
+        env = "S3L_WRITE_LOG",
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true
+    )]
+    pub write_log: Option<Option<crate::LogFormat>>,
 
     /// Whether to automatically enable the output plugin in openmw.cfg.
     /// Disabled by default, and only available via CLI.
     /// Typically lightfixes is ran under momw-configurator, making this param
     /// unnecessary for many users.
-    #[arg(short = 'e', long = "auto-enable")]
+    #[arg(short = 'e', long = "auto-enable", env = "S3L_AUTO_ENABLE")]
     pub auto_enable: bool,
 
+    /// Install the plugin into its own data directory, `s3lightfixes` next to the user openmw.cfg.
+    /// The directory is added as a `data=` line to the user openmw.cfg if it isn't one already, and the plugin is enabled.
+    #[arg(long = "install", env = "S3L_INSTALL", conflicts_with = "output")]
+    pub install: bool,
+
+    /// Disable older copies of the plugin left in other data directories or next to openmw.cfg, including renamed ones,
+    /// by adding .disabled to their names. Their content= lines are removed from openmw.cfg unless the name is still in use.
+    #[arg(long = "clean", env = "S3L_CLEAN", conflicts_with = "stdout")]
+    pub clean: bool,
+
+    /// Write the generated plugin to stdout instead of saving it, for piping into other tools.
+    /// Nothing else is written, and messages which would have been printed go to stderr.
+    /// Only one plugin is written: the ESP when output_format is esp, and the omwaddon otherwise.
+    #[arg(
+        long = "stdout",
+        env = "S3L_STDOUT",
+        conflicts_with_all = ["output", "install", "auto_enable"]
+    )]
+    pub stdout: bool,
+
+    /// Read settings from this lightconfig.toml instead of the one next to openmw.cfg, or from stdin when given `-`.
+    /// The file is never written to, even by --update-light-config.
+    #[arg(long = "config", env = "S3L_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Read shared settings from this lightconfig.toml, such as one kept in a modlist's repository.
+    /// Settings in the lightconfig.toml next to openmw.cfg are layered on top and win, so it only needs the ones being changed.
+    /// Neither file is written to, even by --update-light-config.
+    #[arg(
+        long = "light-config",
+        env = "S3L_LIGHT_CONFIG",
+        conflicts_with = "config"
+    )]
+    pub light_config: Option<PathBuf>,
+
     /// If used, print to stdout instead of using native GUI dialogs.
     /// Not available on android.
     #[arg(short = 'n', long = "no-notifications")]
     pub no_notifications: bool,
 
+    /// Use desktop notifications instead of blocking dialogs, so lightfixes can run from
+    /// post-deploy hooks without stealing focus.
+    /// Only available on Linux desktops with a running notification daemon.
+    /// Same as `--notify desktop`.
+    #[arg(short = 'D', long = "desktop-notifications")]
+    pub desktop_notifications: bool,
+
+    /// Where to send messages about the run.
+    /// Overrides --no-notifications and --desktop-notifications.
+    #[arg(long = "notify", env = "S3L_NOTIFY")]
+    pub notify: Option<crate::NotifyBackend>,
+
+    /// Language to show messages in.
+    /// By default, this is detected from the system locale, falling back to English.
+    #[arg(long = "language", env = "S3L_LANGUAGE")]
+    pub language: Option<crate::Language>,
+
+    /// Code page of the content files, used to match record IDs and cell names containing non-ASCII characters.
+    /// By default, this is read from the `encoding` line of openmw.cfg, falling back to win1252.
+    #[arg(long = "encoding", env = "S3L_ENCODING")]
+    pub encoding: Option<crate::Encoding>,
+
+    /// Wait for Enter to be pressed before closing.
+    /// Enabled automatically on Windows when printing to a console window opened just for lightfixes.
+    #[arg(long = "pause", env = "S3L_PAUSE")]
+    pub pause: bool,
+
     /// Output debugging information during lightfixes generation
-    /// Primarily displays output related to the openmw.cfg being used for generation
+    /// Primarily displays output related to the openmw.cfg being used for generation,
+    /// how long each plugin took to load and process along with how many records it held,
+    /// and how long each stage of the run took
     #[arg(short = 'd', long = "debug")]
     pub debug: bool,
 
+    /// Least severe messages to print: off, error, warn, info, debug, or trace.
+    /// Defaults to warn, or debug along with --debug.
+    /// Everything from info up is also written to s3lightfixes.log next to lightConfig.toml, with the logs of the last few runs kept beside it.
+    #[arg(long = "log-level", env = "S3L_LOG_LEVEL")]
+    pub log_level: Option<log::LevelFilter>,
+
+    /// Print nothing but errors: no progress bar, summary, or success message.
+    /// Overrides --log-level for the console, though s3lightfixes.log is written as usual
+    #[arg(short = 'q', long = "quiet", env = "S3L_QUIET")]
+    pub quiet: bool,
+
+    /// Print each log message to stderr as a line of JSON, with its timestamp, level, plugin, and message.
+    /// Messages which would otherwise be printed to stdout, such as the run summary, are included as info,
+    /// which becomes the default --log-level.
+    /// Meant for launchers which show their own progress
+    #[arg(long = "json-logs", env = "S3L_JSON_LOGS")]
+    pub json_logs: bool,
+
     /// Outputs version info
     // Might be more later?
-    #[arg(short = 'i', long = "info")]
+    #[arg(short = 'i', long = "info", env = "S3L_INFO")]
     pub info: bool,
 
+    /// Use a named set of light values instead of picking multipliers by hand.
+    /// Built-in presets are momw, vanilla-plus, vtastek-classic, and zesterer-shaders.
+    /// Presets defined under [presets] in lightConfig.toml may also be used.
+    /// Any multipliers passed as arguments still override the preset.
+    #[arg(short = 'k', long = "preset", env = "S3L_PRESET")]
+    pub preset: Option<String>,
+
     /// Whether to disable flickering lights during lightfixes generation
-    #[arg(short = 'f', long = "no-flicker")]
+    #[arg(short = 'f', long = "no-flicker", env = "S3L_NO_FLICKER")]
     pub disable_flickering: Option<bool>,
 
     /// Whether to disable pulsing lights during lightfixes generation
-    #[arg(short = 'p', long = "no-pulse")]
+    #[arg(short = 'p', long = "no-pulse", env = "S3L_NO_PULSE")]
     pub disable_pulse: Option<bool>,
 
     #[arg(
         long = "standard-hue",
+        env = "S3L_STANDARD_HUE",
         help = &format!("For lights in the orange range, multiply their HSV hue by this value.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.\nThis argument has no short form due to a conflict with -h.", default::standard_hue())
     )]
     pub standard_hue: Option<f32>,
 
+    #[arg(
+        long = "standard-hue-shift",
+        env = "S3L_STANDARD_HUE_SHIFT",
+        allow_negative_numbers = true,
+        help = "For lights in the orange range, rotate their HSV hue by this many degrees after multiplying it, wrapping around at 360.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of 0."
+    )]
+    pub standard_hue_shift: Option<f32>,
+
     #[arg(
         short = 's',
         long = "standard-saturation",
+        env = "S3L_STANDARD_SATURATION",
         help = &format!("For lights in the orange range, multiply their HSV saturation by this amount.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::standard_saturation())
     )]
     pub standard_saturation: Option<f32>,
@@ -82,6 +331,7 @@ pub struct LightArgs {
     #[arg(
         short = 'v',
         long = "standard-value",
+        env = "S3L_STANDARD_VALUE",
         help = &format!("For lights in the orange range, multiply their HSV value by this amount.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::standard_value())
     )]
     pub standard_value: Option<f32>,
@@ -89,6 +339,7 @@ pub struct LightArgs {
     #[arg(
         short = 'r',
         long = "standard-radius",
+        env = "S3L_STANDARD_RADIUS",
         help = &format!("For lights in the orange range, multiply their radius by this value.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::standard_radius())
     )]
     pub standard_radius: Option<f32>,
@@ -96,13 +347,23 @@ pub struct LightArgs {
     #[arg(
         short = 'H',
         long = "colored-hue",
+        env = "S3L_COLORED_HUE",
         help = &format!("For lights that are red, purple, blue, green, or yellow, multiply their HSV hue by this value.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::colored_hue())
     )]
     pub colored_hue: Option<f32>,
 
+    #[arg(
+        long = "colored-hue-shift",
+        env = "S3L_COLORED_HUE_SHIFT",
+        allow_negative_numbers = true,
+        help = "For lights that are red, purple, blue, green, or yellow, rotate their HSV hue by this many degrees after multiplying it, wrapping around at 360.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of 0."
+    )]
+    pub colored_hue_shift: Option<f32>,
+
     #[arg(
         short = 'S',
         long = "colored-saturation",
+        env = "S3L_COLORED_SATURATION",
         help = &format!("For lights that are red, purple, blue, green, or yellow, multiply their HSV saturation by this amount.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::colored_saturation())
     )]
     pub colored_saturation: Option<f32>,
@@ -110,6 +371,7 @@ pub struct LightArgs {
     #[arg(
         short = 'V',
         long = "colored-value",
+        env = "S3L_COLORED_VALUE",
         help = &format!("For lights that are red, purple, blue, green, or yellow, multiply their HSV value by this amount.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::colored_value())
     )]
     pub colored_value: Option<f32>,
@@ -117,6 +379,7 @@ pub struct LightArgs {
     #[arg(
         short = 'R',
         long = "colored-radius",
+        env = "S3L_COLORED_RADIUS",
         help = &format!("For lights that are red, purple, blue, green, or yellow, multiply their radius by this value.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::colored_radius())
     )]
     pub colored_radius: Option<f32>,
@@ -124,13 +387,211 @@ pub struct LightArgs {
     #[arg(
         short = 'M',
         long = "duration-mult",
+        env = "S3L_DURATION_MULT",
         help = &format!("Multiplies the duration of all carryable lights.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::duration_mult())
     )]
     pub duration_mult: Option<f32>,
 
+    #[arg(
+        long = "exterior-radius",
+        env = "S3L_EXTERIOR_RADIUS",
+        help = &format!("Extra radius multiplier for lights placed in exterior cells, or interiors which behave like exteriors, applied on top of the standard and colored multipliers. Some shaders need much larger radii for lights to show up outdoors at all.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::exterior_radius())
+    )]
+    pub exterior_radius: Option<f32>,
+
+    #[arg(
+        long = "interior-radius",
+        env = "S3L_INTERIOR_RADIUS",
+        help = &format!("Extra radius multiplier for lights placed in interior cells, applied on top of the standard and colored multipliers.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::interior_radius())
+    )]
+    pub interior_radius: Option<f32>,
+
+    #[arg(
+        long = "mixed-placement-radius",
+        env = "S3L_MIXED_PLACEMENT_RADIUS",
+        help = "Which of --exterior-radius and --interior-radius applies to lights placed both outdoors and indoors, as each light has only one radius.\n`larger` and `smaller` pick between them, while `exterior` and `interior` always use that one.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default of `larger`."
+    )]
+    pub mixed_placement_radius: Option<crate::MixedPlacementRadius>,
+
+    #[arg(
+        long = "remove-light-sounds",
+        env = "S3L_REMOVE_LIGHT_SOUNDS",
+        help = "Remove the looping sound from every light. Some light mods attach sounds which stack badly when many lights are loaded at once."
+    )]
+    pub remove_light_sounds: bool,
+
+    #[arg(
+        long = "performance-mode",
+        env = "S3L_PERFORMANCE_MODE",
+        help = "Turn off decorative lights entirely, to reduce the number of dynamic lights in dense cells. Lights are decorative if they match decorative_ids in lightConfig.toml, or are smaller than --decorative-radius. Carryable lights are never turned off"
+    )]
+    pub performance_mode: bool,
+
+    #[arg(
+        long = "decorative-radius",
+        env = "S3L_DECORATIVE_RADIUS",
+        help = &format!("Lights whose original radius is smaller than this are turned off by --performance-mode.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::decorative_radius())
+    )]
+    pub decorative_radius: Option<u32>,
+
+    #[arg(
+        long = "process-groundcover",
+        env = "S3L_PROCESS_GROUNDCOVER",
+        help = "Also fix lights in plugins loaded with `groundcover=` in openmw.cfg. These are skipped by default. Groundcover plugins are never made masters of the generated plugin, since OpenMW does not load them as content files."
+    )]
+    pub process_groundcover: bool,
+
+    #[arg(
+        long = "mesh-aware-radius",
+        env = "S3L_MESH_AWARE_RADIUS",
+        help = "Scale the radius of each light by the size of its model before applying multipliers, so chandeliers reach further than candles."
+    )]
+    pub mesh_aware_radius: bool,
+
+    #[arg(
+        long = "skip-scripted-lights",
+        env = "S3L_SKIP_SCRIPTED_LIGHTS",
+        help = "Leave lights with a script attached out of the generated plugin, so light switches and other scripted lights keep the values their mod expects. The number left out is shown in the summary."
+    )]
+    pub skip_scripted_lights: bool,
+
+    /// Also write an OpenMW Lua package into this directory, which applies the standard and colored multipliers
+    /// to lights that Lua mods create while the game is running. Add it as a data directory and enable S3LightFixes.omwscripts to use it.
+    #[arg(long = "emit-lua-package", env = "S3L_EMIT_LUA_PACKAGE")]
+    pub emit_lua_package: Option<PathBuf>,
+
+    #[arg(
+        long = "mesh-reference-radius",
+        env = "S3L_MESH_REFERENCE_RADIUS",
+        help = &format!("Size of model, in game units, whose lights are left unscaled by --mesh-aware-radius. Larger models get larger radii, and smaller ones smaller radii.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::mesh_reference_radius())
+    )]
+    pub mesh_reference_radius: Option<f32>,
+
+    #[arg(
+        long = "max-radius",
+        env = "S3L_MAX_RADIUS",
+        help = "Upper bound for the radius of any light, applied after multipliers. Keeps huge exterior lights from tanking performance.\nIf this argument is not used, the value will be derived from lightConfig.toml, or radii will be left unbounded."
+    )]
+    pub max_radius: Option<u32>,
+
+    #[arg(
+        long = "min-radius",
+        env = "S3L_MIN_RADIUS",
+        help = "Lower bound for the radius of any light, applied after multipliers. Keeps tiny candles from becoming invisible.\nIf this argument is not used, the value will be derived from lightConfig.toml, or radii will be left unbounded."
+    )]
+    pub min_radius: Option<u32>,
+
+    #[arg(
+        long = "min-perceived-brightness",
+        env = "S3L_MIN_PERCEIVED_BRIGHTNESS",
+        help = "Lowest relative luminance, from 0.0 for black to 1.0 for white, any light may have after multipliers. Dimmer lights are brightened until they reach it, so low value multipliers can't leave interiors too dark to play. Around 0.1 keeps most lights useful.\nIf this argument is not used, the value will be derived from lightConfig.toml, or lights will be left as dim as the multipliers make them."
+    )]
+    pub min_perceived_brightness: Option<f32>,
+
+    #[arg(
+        long = "transform-mode",
+        env = "S3L_TRANSFORM_MODE",
+        help = "How to adjust the color of lights in the orange range.\n`hsv` multiplies their hue, saturation, and value by the standard_* values.\n`kelvin` pulls them toward the color of a flame at --target-kelvin, and only applies standard_value.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default of `hsv`."
+    )]
+    pub transform_mode: Option<crate::TransformMode>,
+
+    #[arg(
+        long = "radius-mode",
+        env = "S3L_RADIUS_MODE",
+        help = "How light radii are scaled.\n`flat` multiplies them by standard_radius or colored_radius alone.\n`adaptive` also scales them by each light's adjusted value raised to --radius-curve, so dimmer lights get smaller radii.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default of `flat`."
+    )]
+    pub radius_mode: Option<crate::RadiusMode>,
+
+    #[arg(
+        long = "radius-curve",
+        env = "S3L_RADIUS_CURVE",
+        help = &format!("Exponent applied to each light's value when using the `adaptive` radius mode. 1.0 shrinks radii in proportion to value, while lower values are gentler on dim lights.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::radius_curve())
+    )]
+    pub radius_curve: Option<f32>,
+
+    #[arg(
+        long = "color-rounding",
+        env = "S3L_COLOR_ROUNDING",
+        help = "How adjusted colors are rounded back into light records.\n`nearest` rounds each channel to the closest value.\n`truncate` always rounds down.\n`dither` rounds up or down in proportion to how close each channel is, so dim lights of similar colors don't collapse onto the same one. Each light is always rounded the same way between runs.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default of `nearest`."
+    )]
+    pub color_rounding: Option<crate::ColorRounding>,
+
+    #[arg(
+        long = "color-space",
+        env = "S3L_COLOR_SPACE",
+        help = "Where value multipliers are applied.\n`srgb` multiplies the gamma-encoded value, as earlier versions did, which darkens midtones more than intended.\n`linear` multiplies linear light, so the whole color dims evenly. Lights come out brighter for the same multipliers, so lower them to match.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default of `srgb`."
+    )]
+    pub color_space: Option<crate::ColorSpace>,
+
+    #[arg(
+        long = "merge-strategy",
+        env = "S3L_MERGE_STRATEGY",
+        help = "How lights defined by more than one plugin are combined.\n`last-wins` uses the light from the last plugin defining it, ignoring all others.\n`merge` starts from the first plugin defining the light, and takes each field from the last plugin which changed it, so edits to different parts of a light by different mods are all kept.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default of `last-wins`."
+    )]
+    pub merge_strategy: Option<crate::MergeStrategy>,
+
+    #[arg(
+        long = "master-strategy",
+        env = "S3L_MASTER_STRATEGY",
+        help = "Which plugins are listed as masters of the generated plugin.\n`full` lists every plugin whose version of a light or cell was used.\n`minimal` also leaves out mods whose version of a light only differs from the base game's in fields lightfixes doesn't change, such as its name or model, so the patch breaks less often when mods are renamed or updated.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default of `full`."
+    )]
+    pub master_strategy: Option<crate::MasterStrategy>,
+
+    #[arg(
+        long = "max-masters",
+        env = "S3L_MAX_MASTERS",
+        help = "Warn when the generated plugin needs more masters than this, as a long master list breaks whenever any of them is renamed.\nIf this argument is not used, the value will be derived from lightConfig.toml, or there will be no warning."
+    )]
+    pub max_masters: Option<usize>,
+
+    #[arg(
+        long = "target-kelvin",
+        env = "S3L_TARGET_KELVIN",
+        help = &format!("Color temperature, in Kelvin, which orange lights are pulled toward when using the `kelvin` transform mode.\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::target_kelvin())
+    )]
+    pub target_kelvin: Option<f32>,
+
+    #[arg(
+        long = "kelvin-strength",
+        env = "S3L_KELVIN_STRENGTH",
+        help = &format!("How strongly orange lights are pulled toward --target-kelvin, from 0.0 (unchanged) to 1.0 (exactly that temperature).\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::kelvin_strength())
+    )]
+    pub kelvin_strength: Option<f32>,
+
+    #[arg(
+        long = "standard-target-color",
+        env = "S3L_STANDARD_TARGET_COLOR",
+        value_name = "HEX",
+        help = "Hex color, like #FFB46E, which non-colored lights are pulled toward instead of multiplying their hue and saturation. Their value is still multiplied by standard_value.\nIf this argument is not used, the value will be derived from lightConfig.toml."
+    )]
+    pub standard_target_color: Option<String>,
+
+    #[arg(
+        long = "standard-target-strength",
+        env = "S3L_STANDARD_TARGET_STRENGTH",
+        help = &format!("How strongly non-colored lights are pulled toward --standard-target-color, from 0.0 (unchanged) to 1.0 (exactly that color).\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::target_color_strength())
+    )]
+    pub standard_target_strength: Option<f32>,
+
+    #[arg(
+        long = "colored-target-color",
+        env = "S3L_COLORED_TARGET_COLOR",
+        value_name = "HEX",
+        help = "Hex color which colored lights are pulled toward instead of multiplying their hue and saturation. Their value is still multiplied by colored_value.\nIf this argument is not used, the value will be derived from lightConfig.toml."
+    )]
+    pub colored_target_color: Option<String>,
+
+    #[arg(
+        long = "colored-target-strength",
+        env = "S3L_COLORED_TARGET_STRENGTH",
+        help = &format!("How strongly colored lights are pulled toward --colored-target-color, from 0.0 (unchanged) to 1.0 (exactly that color).\nIf this argument is not used, the value will be derived from lightConfig.toml or use the default value of {}.", default::target_color_strength())
+    )]
+    pub colored_target_strength: Option<f32>,
+
     #[arg(
         short = 'x',
         long = "excluded-ids",
+        env = "S3L_EXCLUDED_IDS",
         help = &format!("List of Regex patterns of light recordIds to exclude. This setting is *merged* onto values defined by lightconfig.toml.\nIf this argument is not used, the value will be derived from lightConfig.toml."),
         value_delimiter = ',',
     )]
@@ -139,6 +600,7 @@ pub struct LightArgs {
     #[arg(
         short = 'X',
         long = "excluded-plugins",
+        env = "S3L_EXCLUDED_PLUGINS",
         help = &format!("List of Regex patterns of plugins to exclude. This setting is *merged* onto values defined by lightconfig.toml.\nIf this argument is not used, the value will be derived from lightConfig.toml."),
         value_delimiter = ',',
     )]
@@ -146,6 +608,7 @@ pub struct LightArgs {
 
     #[arg(
         long = "light",
+        env = "S3L_LIGHT",
         value_parser = crate::light_override::parse_light_override,
         value_delimiter = ':',
         help = &format!(
@@ -157,32 +620,157 @@ pub struct LightArgs {
      OR
      --light \"Torch_001=radius=255,hue=240,duration=1200,flag=FLICKERSLOW:Torch_002=radius_mult=2.0,hue_mult=1.3,duration_mult=5.0,flag=NONE\"
      Hue is a range from 0-360 and saturation/value are normalized floats (0.0 - 1.0). Radius and duration are u32 (can be very big).
-     `flag` may be: NONE, FLICKER, FLICKERSLOW, PULSE, PULSESLOW
+     `flag` may be: NONE, FLICKER, FLICKERSLOW, PULSE, PULSESLOW. It replaces all of the light's flags, so NONE also clears eg dynamic and can_carry.
+     `animation` takes the same values, but only swaps how the light flickers or pulses, keeping its other flags. Only one of the two may be set.
      Fixed values are mutually exclusive with multipliers for each value and setting both will cause an error."),
     )]
     pub light_overrides: Vec<(String, crate::CustomLightData)>,
 
     #[arg(
         long = "ambient",
+        env = "S3L_AMBIENT",
         value_parser = crate::light_override::parse_ambient_override,
         value_delimiter = ':',
         help = &format!(
             "
-            Colon-separated list of cell id regexes, to the corresponding ambient data.
+            Colon-separated list of cell id regexes or globs, to the corresponding ambient data.
+            Globs start with `glob:`, such as `glob:Vivec, *`, and cover many cells at once. When several patterns match a cell, each value is taken from the most specific one.
             `sunlight`, `ambient`, `fog`, and `fog_density` are available parameters.
             Values are provided as fixed HSV values, no multipliers.
             Hue is a range from 0-360 and saturation/value are normalized floats (0.0 - 1.0).
             Each field of cell ambient data is separated by a semicolon, as below:
-            --ambient \"caius cosades\' house=sun=hue=360,saturation=1.0,value=1.0;ambient=hue=24,saturation=0.25,value=0.69\"
+            --ambient \"caius cosades\' house=sunlight=hue=360,saturation=1.0,value=1.0;ambient=hue=24,saturation=0.25,value=0.69\"
             "
         )
     )]
     pub ambient_overrides: Vec<(String, crate::CustomCellAmbient)>,
 
+    #[arg(
+        long = "overrides",
+        env = "S3L_OVERRIDES",
+        value_name = "PATH",
+        help = "Load light and ambient overrides from another toml file, laid out like lightoverrides.toml. May be used more than once, with later files taking priority.\nOverrides in lightConfig.toml always win over those loaded this way, and these are never saved into lightConfig.toml."
+    )]
+    pub override_packs: Vec<PathBuf>,
+
+    #[arg(
+        long = "preview-top",
+        env = "S3L_PREVIEW_TOP",
+        value_name = "N",
+        help = "Instead of generating a plugin, list the N lights whose color would change the most under the current settings, grouped by plugin."
+    )]
+    pub preview_top: Option<usize>,
+
+    #[arg(
+        long = "retry-failed",
+        env = "S3L_RETRY_FAILED",
+        help = "Try loading plugins which failed to load on a previous run, even if they haven't changed since. Such plugins are normally skipped, and listed in lightconfig_skipped.toml next to lightConfig.toml."
+    )]
+    pub retry_failed: bool,
+
+    #[arg(
+        long = "strict-parse",
+        env = "S3L_STRICT_PARSE",
+        help = "Skip any plugin with a light or cell which can't be read. By default, such plugins are read again for lights and cells separately, so that a malformed or localized record of one kind doesn't lose the other."
+    )]
+    pub strict_parse: bool,
+
+    #[arg(
+        long = "load-threads",
+        env = "S3L_LOAD_THREADS",
+        help = "Most plugins to read at once. Each plugin being read is held in memory in full, so lowering this caps memory use on very large masters such as Tamriel_Data.esm.\nIf this argument is not used, the value will be derived from lightConfig.toml, or one plugin is read per CPU core."
+    )]
+    pub load_threads: Option<usize>,
+
+    #[arg(
+        long = "conflicts",
+        env = "S3L_CONFLICTS",
+        help = "Instead of generating a plugin, list every light defined by more than one plugin, with each version's color and radius in load order and which one wins."
+    )]
+    pub conflicts: bool,
+
+    #[arg(
+        long = "vfs-debug",
+        env = "S3L_VFS_DEBUG",
+        help = "Print which file each content entry resolved to, along with any files it won over, such as clock.esp and Clock.ESP in different data directories."
+    )]
+    pub vfs_debug: bool,
+
     #[arg(
         short = 'U',
         long,
-        help = &format!("Force-saves the light config on this run. Note that this parameter does not merge into lightConfig.toml like others, and must be manually set there.")
+        env = "S3L_UPDATE_LIGHT_CONFIG",
+        help = &format!("Force-saves the light config on this run, including any values overridden by other arguments. Comments and unrecognized keys in an existing lightConfig.toml are preserved. Note that this parameter does not merge into lightConfig.toml like others, and must be manually set there.")
     )]
     pub update_light_config: bool,
+
+    /// Write the settings this run actually uses to PATH, or to the lightconfig.toml next to openmw.cfg without one.
+    /// This is the lightconfig.toml, --config, or --light-config settings, with the preset and any overriding arguments applied on top.
+    /// Comments and unrecognized keys in an existing file are preserved, while settings which are no longer set are removed from it.
+    /// Override packs are never included. Only available via CLI, as an environment variable can't tell a path from turning it on.
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        require_equals = true
+    )]
+    pub save_config: Option<Option<PathBuf>>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LightCommand {
+    /// Write every light, with its original and adjusted values, to a table instead of generating a plugin.
+    /// The table can be edited in a spreadsheet and read back in with `import`.
+    Export {
+        /// Format of the table
+        #[arg(long = "table-format", default_value = "csv")]
+        format: crate::light_table::TableFormat,
+
+        /// Where to write the table. Defaults to s3lightfixes_lights.csv (or .json) in the current directory.
+        path: Option<PathBuf>,
+    },
+
+    /// Read an edited table back in, saving its values as light_overrides in lightConfig.toml.
+    /// The format is chosen from the file extension, .json or .csv.
+    Import {
+        /// The table to read
+        path: PathBuf,
+    },
+
+    /// Convert the output of another light patcher into light_overrides in lightConfig.toml, to keep its tweaks.
+    /// Accepts a plugin such as Waza_lightfixes.esp, whose lights are kept as they are, or a Modding-OpenMW lightfixes.json.
+    ImportOverrides {
+        /// The plugin or lightfixes.json to read
+        path: PathBuf,
+    },
+
+    /// Guess whether each light in the load order is a flame, magic, daylight, or ambient light, and save the guesses to lightclasses.toml.
+    /// Lights already listed there are left alone, so corrections made by hand are kept. Set multipliers per class under [classes] in lightconfig.toml.
+    Classify,
+
+    /// Check the assets used by every light in the load order instead of generating a plugin.
+    /// Reports meshes and icons which are missing, or only found in archives that openmw.cfg doesn't list as fallback-archive.
+    Doctor,
+
+    /// Restore the previous version of every generated plugin in the output directory from its .bak file.
+    /// With auto-enable, restored plugins missing from openmw.cfg are enabled again.
+    Rollback,
+
+    /// Download the latest release from GitHub and replace this executable with it.
+    /// Only available with `self_update = true` in lightconfig.toml.
+    Update,
+
+    /// Print the color a light would be given under the current config, before and after, as hex and a terminal swatch.
+    /// No plugin is generated.
+    Preview {
+        /// A hex color starting with #, like #FFB46E, or the ID of a light in the load order
+        target: String,
+    },
+
+    /// Print the masters, record counts, generation details, and a sample of light values of a generated plugin.
+    /// Neither openmw.cfg nor the load order is read, so this works on a plugin sent in on its own.
+    Inspect {
+        /// The plugin to describe
+        path: PathBuf,
+    },
 }
@@ -2,16 +2,362 @@ use std::{
     collections::HashMap,
     fs::{File, read_dir, read_to_string},
     io::{self, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use serde::{Deserialize, Serialize};
+use tes3::esp::{AtmosphereData, Light, LightFlags};
+use toml_edit::{DocumentMut, Item, Table};
 
 use crate::{
-    CustomCellAmbient, CustomLightData, DEFAULT_CONFIG_NAME, default, notification_box, to_io_error,
+    BUILTIN_PRESETS, CarriedLightFlags, Carryable, CustomCellAmbient, CustomLightData,
+    DEFAULT_CONFIG_NAME, Encoding, ExitCode, ExteriorNights, ExtraMultipliers, Language,
+    LightClass, MergeStrategy, Message, NotifyBackend, OVERRIDES_NAME, Placement, Preset,
+    TypedLightColor, default, notification_box,
+    openmw_settings::{LEGACY_MAX_RADIUS, LightingMethod, LightingSettings},
+    to_io_error,
 };
 
+/// Marks a pattern in lightconfig.toml or on the command line as a glob, rather than a regex
+pub const GLOB_PREFIX: &str = "glob:";
+
+/// How non-colored lights have their color adjusted
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TransformMode {
+    /// Multiply the HSV components of each light by the standard_* values
+    #[default]
+    Hsv,
+    /// Pull each light toward the color of a flame at target_kelvin, then scale it by standard_value
+    Kelvin,
+}
+
+/// How light radii follow the brightness of each light
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum RadiusMode {
+    /// Multiply every radius by the standard or colored radius alone
+    #[default]
+    Flat,
+    /// Also scale each radius by its light's adjusted value raised to radius_curve, so dimmer lights reach less far
+    Adaptive,
+}
+
+/// What save_log writes
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Old and new values of each changed record, and the plugin it came from
+    #[default]
+    Toml,
+    /// The same change log, as JSON
+    Json,
+    /// A text dump of the entire generated plugin
+    Full,
+}
+
+/// How colors are rounded to the 8-bit channels stored in light records
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorRounding {
+    /// Round each channel to the nearest value
+    #[default]
+    Nearest,
+    /// Round each channel down
+    Truncate,
+    /// Round each channel up or down, in proportion to how close it is to each,
+    /// so that many similar dim lights don't all collapse onto the same color
+    /// Each light is always rounded the same way between runs
+    Dither,
+}
+
+/// What is copied into the cell records of the generated plugin
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CellRecordContents {
+    /// The whole cell as the last plugin to light it has it, minus its references
+    #[default]
+    Full,
+    /// Only the name, flags, and lighting, so OpenMW keeps the region, water, and map color of whichever plugin wins them
+    Atmosphere,
+}
+
+/// The oldest OpenMW release the generated files have to work in
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, PartialOrd, clap::ValueEnum,
+)]
+pub enum TargetEngine {
+    #[serde(rename = "0.47")]
+    #[value(name = "0.47")]
+    V047,
+    #[serde(rename = "0.48")]
+    #[value(name = "0.48")]
+    V048,
+    #[default]
+    #[serde(rename = "0.49")]
+    #[value(name = "0.49")]
+    V049,
+}
+
+impl TargetEngine {
+    /// Whether a later cell record only replaces the fields it has, which cell_records = "atmosphere" relies on
+    pub fn merges_cell_fields(self) -> bool {
+        self >= TargetEngine::V049
+    }
+
+    /// Whether Lua scripts can create light records, which the Lua package needs
+    pub fn creates_lua_records(self) -> bool {
+        self >= TargetEngine::V049
+    }
+}
+
+/// Which values brightness multipliers are applied to
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    /// The gamma-encoded value, as earlier versions did, which darkens midtones more than brights
+    #[default]
+    Srgb,
+    /// Linear light, so every part of the color dims by the same physical amount
+    Linear,
+}
+
+/// Which kinds of plugin are written
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// S3LightFixes.omwaddon, for OpenMW
+    #[default]
+    Omwaddon,
+    /// S3LightFixes.esp, for vanilla Morrowind and MWSE
+    Esp,
+    /// Both of the above
+    Both,
+}
+
+impl OutputFormat {
+    pub fn writes_omwaddon(&self) -> bool {
+        matches!(self, Self::Omwaddon | Self::Both)
+    }
+
+    pub fn writes_esp(&self) -> bool {
+        matches!(self, Self::Esp | Self::Both)
+    }
+}
+
+/// Whether the generated records are saved together or apart
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitOutput {
+    /// One plugin, with every plugin it changes records from as a master
+    #[default]
+    None,
+    /// One addon per plugin records were changed from, such as S3LF_Morrowind.omwaddon, each with only that plugin as a master
+    PerPlugin,
+}
+
+/// Which kinds of records are written into the generated plugin
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordCategories {
+    /// Both lights and interior cells
+    #[default]
+    All,
+    /// Only lights, leaving every cell's ambient lighting and fog as the load order has it
+    Lights,
+    /// Only interior cells, leaving every light as the load order has it
+    Cells,
+}
+
+/// Which plugins generated lights are attributed to, and so which end up as masters
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum MasterStrategy {
+    /// Each light belongs to the earliest plugin with a byte-identical copy of its winning version
+    #[default]
+    Full,
+    /// As full, but lights whose color, radius, flags, and duration are the same as in a base game master
+    /// belong to that master instead, so mods which only change a light's name or model aren't needed as masters
+    Minimal,
+}
+
+/// Which of exterior_radius and interior_radius applies to lights placed both outdoors and indoors
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum MixedPlacementRadius {
+    /// Whichever multiplier is larger, so the light stays visible outdoors
+    #[default]
+    Larger,
+    /// Whichever multiplier is smaller, so the light doesn't swamp interiors
+    Smaller,
+    Exterior,
+    Interior,
+}
+
+/// What happens to the run when a plugin can't be loaded
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginErrorPolicy {
+    /// Warn, and generate the plugin without it
+    #[default]
+    Skip,
+    /// Stop without writing anything, so a load order is either fixed in full or not at all
+    Abort,
+    /// Ask on the terminal whether to carry on without it, stopping if nobody is there to answer
+    Prompt,
+}
+
+impl RecordCategories {
+    pub fn includes_lights(self) -> bool {
+        self != Self::Cells
+    }
+
+    pub fn includes_cells(self) -> bool {
+        self != Self::Lights
+    }
+}
+
+/// Multipliers for one hue range of colored lights
+/// Any value left unset falls back to the matching colored_* value
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ColoredMultipliers {
+    pub hue: Option<f32>,
+    pub saturation: Option<f32>,
+    pub value: Option<f32>,
+    pub radius: Option<f32>,
+}
+
+/// Separate multipliers for colored lights, by hue
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ColoredBuckets {
+    /// Hues from 330 through 14 degrees
+    pub red: Option<ColoredMultipliers>,
+    /// Hues from 64 through 170 degrees
+    pub green: Option<ColoredMultipliers>,
+    /// Hues from 170 through 260 degrees
+    pub blue: Option<ColoredMultipliers>,
+    /// Hues from 260 through 330 degrees
+    pub purple: Option<ColoredMultipliers>,
+}
+
+impl ColoredBuckets {
+    /// Returns the multipliers for a colored light of the given hue, if any were set
+    pub fn for_hue(&self, hue_degrees: f32) -> Option<&ColoredMultipliers> {
+        match hue_degrees {
+            hue if hue < 14. || hue >= 330. => self.red.as_ref(),
+            hue if hue < 170. => self.green.as_ref(),
+            hue if hue < 260. => self.blue.as_ref(),
+            _ => self.purple.as_ref(),
+        }
+    }
+}
+
+/// Multipliers for the fog of every interior cell
+/// Anything left unset is kept as each plugin had it
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct InteriorFog {
+    pub density: Option<f32>,
+    pub hue: Option<f32>,
+    pub saturation: Option<f32>,
+    pub value: Option<f32>,
+}
+
+impl InteriorFog {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply(&self, fog_color: &mut [u8; 4], fog_density: &mut f32) {
+        if let Some(density) = self.density {
+            *fog_density *= density;
+        }
+
+        if self.hue.is_some() || self.saturation.is_some() || self.value.is_some() {
+            *fog_color = crate::color::scale_hsv(
+                *fog_color,
+                self.hue.unwrap_or(1.0),
+                self.saturation.unwrap_or(1.0),
+                self.value.unwrap_or(1.0),
+            );
+        }
+    }
+}
+
+/// Lighting for interiors flagged to behave like exteriors, such as Mournhold's plazas
+/// They're lit by the sun and weather, so disable_interior_sun and interior_fog leave them alone
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct QuasiExterior {
+    /// Remove their sunlight, like disable_interior_sun does for true interiors
+    #[serde(default)]
+    pub disable_sun: bool,
+    /// Fog multipliers, like interior_fog
+    #[serde(default)]
+    pub fog: InteriorFog,
+    pub ambient: Option<TypedLightColor>,
+    pub sunlight: Option<TypedLightColor>,
+}
+
+impl QuasiExterior {
+    /// Returns whether anything was changed
+    pub fn apply(&self, atmosphere: &mut AtmosphereData) -> bool {
+        let mut changed = false;
+
+        if !self.fog.is_empty() {
+            self.fog
+                .apply(&mut atmosphere.fog_color, &mut atmosphere.fog_density);
+            changed = true;
+        }
+
+        if let Some(ambient) = &self.ambient {
+            atmosphere.ambient_color = ambient.to_rgba();
+            changed = true;
+        }
+
+        if self.disable_sun {
+            atmosphere.sunlight_color = [0, 0, 0, 0];
+            changed = true;
+        } else if let Some(sunlight) = &self.sunlight {
+            atmosphere.sunlight_color = sunlight.to_rgba();
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+/// Per-id and per-cell overrides, shared separately from the rest of lightconfig.toml
+/// Loaded from lightoverrides.toml next to lightconfig.toml, and any files passed with --overrides
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct OverridePack {
+    #[serde(default)]
+    pub light_overrides: HashMap<String, CustomLightData>,
+
+    #[serde(default)]
+    pub ambient_overrides: HashMap<String, CustomCellAmbient>,
+}
+
+impl OverridePack {
+    pub fn from_path(path: &Path) -> io::Result<OverridePack> {
+        let contents = read_to_string(path).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("Couldn't read override pack {}: {err}", path.display()),
+            )
+        })?;
+
+        toml::from_str(&contents).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Couldn't parse override pack {}: {err}", path.display()),
+            )
+        })
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct LightConfig {
     /// This parameter is DANGEROUS
     /// It's only meant to be used with vtastek's experimental shaders for openmw 0.47
@@ -28,18 +374,49 @@ pub struct LightConfig {
     #[serde(default = "default::save_log")]
     pub save_log: bool,
 
+    #[serde(default)]
+    pub log_format: LogFormat,
+
     #[serde(default = "default::auto_enable")]
     pub auto_enable: bool,
 
     #[serde(default)]
     pub no_notifications: bool,
 
+    #[serde(default)]
+    pub desktop_notifications: bool,
+
+    /// Language for messages about the run
+    /// Detected from the system locale when unset
+    pub language: Option<Language>,
+
+    /// Where to send messages about the run
+    /// Takes priority over no_notifications and desktop_notifications when set
+    pub notify: Option<NotifyBackend>,
+
     #[serde(default)]
     pub debug: bool,
 
+    /// Least severe messages printed to the console: off, error, warn, info, debug, or trace
+    /// Defaults to warn, or debug along with the debug setting
+    /// s3lightfixes.log next to lightconfig.toml always holds info and up
+    pub log_level: Option<log::LevelFilter>,
+
+    /// Print nothing to the console but errors
+    #[serde(default)]
+    pub quiet: bool,
+
+    /// Print log messages to the console as lines of JSON
+    #[serde(default)]
+    pub json_logs: bool,
+
     #[serde(default = "default::standard_hue")]
     pub standard_hue: f32,
 
+    /// Degrees added to the hue of non-colored lights after standard_hue, wrapping around at 360
+    #[serde(default)]
+    pub standard_hue_shift: f32,
+
     #[serde(default = "default::standard_saturation")]
     pub standard_saturation: f32,
 
@@ -52,6 +429,10 @@ pub struct LightConfig {
     #[serde(default = "default::colored_hue")]
     pub colored_hue: f32,
 
+    /// Degrees added to the hue of colored lights after colored_hue, wrapping around at 360
+    #[serde(default)]
+    pub colored_hue_shift: f32,
+
     #[serde(default = "default::colored_saturation")]
     pub colored_saturation: f32,
 
@@ -61,31 +442,297 @@ pub struct LightConfig {
     #[serde(default = "default::colored_radius")]
     pub colored_radius: f32,
 
+    /// Multipliers for colored lights of particular hues, eg `[colored.blue]`
+    /// These take priority over the colored_* values above
+    #[serde(default)]
+    pub colored: ColoredBuckets,
+
     #[serde(default = "default::duration_mult")]
     pub duration_mult: f32,
 
+    /// Extra radius multiplier for lights placed in exterior cells, including interiors which behave like exteriors
+    #[serde(default = "default::exterior_radius")]
+    pub exterior_radius: f32,
+
+    /// Extra radius multiplier for lights placed in interior cells
+    #[serde(default = "default::interior_radius")]
+    pub interior_radius: f32,
+
+    /// Which of exterior_radius and interior_radius applies to lights placed in both
+    #[serde(default)]
+    pub mixed_placement_radius: MixedPlacementRadius,
+
+    /// Flags to set or clear on every carryable light, eg `[carried_light_flags] off_by_default = "set"`
+    #[serde(default)]
+    pub carried_light_flags: CarriedLightFlags,
+
+    /// Weight and value limits for every carryable light, eg `[carryable] max_weight = 2.0`
+    #[serde(default)]
+    pub carryable: Carryable,
+
+    /// Remove the looping sound from every light
+    #[serde(default)]
+    pub remove_light_sounds: bool,
+
+    /// Sound ids to swap on lights, from the original to the replacement
+    /// An empty replacement removes the sound
+    #[serde(default)]
+    pub light_sound_replacements: HashMap<String, String>,
+
+    /// Light ids to swap in leveled lists and NPC and creature inventories, from the original to the replacement
+    /// Records handing out a swapped light are copied into the generated plugin
+    #[serde(default)]
+    pub inventory_light_swaps: HashMap<String, String>,
+
+    /// Extra multipliers for lights placed in a region, eg `[regions."Ashlands Region"]`
+    /// A light is adjusted for the region it's placed in most often, out of all exterior references in the load order
+    #[serde(default)]
+    pub regions: HashMap<String, ExtraMultipliers>,
+
+    /// Extra multipliers for each class of light, eg `[classes.flame]`
+    /// Classes come from lightclasses.toml, written by `s3lightfixes classify`, or are guessed for lights it doesn't list
+    #[serde(default)]
+    pub classes: HashMap<LightClass, ExtraMultipliers>,
+
+    /// Scale each light's radius by the size of its model before applying multipliers
+    #[serde(default)]
+    pub mesh_aware_radius: bool,
+
+    /// Leave lights with a script attached out of the generated plugin, so light switches and similar mods keep working
+    #[serde(default)]
+    pub skip_scripted_lights: bool,
+
+    /// The oldest OpenMW release the generated files have to work in
+    #[serde(default)]
+    pub target_engine: TargetEngine,
+
+    /// Lower radius multipliers which the lighting method in settings.cfg can't show well, instead of only warning about them
+    #[serde(default)]
+    pub fit_lighting_method: bool,
+
+    /// Also write an OpenMW Lua package here which applies the standard and colored multipliers to lights spawned at runtime
+    #[serde(default)]
+    pub lua_package_dir: Option<PathBuf>,
+
+    /// Size of model, in game units, whose lights are left unscaled by mesh_aware_radius
+    #[serde(default = "default::mesh_reference_radius")]
+    pub mesh_reference_radius: f32,
+
+    /// Upper bound for light radii, applied after multipliers
+    pub max_radius: Option<u32>,
+
+    /// Lower bound for light radii, applied after multipliers
+    pub min_radius: Option<u32>,
+
+    /// Lowest relative luminance, from 0 to 1, a light may end up with after multipliers
+    pub min_perceived_brightness: Option<f32>,
+
+    #[serde(default)]
+    pub transform_mode: TransformMode,
+
+    #[serde(default)]
+    pub radius_mode: RadiusMode,
+
+    /// Exponent of the curve adaptive radii follow: 1.0 is proportional to value, lower values are gentler on dim lights
+    #[serde(default = "default::radius_curve")]
+    pub radius_curve: f32,
+
+    /// How adjusted colors are rounded back into light records, which is only done once per light
+    #[serde(default)]
+    pub color_rounding: ColorRounding,
+
+    /// Whether value multipliers scale the encoded sRGB value or linear light
+    #[serde(default)]
+    pub color_space: ColorSpace,
+
+    /// How lights defined by more than one plugin are combined
+    #[serde(default)]
+    pub merge_strategy: MergeStrategy,
+
+    /// Which plugins generated lights are attributed to, and so which end up as masters
+    #[serde(default)]
+    pub master_strategy: MasterStrategy,
+
+    /// Warn when the generated plugin needs more masters than this
+    pub max_masters: Option<usize>,
+
+    /// Save one addon per plugin instead of a single plugin, so that mods can be removed mid-playthrough
+    /// Only omwaddons are written this way, so it can't be combined with ESP output or preserve_edits
+    #[serde(default)]
+    pub split_output: SplitOutput,
+
+    /// Generate only lights or only cells, eg to keep another mod's interior ambient lighting
+    #[serde(default)]
+    pub only: RecordCategories,
+
+    /// Whether a plugin which can't be loaded is skipped, stops the run, or is asked about
+    #[serde(default)]
+    pub on_plugin_error: PluginErrorPolicy,
+
+    #[serde(default = "default::target_kelvin")]
+    pub target_kelvin: f32,
+
+    #[serde(default = "default::kelvin_strength")]
+    pub kelvin_strength: f32,
+
+    /// Hex color, like "#FFB46E", which non-colored lights are pulled toward
+    /// instead of multiplying their hue and saturation
+    pub standard_target_color: Option<String>,
+
+    #[serde(default = "default::target_color_strength")]
+    pub standard_target_strength: f32,
+
+    /// Hex color which colored lights are pulled toward instead of multiplying their hue and saturation
+    pub colored_target_color: Option<String>,
+
+    #[serde(default = "default::target_color_strength")]
+    pub colored_target_strength: f32,
+
     #[serde(default = "default::excluded_plugins")]
     pub excluded_plugins: Vec<String>,
 
     #[serde(default)]
     pub excluded_ids: Vec<String>,
 
+    /// Turn off decorative lights entirely, to reduce the number of dynamic lights in dense cells
+    /// Carryable lights are always left alone
+    #[serde(default)]
+    pub performance_mode: bool,
+
+    /// Lights turned off by performance_mode, using the same patterns as excluded_ids
+    #[serde(default)]
+    pub decorative_ids: Vec<String>,
+
+    /// Lights whose original radius is smaller than this are turned off by performance_mode
+    #[serde(default = "default::decorative_radius")]
+    pub decorative_radius: u32,
+
+    /// Also fix lights in plugins loaded through `groundcover=` in openmw.cfg
+    /// These are skipped by default, and are never listed as masters of the generated plugin
+    #[serde(default)]
+    pub process_groundcover: bool,
+
     #[serde(default)]
     pub light_overrides: HashMap<String, CustomLightData>,
 
     #[serde(default)]
     pub ambient_overrides: HashMap<String, CustomCellAmbient>,
 
+    /// Fog multipliers applied to every interior, before ambient_overrides
+    #[serde(default)]
+    pub interior_fog: InteriorFog,
+
+    /// Lighting for interiors which behave like exteriors, applied instead of disable_interior_sun and interior_fog
+    #[serde(default)]
+    pub quasi_exterior: QuasiExterior,
+
+    /// Brightness multipliers for exterior nights, written into the user openmw.cfg rather than the plugin
+    #[serde(default)]
+    pub exterior_nights: ExteriorNights,
+
+    /// Name of a built-in or user-defined preset to populate the values above with
+    #[serde(default)]
+    pub preset: Option<String>,
+
+    /// User-defined presets, which take priority over built-in ones of the same name
+    #[serde(default)]
+    pub presets: HashMap<String, Preset>,
+
     pub output_dir: Option<PathBuf>,
 
+    /// Directories to save the plugin in, in order, when output_dir can't be written to
+    /// Defaults to the user openmw.cfg directory, the user data directory, and then the working directory
+    #[serde(default)]
+    pub fallback_output_dirs: Vec<PathBuf>,
+
+    /// Extra directories to save copies of the plugin in, such as a mod-staging folder for MO2
+    /// Copies are only written exactly where asked, without using fallback_output_dirs
+    #[serde(default)]
+    pub outputs: Vec<PathBuf>,
+
+    /// Mod Organizer 2 profile whose enabled mods are added to the data directories from openmw.cfg
+    /// Detected automatically when lightfixes is run from inside an MO2 instance
+    pub mo2_profile: Option<PathBuf>,
+
+    /// Replaces the data-local directory from openmw.cfg, which is searched after every other data directory
+    pub data_local: Option<PathBuf>,
+
+    /// Data directories to leave out of the VFS, even though openmw.cfg or MO2 lists them
+    #[serde(default)]
+    pub skip_data_dirs: Vec<PathBuf>,
+
+    /// Archives to search for assets, in order, replacing the fallback-archive lines of openmw.cfg
+    #[serde(default)]
+    pub fallback_archives: Vec<String>,
+
+    /// Code page of the content files, from --encoding or openmw.cfg
+    #[serde(skip)]
+    pub encoding: Encoding,
+
+    #[serde(default)]
+    pub output_format: OutputFormat,
+
+    /// Keep changes made by hand to the previously generated plugin
+    #[serde(default)]
+    pub preserve_edits: bool,
+
+    /// Set the modified flag on every record in the generated plugin, as the Construction Set does for edited records
+    #[serde(default)]
+    pub mark_modified: bool,
+
+    /// Skip plugins with any unreadable light or cell, instead of keeping whichever of the two could be read
+    #[serde(default)]
+    pub strict_parse: bool,
+
+    /// Most plugins to read at once, defaulting to one per CPU core
+    pub load_threads: Option<usize>,
+
+    /// Write S3LightFixes.meta.json next to the plugin, with the same details as its description
+    #[serde(default)]
+    pub write_metadata: bool,
+
+    /// Author written into the header of the generated plugin, at most 32 bytes
+    #[serde(default = "default::author")]
+    pub author: String,
+
+    /// Free text written into the header description of the generated plugin, ahead of the generation details
+    /// Cut short if it doesn't fit alongside them in 256 bytes
+    pub description: Option<String>,
+
+    /// Previous versions of each generated plugin to keep as .bak files when it changes, for `s3lightfixes rollback`
+    /// Zero turns backups off
+    #[serde(default = "default::backup_count")]
+    pub backup_count: usize,
+
+    /// Copy lights and cells deleted by the last plugin to touch them into the generated plugin, still deleted
+    /// Otherwise they're only left out, so that earlier versions aren't brought back
+    #[serde(default)]
+    pub emit_deletions: bool,
+
+    /// Whether generated cells carry every field of the cell they were copied from, or only its lighting
+    #[serde(default)]
+    pub cell_records: CellRecordContents,
+
+    /// Let lightfixes contact GitHub for new releases
+    /// Enables the update command, and a notice at the end of each run when a newer release is out
+    #[serde(default)]
+    pub self_update: bool,
+
     #[serde(default)]
     pub save_config: bool,
 
+    /// Directory lightconfig.toml and the plugin log are read from and written to
+    /// This is the user openmw.cfg directory, or the executable's directory in portable mode
+    #[serde(skip)]
+    pub config_dir: PathBuf,
+
     #[serde(skip)]
     pub excluded_id_regexes: Vec<regex::Regex>,
     #[serde(skip)]
     pub excluded_plugin_regexes: Vec<regex::Regex>,
     #[serde(skip)]
+    pub decorative_id_regexes: Vec<regex::Regex>,
+    #[serde(skip)]
     pub light_regexes: Vec<(regex::Regex, CustomLightData)>,
     #[serde(skip)]
     pub ambient_regexes: Vec<(regex::Regex, CustomCellAmbient)>,
@@ -95,11 +742,17 @@ pub struct LightConfig {
 /// for field values
 impl LightConfig {
     fn find(root_path: &PathBuf) -> Result<PathBuf, io::Error> {
+        Self::find_named(root_path, DEFAULT_CONFIG_NAME)
+    }
+
+    fn find_named(root_path: &PathBuf, file_name: &str) -> Result<PathBuf, io::Error> {
         read_dir(root_path)?
             .filter_map(|entry| entry.ok())
-            .find(|entry| entry.file_name().eq_ignore_ascii_case(DEFAULT_CONFIG_NAME))
+            .find(|entry| entry.file_name().eq_ignore_ascii_case(file_name))
             .map(|entry| entry.path())
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Light config not found"))
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("{file_name} not found"))
+            })
     }
 
     fn overwrite_if_some<'a, I, T>(pairs: I)
@@ -116,41 +769,247 @@ impl LightConfig {
         }
     }
 
+    /// Whether a pattern without the glob prefix was likely written as a glob, such as `light_com_lantern_*`
+    /// Anything using regex-only syntax, like `.*` or anchors, was clearly meant as a regex
+    fn looks_like_glob(pattern: &str) -> bool {
+        const REGEX_TOKENS: [&str; 14] = [
+            "\\", "^", "$", "(", ")", "[", "]", "{", "}", "|", "+", ".*", ".+", ".?",
+        ];
+
+        (pattern.contains('*') || pattern.contains('?'))
+            && !REGEX_TOKENS.iter().any(|token| pattern.contains(token))
+    }
+
+    /// Converts a glob into an anchored regex, where `*` matches anything and `?` matches one character
+    fn glob_to_regex(glob: &str) -> String {
+        let mut regex = String::from("^");
+
+        for character in glob.chars() {
+            match character {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                other => regex.push_str(&regex::escape(&other.to_string())),
+            }
+        }
+
+        regex.push('$');
+        regex
+    }
+
+    /// Compiles a user-provided pattern, which is a regex unless it starts with GLOB_PREFIX
+    /// Matching is case-insensitive, as record ids and file names are in Morrowind
+    /// Invalid patterns are reported and skipped instead of silently ignored
+    fn compile_pattern(pattern: &str, setting: &str) -> Option<regex::Regex> {
+        let source = match pattern.strip_prefix(GLOB_PREFIX) {
+            Some(glob) => Self::glob_to_regex(glob),
+            None => {
+                if Self::looks_like_glob(pattern) {
+                    log::warn!(
+                        "Pattern `{pattern}` in {setting} looks like a glob, but is read as a regex. Write it as `{GLOB_PREFIX}{pattern}` to match it as a glob."
+                    );
+                }
+
+                pattern.to_owned()
+            }
+        };
+
+        match regex::RegexBuilder::new(&source)
+            .case_insensitive(true)
+            .build()
+        {
+            Ok(regex) => Some(regex),
+            Err(err) => {
+                log::warn!("Ignoring invalid pattern `{pattern}` in {setting}: {err}");
+                None
+            }
+        }
+    }
+
+    /// Copies every key of `updated` into `existing`
+    /// Values which already exist keep their surrounding comments and whitespace,
+    /// and keys only present in `existing` are left untouched
+    pub(crate) fn merge_toml_tables(existing: &mut Table, updated: &Table) {
+        for (key, updated_item) in updated.iter() {
+            match (existing.get_mut(key), updated_item) {
+                (Some(Item::Table(existing_table)), Item::Table(updated_table)) => {
+                    Self::merge_toml_tables(existing_table, updated_table)
+                }
+                (Some(Item::Value(existing_value)), Item::Value(updated_value)) => {
+                    let decor = existing_value.decor().clone();
+                    *existing_value = updated_value.clone();
+                    *existing_value.decor_mut() = decor;
+                }
+                _ => {
+                    existing.insert(key, updated_item.clone());
+                }
+            }
+        }
+    }
+
+    /// Removes every key of `existing` which `updated` doesn't have, in nested tables too
+    /// Unset settings aren't serialized at all, so this is how they're cleared from an existing file
+    /// Top-level keys which `is_setting` rejects, such as ones from newer versions, are kept
+    fn remove_missing_keys(
+        existing: &mut Table,
+        updated: &Table,
+        is_setting: &dyn Fn(&str) -> bool,
+    ) {
+        existing.retain(|key, _| updated.contains_key(key) || !is_setting(key));
+
+        for (key, existing_item) in existing.iter_mut() {
+            if let Item::Table(existing_table) = existing_item
+                && let Some(Item::Table(updated_table)) = updated.get(key.get())
+            {
+                Self::remove_missing_keys(existing_table, updated_table, &|_| true);
+            }
+        }
+    }
+
+    /// Writes these settings to `config_path`
+    /// An existing file is merged into rather than replaced, so that its comments, formatting, and unknown keys survive,
+    /// while settings which are no longer set are removed from it
+    fn save_to(&self, config_path: &Path) -> io::Result<()> {
+        let config_serialized = toml::to_string_pretty(self).map_err(to_io_error)?;
+
+        let config_serialized = match read_to_string(config_path) {
+            Ok(existing_contents) => {
+                let mut document: DocumentMut = existing_contents.parse().map_err(to_io_error)?;
+                let updated: DocumentMut = config_serialized.parse().map_err(to_io_error)?;
+
+                let known = crate::config_check::field_names::<LightConfig>();
+                Self::remove_missing_keys(document.as_table_mut(), updated.as_table(), &|key| {
+                    known.contains(&key)
+                });
+                Self::merge_toml_tables(document.as_table_mut(), updated.as_table());
+                document.to_string()
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => config_serialized,
+            Err(error) => return Err(error),
+        };
+
+        let mut config_file = File::create(config_path)?;
+        write!(config_file, "{}", config_serialized)
+    }
+
     /// Gives back the lightconfig adjacent to openmw.cfg when called
     /// use_classic dictates whether or not a fixed radius of 2.0 will be used on orange-y lights
     /// and whether or not to disable interior sunlight
     /// the latter field is not de/serializable and can only be used via the --classic argument
     pub fn get(
         mut light_args: crate::LightArgs,
-        openmw_config: &openmw_config::OpenMWConfiguration,
+        openmw_config: Option<&openmw_config::OpenMWConfiguration>,
     ) -> Result<LightConfig, io::Error> {
         let mut write_config = false;
+        let mut existing_config_path: Option<PathBuf> = None;
+
+        // Without an openmw.cfg, lightconfig.toml lives in the working directory, next to the plugins being fixed
+        let user_config_path = match openmw_config {
+            _ if light_args.portable => crate::portable_dir()?,
+            Some(openmw_config) => openmw_config.user_config_path(),
+            None => std::env::current_dir()?,
+        };
+
+        let parse = |config_contents: &str| -> LightConfig {
+            // A misspelled setting would otherwise quietly fall back to its default
+            let unknown = crate::unknown_settings(config_contents);
+            if !unknown.is_empty() {
+                notification_box(
+                    Message::ReadLightConfigFailed.text(),
+                    &Message::ReadLightConfigFailedBody.fill(&[&unknown.join("\n")]),
+                );
+                crate::exit(ExitCode::InvalidConfig);
+            }
+
+            match toml::from_str(config_contents) {
+                Ok(config) => config,
+                Err(e) => {
+                    notification_box(
+                        Message::ReadLightConfigFailed.text(),
+                        &Message::ReadLightConfigFailedBody.fill(&[&e]),
+                    );
+                    crate::exit(ExitCode::InvalidConfig);
+                }
+            }
+        };
+
+        let parse_document = |config_contents: &str| -> DocumentMut {
+            match config_contents.parse() {
+                Ok(document) => document,
+                Err(e) => {
+                    notification_box(
+                        Message::ReadLightConfigFailed.text(),
+                        &Message::ReadLightConfigFailedBody.fill(&[&e]),
+                    );
+                    crate::exit(ExitCode::InvalidConfig);
+                }
+            }
+        };
+
+        // A document given with --config is only read, so it can come from anywhere, stdin included
+        let config_document = light_args.config.take();
+        // Shared settings from --light-config, which the user's own lightconfig.toml is layered over
+        let shared_config = light_args.light_config.take();
+
+        let mut light_config: LightConfig = if let Some(document_path) = &config_document {
+            let config_contents = if document_path.as_os_str() == "-" {
+                io::read_to_string(io::stdin())?
+            } else {
+                read_to_string(document_path)?
+            };
+
+            parse(&config_contents)
+        } else if let Some(shared_path) = &shared_config {
+            let mut document = parse_document(&read_to_string(shared_path)?);
+
+            // Keys the user set win, tables are merged key by key
+            if let Ok(config_path) = Self::find(&user_config_path) {
+                let user_document = parse_document(&read_to_string(&config_path)?);
+                Self::merge_toml_tables(document.as_table_mut(), user_document.as_table());
+            }
 
-        let user_config_path = openmw_config.user_config_path();
+            parse(&document.to_string())
+        } else if let Ok(config_path) = Self::find(&user_config_path) {
+            let config_contents = read_to_string(&config_path)?;
+            let config = parse(&config_contents);
+            existing_config_path = Some(config_path);
+            config
+        } else {
+            write_config = true;
+            LightConfig::default()
+        };
 
-        let mut light_config: LightConfig = if let Ok(config_path) = Self::find(&user_config_path) {
-            let config_contents = read_to_string(config_path)?;
+        if let Some(preset_name) = light_args.preset.take() {
+            light_config.preset = Some(preset_name);
+        }
 
-            match toml::from_str(&config_contents) {
-                Ok(config) => config,
-                Err(e) => {
+        // Presets are applied on top of the config file, and CLI args on top of the preset
+        if let Some(preset_name) = light_config.preset.clone() {
+            let preset = match light_config.presets.get(&preset_name) {
+                Some(user_preset) => Some(user_preset.to_owned()),
+                None => Preset::builtin(&preset_name),
+            };
+
+            match preset {
+                Some(preset) => preset.apply(&mut light_config),
+                None => {
                     notification_box(
-                        "Failed to read light config!",
-                        &format!("Lightconfig.toml couldn't be read: {e}"),
-                        light_args.no_notifications,
+                        Message::UnknownPreset.text(),
+                        &Message::UnknownPresetBody
+                            .fill(&[&preset_name, &BUILTIN_PRESETS.join(", ")]),
                     );
-                    std::process::exit(256);
+                    crate::exit(ExitCode::InvalidConfig);
                 }
             }
-        } else {
-            write_config = true;
-            LightConfig::default()
-        };
+        }
 
         // Replace any values provided as CLI args in the config
         // use_classic will always override the standard_radius and disable_interior_sun
         Self::overwrite_if_some([
             (&mut light_config.standard_hue, &mut light_args.standard_hue),
+            (
+                &mut light_config.standard_hue_shift,
+                &mut light_args.standard_hue_shift,
+            ),
             (
                 &mut light_config.standard_saturation,
                 &mut light_args.standard_saturation,
@@ -164,6 +1023,10 @@ impl LightConfig {
                 &mut light_args.standard_radius,
             ),
             (&mut light_config.colored_hue, &mut light_args.colored_hue),
+            (
+                &mut light_config.colored_hue_shift,
+                &mut light_args.colored_hue_shift,
+            ),
             (
                 &mut light_config.colored_saturation,
                 &mut light_args.colored_saturation,
@@ -180,8 +1043,184 @@ impl LightConfig {
                 &mut light_config.duration_mult,
                 &mut light_args.duration_mult,
             ),
+            (
+                &mut light_config.mesh_reference_radius,
+                &mut light_args.mesh_reference_radius,
+            ),
+            (
+                &mut light_config.exterior_radius,
+                &mut light_args.exterior_radius,
+            ),
+            (
+                &mut light_config.interior_radius,
+                &mut light_args.interior_radius,
+            ),
+            (&mut light_config.radius_curve, &mut light_args.radius_curve),
+            (
+                &mut light_config.target_kelvin,
+                &mut light_args.target_kelvin,
+            ),
+            (
+                &mut light_config.kelvin_strength,
+                &mut light_args.kelvin_strength,
+            ),
+            (
+                &mut light_config.standard_target_strength,
+                &mut light_args.standard_target_strength,
+            ),
+            (
+                &mut light_config.colored_target_strength,
+                &mut light_args.colored_target_strength,
+            ),
         ]);
 
+        if light_args.standard_target_color.is_some() {
+            light_config.standard_target_color = light_args.standard_target_color.take();
+        }
+
+        if light_args.colored_target_color.is_some() {
+            light_config.colored_target_color = light_args.colored_target_color.take();
+        }
+
+        // Reported once here, rather than for every light
+        for (setting, target_color) in [
+            (
+                "standard_target_color",
+                &mut light_config.standard_target_color,
+            ),
+            (
+                "colored_target_color",
+                &mut light_config.colored_target_color,
+            ),
+        ] {
+            if target_color
+                .as_deref()
+                .is_some_and(|hex| crate::color::from_hex(hex).is_none())
+            {
+                log::warn!(
+                    "Ignoring invalid color `{}` in {setting}. Colors must be hex triplets, like #FFB46E",
+                    target_color.take().unwrap_or_default()
+                );
+            }
+        }
+
+        Self::overwrite_if_some([(
+            &mut light_config.decorative_radius,
+            &mut light_args.decorative_radius,
+        )]);
+
+        if light_args.mo2_profile.is_some() {
+            light_config.mo2_profile = light_args.mo2_profile.take();
+        }
+
+        if light_args.data_local.is_some() {
+            light_config.data_local = light_args.data_local.take();
+        }
+
+        if light_args.emit_lua_package.is_some() {
+            light_config.lua_package_dir = light_args.emit_lua_package.take();
+        }
+
+        if !light_args.skip_data_dir.is_empty() {
+            light_config.skip_data_dirs = std::mem::take(&mut light_args.skip_data_dir);
+        }
+
+        if !light_args.fallback_archive.is_empty() {
+            light_config.fallback_archives = std::mem::take(&mut light_args.fallback_archive);
+        }
+
+        if light_args.language.is_some() {
+            light_config.language = light_args.language;
+        }
+
+        if light_args.log_level.is_some() {
+            light_config.log_level = light_args.log_level;
+        }
+
+        if light_args.load_threads.is_some() {
+            light_config.load_threads = light_args.load_threads;
+        }
+
+        if let Some(author) = light_args.author.take() {
+            light_config.author = author;
+        }
+
+        if light_args.description.is_some() {
+            light_config.description = light_args.description.take();
+        }
+
+        if let Some(backup_count) = light_args.backup_count {
+            light_config.backup_count = backup_count;
+        }
+
+        if light_args.max_radius.is_some() {
+            light_config.max_radius = light_args.max_radius;
+        }
+
+        if light_args.min_radius.is_some() {
+            light_config.min_radius = light_args.min_radius;
+        }
+
+        if light_args.min_perceived_brightness.is_some() {
+            light_config.min_perceived_brightness = light_args.min_perceived_brightness;
+        }
+
+        Self::overwrite_if_some([(
+            &mut light_config.transform_mode,
+            &mut light_args.transform_mode,
+        )]);
+
+        Self::overwrite_if_some([(&mut light_config.radius_mode, &mut light_args.radius_mode)]);
+
+        // A bare --write-log keeps the format from lightconfig.toml
+        if let Some(log_format) = light_args.write_log {
+            light_config.save_log = true;
+
+            if let Some(log_format) = log_format {
+                light_config.log_format = log_format;
+            }
+        }
+
+        Self::overwrite_if_some([(
+            &mut light_config.color_rounding,
+            &mut light_args.color_rounding,
+        )]);
+        Self::overwrite_if_some([(&mut light_config.color_space, &mut light_args.color_space)]);
+
+        Self::overwrite_if_some([(
+            &mut light_config.merge_strategy,
+            &mut light_args.merge_strategy,
+        )]);
+        Self::overwrite_if_some([(
+            &mut light_config.master_strategy,
+            &mut light_args.master_strategy,
+        )]);
+        Self::overwrite_if_some([(
+            &mut light_config.target_engine,
+            &mut light_args.target_engine,
+        )]);
+        Self::overwrite_if_some([(
+            &mut light_config.mixed_placement_radius,
+            &mut light_args.mixed_placement_radius,
+        )]);
+
+        if light_args.max_masters.is_some() {
+            light_config.max_masters = light_args.max_masters;
+        }
+
+        Self::overwrite_if_some([(&mut light_config.split_output, &mut light_args.split_output)]);
+        Self::overwrite_if_some([(&mut light_config.only, &mut light_args.only)]);
+        Self::overwrite_if_some([(&mut light_config.cell_records, &mut light_args.cell_records)]);
+        Self::overwrite_if_some([(
+            &mut light_config.on_plugin_error,
+            &mut light_args.on_plugin_error,
+        )]);
+
+        Self::overwrite_if_some([(
+            &mut light_config.output_format,
+            &mut light_args.output_format,
+        )]);
+
         Self::overwrite_if_some([
             (
                 &mut light_config.disable_pulse,
@@ -191,14 +1230,6 @@ impl LightConfig {
                 &mut light_config.disable_flickering,
                 &mut light_args.disable_flickering,
             ),
-            (
-                &mut light_config.save_log,
-                &mut if light_args.write_log {
-                    Some(light_args.write_log)
-                } else {
-                    None
-                },
-            ),
             (
                 &mut light_config.auto_enable,
                 &mut if light_args.auto_enable {
@@ -215,6 +1246,118 @@ impl LightConfig {
                     None
                 },
             ),
+            (
+                &mut light_config.desktop_notifications,
+                &mut if light_args.desktop_notifications {
+                    Some(light_args.desktop_notifications)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.quiet,
+                &mut if light_args.quiet {
+                    Some(light_args.quiet)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.json_logs,
+                &mut if light_args.json_logs {
+                    Some(light_args.json_logs)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.remove_light_sounds,
+                &mut if light_args.remove_light_sounds {
+                    Some(light_args.remove_light_sounds)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.mesh_aware_radius,
+                &mut if light_args.mesh_aware_radius {
+                    Some(light_args.mesh_aware_radius)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.skip_scripted_lights,
+                &mut if light_args.skip_scripted_lights {
+                    Some(light_args.skip_scripted_lights)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.fit_lighting_method,
+                &mut if light_args.fit_lighting_method {
+                    Some(light_args.fit_lighting_method)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.performance_mode,
+                &mut if light_args.performance_mode {
+                    Some(light_args.performance_mode)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.process_groundcover,
+                &mut if light_args.process_groundcover {
+                    Some(light_args.process_groundcover)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.preserve_edits,
+                &mut if light_args.preserve_edits {
+                    Some(light_args.preserve_edits)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.mark_modified,
+                &mut if light_args.mark_modified {
+                    Some(light_args.mark_modified)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.strict_parse,
+                &mut if light_args.strict_parse {
+                    Some(light_args.strict_parse)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.emit_deletions,
+                &mut if light_args.emit_deletions {
+                    Some(light_args.emit_deletions)
+                } else {
+                    None
+                },
+            ),
+            (
+                &mut light_config.write_metadata,
+                &mut if light_args.write_metadata {
+                    Some(light_args.write_metadata)
+                } else {
+                    None
+                },
+            ),
             (
                 &mut light_config.debug,
                 &mut if light_args.debug {
@@ -227,28 +1370,49 @@ impl LightConfig {
 
         light_config.no_notifications |= std::env::var("S3L_NO_NOTIFICATIONS").is_ok();
         light_config.debug |= std::env::var("S3L_DEBUG").is_ok();
+        light_config.desktop_notifications |= std::env::var("S3L_DESKTOP_NOTIFICATIONS").is_ok();
+
+        if light_args.notify.is_some() {
+            light_config.notify = light_args.notify;
+        }
+
+        // Every --output after the first is a copy, replacing those from the config
+        if light_args.output.len() > 1 {
+            light_config.outputs = light_args.output.split_off(1);
+        }
+
+        for copy_dir in &light_config.outputs {
+            if !copy_dir.is_dir() {
+                notification_box(
+                    Message::OutputMissing.text(),
+                    &Message::OutputMissingBody.fill(&[&format!("{copy_dir:?}")]),
+                );
+                crate::exit(ExitCode::BadOutputPath)
+            }
+        }
 
         // If an output directory was specified via CLI, that should override config options
         // If the provided path is valid
-        if let Some(out_dir) = light_args.output {
+        if let Some(out_dir) = light_args.output.pop() {
             if out_dir.is_dir() {
                 light_config.output_dir = Some(out_dir);
             } else {
                 notification_box(
-                    "Can't find output location!",
-                    &format!(
-                        "WARNING: The requested output path {out_dir:?} does not exist! Terminating."
-                    ),
-                    light_config.no_notifications,
+                    Message::OutputMissing.text(),
+                    &Message::OutputMissingBody.fill(&[&format!("{out_dir:?}")]),
                 );
-                std::process::exit(1)
+                crate::exit(ExitCode::BadOutputPath)
             }
         // Otherwise, if there is neither an output directory specified by the config nor the CLI, use the default location,
         // Being data-local, if defined by the current openmw.cfg, or the current working directory
         } else if let None = light_config.output_dir {
-            light_config.output_dir = Some(match openmw_config.data_local() {
-                Some(path) => path.parsed().to_owned(),
-                None => std::env::current_dir().expect("Failed to get workdir!"),
+            light_config.output_dir = Some(if light_args.portable {
+                user_config_path.to_owned()
+            } else {
+                match openmw_config.and_then(|openmw_config| openmw_config.data_local()) {
+                    Some(path) => path.parsed().to_owned(),
+                    None => std::env::current_dir()?,
+                }
             });
         };
 
@@ -277,29 +1441,106 @@ impl LightConfig {
             light_config.disable_interior_sun = true;
         }
 
+        // Checked once CLI args are in, and before saving, so a bad value never ends up in lightconfig.toml
+        let invalid_settings: Vec<String> = light_config
+            .negative_multipliers()
+            .into_iter()
+            .chain(light_config.conflicting_settings())
+            .collect();
+        if !invalid_settings.is_empty() {
+            notification_box(
+                Message::InvalidLightConfig.text(),
+                &Message::InvalidLightConfigBody.fill(&[&invalid_settings.join("\n")]),
+            );
+            crate::exit(ExitCode::InvalidConfig);
+        }
+
         // If the configuration file didn't exist when we tried to find it, or the user specified to update
         // serialize it here
-        if write_config || light_config.save_config || light_args.update_light_config {
-            let config_serialized = toml::to_string_pretty(&light_config).map_err(to_io_error)?;
+        // With --light-config, saving would copy the shared settings into the user's file, where they'd override later changes
+        if config_document.is_none()
+            && shared_config.is_none()
+            && (write_config || light_config.save_config || light_args.update_light_config)
+        {
+            let config_path = existing_config_path
+                .clone()
+                .unwrap_or_else(|| user_config_path.join(DEFAULT_CONFIG_NAME));
+            light_config.save_to(&config_path)?;
+        }
+
+        // --save-config writes exactly what this run uses, wherever its settings were read from
+        // Without a path it goes to the user's own lightconfig.toml, never the --config or --light-config file
+        if let Some(save_path) = light_args.save_config.take() {
+            let config_path = save_path
+                .or(existing_config_path)
+                .or_else(|| Self::find(&user_config_path).ok())
+                .unwrap_or_else(|| user_config_path.join(DEFAULT_CONFIG_NAME));
+            light_config.save_to(&config_path)?;
+        }
+
+        // Override packs are merged only after saving, so they never end up in lightconfig.toml
+        // Entries from lightconfig.toml and the CLI take priority, then later packs over earlier ones
+        let mut override_packs: Vec<PathBuf> = Self::find_named(&user_config_path, OVERRIDES_NAME)
+            .into_iter()
+            .collect();
+        override_packs.extend(std::mem::take(&mut light_args.override_packs));
+
+        for pack_path in override_packs.iter().rev() {
+            let pack = OverridePack::from_path(pack_path)?;
 
-            let config_path = user_config_path.join(DEFAULT_CONFIG_NAME);
-            let mut config_file = File::create(config_path)?;
-            write!(config_file, "{}", config_serialized)?;
+            for (id, light_data) in pack.light_overrides {
+                light_config.light_overrides.entry(id).or_insert(light_data);
+            }
+
+            for (id, ambient_data) in pack.ambient_overrides {
+                light_config
+                    .ambient_overrides
+                    .entry(id)
+                    .or_insert(ambient_data);
+            }
         }
 
+        light_config.config_dir = user_config_path;
+
+        // Sound ids are compared case-insensitively, like every other record id
+        light_config.light_sound_replacements =
+            std::mem::take(&mut light_config.light_sound_replacements)
+                .into_iter()
+                .map(|(sound, replacement)| (sound.to_ascii_lowercase(), replacement))
+                .collect();
+
+        light_config.inventory_light_swaps =
+            std::mem::take(&mut light_config.inventory_light_swaps)
+                .into_iter()
+                .map(|(light_id, replacement)| (light_id.to_lowercase(), replacement))
+                .collect();
+
+        light_config.regions = std::mem::take(&mut light_config.regions)
+            .into_iter()
+            .map(|(region, multipliers)| (region.to_lowercase(), multipliers))
+            .collect();
+
         // Consume the original values *after* reserializing the config
         std::mem::take(&mut light_config.excluded_ids)
             .into_iter()
             .for_each(|id| {
-                if let Ok(pattern) = regex::Regex::new(&id) {
+                if let Some(pattern) = Self::compile_pattern(&id, "excluded_ids") {
                     light_config.excluded_id_regexes.push(pattern);
                 }
             });
 
+        std::mem::take(&mut light_config.decorative_ids)
+            .into_iter()
+            .for_each(|id| {
+                if let Some(pattern) = Self::compile_pattern(&id, "decorative_ids") {
+                    light_config.decorative_id_regexes.push(pattern);
+                }
+            });
+
         std::mem::take(&mut light_config.excluded_plugins)
             .into_iter()
             .for_each(|id| {
-                if let Ok(pattern) = regex::Regex::new(&id) {
+                if let Some(pattern) = Self::compile_pattern(&id, "excluded_plugins") {
                     light_config.excluded_plugin_regexes.push(pattern);
                 }
             });
@@ -307,20 +1548,27 @@ impl LightConfig {
         std::mem::take(&mut light_config.light_overrides)
             .into_iter()
             .for_each(|(id, light_data)| {
-                if let Ok(pattern) = regex::Regex::new(&id) {
+                if let Some(pattern) = Self::compile_pattern(&id, "light_overrides") {
                     light_config.light_regexes.push((pattern, light_data));
-                } // Handle bad patterns and bail here
-                // Later
+                }
             });
 
-        std::mem::take(&mut light_config.ambient_overrides)
-            .into_iter()
-            .for_each(|(id, light_data)| {
-                if let Ok(pattern) = regex::Regex::new(&id) {
-                    light_config.ambient_regexes.push((pattern, light_data));
-                } // Handle bad patterns and bail here
-                // Later
-            });
+        // Cells matched by several patterns take each value from the most specific one,
+        // so patterns are applied from shortest to longest
+        let mut ambient_overrides: Vec<(String, CustomCellAmbient)> =
+            std::mem::take(&mut light_config.ambient_overrides)
+                .into_iter()
+                .collect();
+        let pattern_len =
+            |pattern: &str| pattern.strip_prefix(GLOB_PREFIX).unwrap_or(pattern).len();
+        ambient_overrides
+            .sort_by(|(a, _), (b, _)| pattern_len(a).cmp(&pattern_len(b)).then_with(|| a.cmp(b)));
+
+        ambient_overrides.into_iter().for_each(|(id, light_data)| {
+            if let Some(pattern) = Self::compile_pattern(&id, "ambient_overrides") {
+                light_config.ambient_regexes.push((pattern, light_data));
+            }
+        });
 
         Ok(light_config)
     }
@@ -340,6 +1588,183 @@ impl LightConfig {
         false
     }
 
+    /// Radius, hue, saturation, and value multipliers for a colored light of the given hue
+    pub fn colored_multipliers(&self, hue_degrees: f32) -> (f32, f32, f32, f32) {
+        let bucket = self
+            .colored
+            .for_hue(hue_degrees)
+            .copied()
+            .unwrap_or_default();
+
+        (
+            bucket.radius.unwrap_or(self.colored_radius),
+            bucket.hue.unwrap_or(self.colored_hue),
+            bucket.saturation.unwrap_or(self.colored_saturation),
+            bucket.value.unwrap_or(self.colored_value),
+        )
+    }
+
+    /// The color lights in the given bucket are pulled toward, and how strongly, if one is set
+    pub fn target_color(&self, is_colored: bool) -> Option<(palette::Srgb, f32)> {
+        let (hex, strength) = if is_colored {
+            (&self.colored_target_color, self.colored_target_strength)
+        } else {
+            (&self.standard_target_color, self.standard_target_strength)
+        };
+
+        let [red, green, blue] = crate::color::from_hex(hex.as_deref()?)?;
+
+        Some((palette::Srgb::new(red, green, blue).into_format(), strength))
+    }
+
+    /// Bounds a multiplied radius by min_radius and max_radius, if they're set
+    pub fn clamp_radius(&self, radius: u32) -> u32 {
+        let radius = self.min_radius.map_or(radius, |min| radius.max(min));
+        self.max_radius.map_or(radius, |max| radius.min(max))
+    }
+
+    /// Adds light overrides to lightconfig.toml, replacing any existing ones for the same patterns
+    /// The rest of the file is left as it was, comments included
+    /// Returns the path which was written
+    pub fn add_light_overrides(
+        &self,
+        light_overrides: HashMap<String, CustomLightData>,
+    ) -> io::Result<PathBuf> {
+        #[derive(Serialize)]
+        struct OverridesOnly {
+            light_overrides: HashMap<String, CustomLightData>,
+        }
+
+        let config_path = Self::find(&self.config_dir)
+            .unwrap_or_else(|_| self.config_dir.join(DEFAULT_CONFIG_NAME));
+
+        let mut document: DocumentMut = match read_to_string(&config_path) {
+            Ok(contents) => contents.parse().map_err(to_io_error)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => DocumentMut::new(),
+            Err(err) => return Err(err),
+        };
+
+        let updated: DocumentMut = toml::to_string_pretty(&OverridesOnly { light_overrides })
+            .map_err(to_io_error)?
+            .parse()
+            .map_err(to_io_error)?;
+
+        Self::merge_toml_tables(document.as_table_mut(), updated.as_table());
+
+        let mut config_file = File::create(&config_path)?;
+        write!(config_file, "{}", document)?;
+
+        Ok(config_path)
+    }
+
+    /// Which backend messages should be sent to, accounting for the older boolean switches
+    pub fn notify_backend(&self) -> NotifyBackend {
+        NotifyBackend::resolve(
+            self.notify,
+            self.desktop_notifications,
+            self.no_notifications,
+        )
+    }
+
+    /// Turns off settings whose output target_engine can't load, returning a warning for each
+    pub fn fit_target_engine(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if self.cell_records == CellRecordContents::Atmosphere
+            && !self.target_engine.merges_cell_fields()
+        {
+            self.cell_records = CellRecordContents::Full;
+            warnings.push("cell_records = \"atmosphere\" needs OpenMW 0.49 or later, which keeps the fields a cell record leaves out. Full cell records are written instead.".to_string());
+        }
+
+        if self.lua_package_dir.is_some() && !self.target_engine.creates_lua_records() {
+            self.lua_package_dir = None;
+            warnings.push("The Lua package needs OpenMW 0.49 or later, as earlier releases can't create light records from Lua. It won't be written.".to_string());
+        }
+
+        warnings
+    }
+
+    /// Checks radius multipliers against the lighting method in settings.cfg, returning a warning for each which works against it
+    /// With fit_lighting_method, they're lowered to what the lighting method can show instead
+    pub fn fit_lighting_method(&mut self, lighting: &LightingSettings) -> Vec<String> {
+        if lighting.lighting_method != LightingMethod::Legacy {
+            return Vec::new();
+        }
+
+        let fit = self.fit_lighting_method;
+        let mut warnings = Vec::new();
+
+        let mut radii: Vec<(String, &mut f32)> = vec![
+            ("standard_radius".to_string(), &mut self.standard_radius),
+            ("colored_radius".to_string(), &mut self.colored_radius),
+        ];
+
+        for (bucket_name, bucket) in [
+            ("red", &mut self.colored.red),
+            ("green", &mut self.colored.green),
+            ("blue", &mut self.colored.blue),
+            ("purple", &mut self.colored.purple),
+        ] {
+            if let Some(radius) = bucket.as_mut().and_then(|bucket| bucket.radius.as_mut()) {
+                radii.push((format!("colored.{bucket_name}.radius"), radius));
+            }
+        }
+
+        for (name, radius) in radii {
+            if *radius <= LEGACY_MAX_RADIUS {
+                continue;
+            }
+
+            if fit {
+                warnings.push(format!("{name} was lowered from {radius} to {LEGACY_MAX_RADIUS}, as settings.cfg uses legacy lighting."));
+                *radius = LEGACY_MAX_RADIUS;
+            } else {
+                warnings.push(format!("{name} is {radius}, but settings.cfg uses legacy lighting, which shows at most {} lights on each object. Larger radii make lights pop in and out as you move. Switch the lighting method to shaders, or use --fit-lighting-method to lower it to {LEGACY_MAX_RADIUS}.", lighting.lights_per_object()));
+            }
+        }
+
+        warnings
+    }
+
+    /// Whether lights need to be traced to the cells they're placed in for exterior_radius and interior_radius
+    pub fn uses_placement_radius(&self) -> bool {
+        self.exterior_radius != 1.0 || self.interior_radius != 1.0
+    }
+
+    /// The radius multiplier for a light placed as described by `placement`
+    pub fn placement_radius(&self, placement: Placement) -> f32 {
+        match (placement.exterior, placement.interior) {
+            (true, false) => self.exterior_radius,
+            (false, true) => self.interior_radius,
+            (true, true) => match self.mixed_placement_radius {
+                MixedPlacementRadius::Larger => self.exterior_radius.max(self.interior_radius),
+                MixedPlacementRadius::Smaller => self.exterior_radius.min(self.interior_radius),
+                MixedPlacementRadius::Exterior => self.exterior_radius,
+                MixedPlacementRadius::Interior => self.interior_radius,
+            },
+            (false, false) => 1.0,
+        }
+    }
+
+    /// Whether performance_mode should turn this light off
+    /// `original_radius` is the light's radius as the load order has it, before any multiplier or model scaling
+    pub fn is_decorative(&self, light: &Light, original_radius: u32) -> bool {
+        if !self.performance_mode || light.data.flags.contains(LightFlags::CAN_CARRY) {
+            return false;
+        }
+
+        if original_radius < self.decorative_radius {
+            return true;
+        }
+
+        let light_id = self.encoding.record_id(&light.id);
+
+        self.decorative_id_regexes
+            .iter()
+            .any(|pattern| pattern.is_match(&light_id))
+    }
+
     pub fn is_excluded_id(&self, record_id: &str) -> bool {
         for pattern in &self.excluded_id_regexes {
             if pattern.is_match(record_id) {
@@ -356,30 +1781,106 @@ impl Default for LightConfig {
         LightConfig {
             save_config: false,
             debug: false,
+            log_level: None,
+            quiet: false,
+            json_logs: false,
             no_notifications: false,
+            desktop_notifications: false,
+            notify: None,
+            language: None,
             output_dir: None,
+            fallback_output_dirs: Vec::new(),
+            outputs: Vec::new(),
+            data_local: None,
+            skip_data_dirs: Vec::new(),
+            fallback_archives: Vec::new(),
+            mo2_profile: None,
+            encoding: Encoding::default(),
+            output_format: OutputFormat::default(),
+            preserve_edits: false,
+            write_metadata: false,
+            author: default::author(),
+            description: None,
+            backup_count: default::backup_count(),
+            emit_deletions: false,
+            cell_records: CellRecordContents::default(),
+            self_update: false,
+            strict_parse: false,
+            load_threads: None,
+            mark_modified: false,
+            config_dir: PathBuf::new(),
             disable_interior_sun: false,
             disable_flickering: default::disable_flicker(),
             disable_pulse: default::disable_pulse(),
             save_log: default::save_log(),
+            log_format: LogFormat::default(),
             auto_enable: default::auto_enable(),
             standard_hue: default::standard_hue(),
+            standard_hue_shift: 0.0,
             standard_saturation: default::standard_saturation(),
             standard_value: default::standard_value(),
             standard_radius: default::standard_radius(),
             colored_hue: default::colored_hue(),
+            colored_hue_shift: 0.0,
             colored_saturation: default::colored_saturation(),
             colored_value: default::colored_value(),
             colored_radius: default::colored_radius(),
+            colored: ColoredBuckets::default(),
             duration_mult: default::duration_mult(),
+            carried_light_flags: CarriedLightFlags::default(),
+            carryable: Carryable::default(),
+            interior_fog: InteriorFog::default(),
+            quasi_exterior: QuasiExterior::default(),
+            exterior_nights: ExteriorNights::default(),
+            remove_light_sounds: false,
+            light_sound_replacements: HashMap::new(),
+            inventory_light_swaps: HashMap::new(),
+            regions: HashMap::new(),
+            classes: HashMap::new(),
+            mesh_aware_radius: false,
+            skip_scripted_lights: false,
+            target_engine: TargetEngine::default(),
+            fit_lighting_method: false,
+            lua_package_dir: None,
+            process_groundcover: false,
+            performance_mode: false,
+            decorative_ids: Vec::new(),
+            decorative_radius: default::decorative_radius(),
+            mesh_reference_radius: default::mesh_reference_radius(),
+            exterior_radius: default::exterior_radius(),
+            interior_radius: default::interior_radius(),
+            mixed_placement_radius: MixedPlacementRadius::default(),
+            max_radius: None,
+            min_radius: None,
+            min_perceived_brightness: None,
+            transform_mode: TransformMode::default(),
+            radius_mode: RadiusMode::default(),
+            radius_curve: default::radius_curve(),
+            color_rounding: ColorRounding::default(),
+            color_space: ColorSpace::default(),
+            merge_strategy: MergeStrategy::default(),
+            master_strategy: MasterStrategy::default(),
+            max_masters: None,
+            split_output: SplitOutput::default(),
+            only: RecordCategories::default(),
+            on_plugin_error: PluginErrorPolicy::default(),
+            target_kelvin: default::target_kelvin(),
+            kelvin_strength: default::kelvin_strength(),
+            standard_target_color: None,
+            standard_target_strength: default::target_color_strength(),
+            colored_target_color: None,
+            colored_target_strength: default::target_color_strength(),
             excluded_ids: Vec::new(),
             excluded_plugins: default::excluded_plugins(),
             excluded_id_regexes: Vec::new(),
             excluded_plugin_regexes: Vec::new(),
+            decorative_id_regexes: Vec::new(),
             light_regexes: Vec::new(),
             light_overrides: HashMap::new(),
             ambient_overrides: HashMap::new(),
             ambient_regexes: Vec::new(),
+            preset: None,
+            presets: HashMap::new(),
         }
     }
 }
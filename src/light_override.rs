@@ -168,9 +168,21 @@ impl FromStr for CustomLightData {
                     data.value = Some(parsed.clamp(0.0, 1.0))
                 }
                 "flag" => {
+                    if let Some(_) = data.animation {
+                        return Err(ParseLightError::ExclusiveFields("animation", "flag"));
+                    }
+
                     let parsed: LightFlag = v.parse()?;
                     data.flag = Some(parsed);
                 }
+                "animation" => {
+                    if let Some(_) = data.flag {
+                        return Err(ParseLightError::ExclusiveFields("flag", "animation"));
+                    }
+
+                    let parsed: LightFlag = v.parse()?;
+                    data.animation = Some(parsed);
+                }
                 _ => return Err(ParseLightError::UnknownField(k.to_owned())),
             }
             Ok(())
@@ -211,6 +223,7 @@ struct RawCustomLightData {
     duration: Option<f32>,
     duration_mult: Option<f32>,
     flag: Option<LightFlag>,
+    animation: Option<LightFlag>,
 }
 
 impl<'de> serde::Deserialize<'de> for CustomLightData {
@@ -238,6 +251,7 @@ impl<'de> serde::Deserialize<'de> for CustomLightData {
         check_exclusive!(value, value_mult);
         check_exclusive!(radius, radius_mult);
         check_exclusive!(duration, duration_mult);
+        check_exclusive!(flag, animation);
 
         Ok(CustomLightData {
             hue: raw.hue.map(|h| h.clamp(0, 360)),
@@ -251,6 +265,7 @@ impl<'de> serde::Deserialize<'de> for CustomLightData {
             duration: raw.duration,
             duration_mult: raw.duration_mult,
             flag: raw.flag,
+            animation: raw.animation,
         })
     }
 }
@@ -267,7 +282,10 @@ pub struct CustomLightData {
     pub radius_mult: Option<f32>,
     pub duration: Option<f32>,
     pub duration_mult: Option<f32>,
+    /// Replaces every flag of the light, leaving only this animation
     pub flag: Option<LightFlag>,
+    /// Swaps only the animation, keeping flags such as can_carry or negative
+    pub animation: Option<LightFlag>,
 }
 
 #[derive(Clone, Debug, Default, Serialize)]
@@ -286,6 +304,21 @@ struct RawTypedLightColor {
     pub value: f32,
 }
 
+impl TypedLightColor {
+    /// The color as stored in cell records
+    pub fn to_rgba(&self) -> [u8; 4] {
+        let hsv: palette::Hsv = palette::Hsv::from_components((
+            palette::RgbHue::from_degrees(self.hue as f32),
+            self.saturation,
+            self.value,
+        ));
+        let rgb8_color: palette::rgb::Srgb<u8> =
+            palette::IntoColor::<palette::rgb::Srgb>::into_color(hsv).into_format();
+
+        [rgb8_color.red, rgb8_color.green, rgb8_color.blue, 0]
+    }
+}
+
 impl<'de> serde::Deserialize<'de> for TypedLightColor {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -457,7 +490,7 @@ pub enum LightFlag {
     NONE,
 }
 
-use tes3::esp::LightFlags;
+use tes3::esp::{LightData, LightFlags};
 impl LightFlag {
     pub fn to_esp_flag(&self) -> LightFlags {
         match &self {
@@ -468,6 +501,94 @@ impl LightFlag {
             Self::NONE => LightFlags::empty(),
         }
     }
+
+    /// Every flag controlling how a light animates, which are replaced when an animation override is used
+    pub fn animation_flags() -> LightFlags {
+        LightFlags::FLICKER | LightFlags::FLICKER_SLOW | LightFlags::PULSE | LightFlags::PULSE_SLOW
+    }
+
+    /// The animation a light record uses, ignoring flags unrelated to animation
+    pub fn from_esp_flags(flags: LightFlags) -> LightFlag {
+        if flags.contains(LightFlags::FLICKER_SLOW) {
+            Self::FLICKERSLOW
+        } else if flags.contains(LightFlags::FLICKER) {
+            Self::FLICKER
+        } else if flags.contains(LightFlags::PULSE_SLOW) {
+            Self::PULSESLOW
+        } else if flags.contains(LightFlags::PULSE) {
+            Self::PULSE
+        } else {
+            Self::NONE
+        }
+    }
+}
+
+/// Whether a flag should be added to or removed from a light
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FlagPolicy {
+    Set,
+    Clear,
+}
+
+/// Flags to add to or remove from every carryable light
+/// Flags left unset are kept as the original plugin had them
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct CarriedLightFlags {
+    /// Whether the light affects objects other than the one holding it
+    pub dynamic: Option<FlagPolicy>,
+    /// Whether the light can be picked up at all
+    pub can_carry: Option<FlagPolicy>,
+    /// Whether the light is put out by water
+    pub fire: Option<FlagPolicy>,
+    /// Whether the light starts out unlit
+    pub off_by_default: Option<FlagPolicy>,
+}
+
+impl CarriedLightFlags {
+    pub fn apply(&self, flags: &mut LightFlags) {
+        for (policy, flag) in [
+            (self.dynamic, LightFlags::DYNAMIC),
+            (self.can_carry, LightFlags::CAN_CARRY),
+            (self.fire, LightFlags::FIRE),
+            (self.off_by_default, LightFlags::OFF_BY_DEFAULT),
+        ] {
+            match policy {
+                Some(FlagPolicy::Set) => flags.insert(flag),
+                Some(FlagPolicy::Clear) => flags.remove(flag),
+                None => {}
+            }
+        }
+    }
+}
+
+/// Normalizes the weight and value of every carryable light, after its duration is adjusted
+/// Fields left unset are kept as the original plugin had them
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Carryable {
+    /// Heaviest a carryable light may be
+    pub max_weight: Option<f32>,
+    /// Sets the value of each light to this much gold per unit of its duration, rounded up,
+    /// so that lights which burn longer cost more
+    pub value_per_duration: Option<f32>,
+    /// Most gold a carryable light may be worth, applied after value_per_duration
+    pub max_value: Option<u32>,
+}
+
+impl Carryable {
+    pub fn apply(&self, data: &mut LightData) {
+        if let Some(max_weight) = self.max_weight {
+            data.weight = data.weight.min(max_weight);
+        }
+
+        if let Some(value_per_duration) = self.value_per_duration {
+            data.value = (data.time.max(0) as f32 * value_per_duration).ceil() as u32;
+        }
+
+        if let Some(max_value) = self.max_value {
+            data.value = data.value.min(max_value);
+        }
+    }
 }
 
 impl FromStr for LightFlag {
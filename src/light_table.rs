@@ -0,0 +1,124 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use palette::{FromColor, Hsv, rgb::Srgb};
+use serde::{Deserialize, Serialize};
+use tes3::esp::Light;
+
+use crate::{CustomLightData, color, light_override::LightFlag, to_io_error};
+
+/// File formats light tables can be written in
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum TableFormat {
+    /// Comma-separated values, for spreadsheets
+    #[default]
+    Csv,
+    /// A JSON array of objects, one per light
+    Json,
+}
+
+impl TableFormat {
+    /// Guesses the format of a table from its file extension, defaulting to CSV
+    pub fn from_path(path: &Path) -> TableFormat {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => TableFormat::Json,
+            _ => TableFormat::Csv,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// One light as it appears in an exported table
+/// Only the `new_*`, `duration`, and `flag` columns are read back on import
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LightRow {
+    pub plugin: String,
+    pub id: String,
+    pub original_color: String,
+    pub new_color: String,
+    pub original_radius: u32,
+    pub new_radius: u32,
+    pub duration: i32,
+    pub flag: LightFlag,
+}
+
+impl LightRow {
+    pub fn new(plugin: &str, original: &Light, fixed: &Light) -> LightRow {
+        LightRow {
+            plugin: plugin.to_owned(),
+            id: original.id.to_owned(),
+            original_color: color::to_hex(original.data.color),
+            new_color: color::to_hex(fixed.data.color),
+            original_radius: original.data.radius,
+            new_radius: fixed.data.radius,
+            duration: fixed.data.time,
+            flag: LightFlag::from_esp_flags(fixed.data.flags),
+        }
+    }
+
+    /// Converts an edited row into a light override matching exactly this record id
+    pub fn to_override(&self) -> io::Result<(String, CustomLightData)> {
+        let [red, green, blue] = color::from_hex(&self.new_color).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid color `{}` for light {}", self.new_color, self.id),
+            )
+        })?;
+
+        let hsv: Hsv = Hsv::from_color(Srgb::new(red, green, blue).into_format::<f32>());
+
+        let light_data = CustomLightData {
+            hue: Some(hsv.hue.into_positive_degrees().round() as u32),
+            saturation: Some(hsv.saturation),
+            value: Some(hsv.value),
+            radius: Some(self.new_radius),
+            duration: Some(self.duration as f32),
+            animation: Some(self.flag.to_owned()),
+            ..Default::default()
+        };
+
+        Ok((format!("^{}$", regex::escape(&self.id)), light_data))
+    }
+}
+
+pub fn write_table<W: Write>(rows: &[LightRow], format: TableFormat, writer: W) -> io::Result<()> {
+    match format {
+        TableFormat::Csv => {
+            let mut csv_writer = csv::Writer::from_writer(writer);
+
+            for row in rows {
+                csv_writer.serialize(row).map_err(to_io_error)?;
+            }
+
+            csv_writer.flush()
+        }
+        TableFormat::Json => serde_json::to_writer_pretty(writer, rows).map_err(to_io_error),
+    }
+}
+
+pub fn read_table<R: Read>(format: TableFormat, reader: R) -> io::Result<Vec<LightRow>> {
+    match format {
+        TableFormat::Csv => csv::Reader::from_reader(reader)
+            .deserialize()
+            .collect::<Result<Vec<LightRow>, _>>()
+            .map_err(to_io_error),
+        TableFormat::Json => serde_json::from_reader(reader).map_err(to_io_error),
+    }
+}
+
+/// Reads a table exported by lightfixes and turns every row into a light override
+pub fn import_table(path: &Path) -> io::Result<HashMap<String, CustomLightData>> {
+    let rows = read_table(TableFormat::from_path(path), File::open(path)?)?;
+
+    rows.iter().map(LightRow::to_override).collect()
+}
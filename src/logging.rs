@@ -0,0 +1,173 @@
+use std::{
+    fs::{File, rename},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record, kv::Key};
+
+/// Log of the most recent run, written next to lightconfig.toml
+/// Not to be confused with LOG_NAME and CHANGE_LOG_NAME, which describe the generated plugin
+pub const RUN_LOG_NAME: &str = "s3lightfixes.log";
+
+/// Logs of earlier runs kept alongside the current one, as s3lightfixes.log.1 and so on
+const KEPT_RUN_LOGS: usize = 3;
+
+/// Records with this target are only written to the run log,
+/// as they've already been shown to the user some other way
+pub const NOTIFICATION_TARGET: &str = "s3lightfixes::notification";
+
+/// Key of the plugin a message is about, for messages logged with `plugin = name;`
+pub const PLUGIN_KEY: &str = "plugin";
+
+struct LoggerState {
+    console_level: LevelFilter,
+    /// Print each record to the console as a line of JSON, notifications included
+    json: bool,
+    file: Option<File>,
+    /// Lines logged before the run log could be opened, written out once it is
+    pending: Vec<String>,
+}
+
+struct RunLogger {
+    state: Mutex<LoggerState>,
+}
+
+static LOGGER: RunLogger = RunLogger {
+    state: Mutex::new(LoggerState {
+        console_level: LevelFilter::Warn,
+        json: false,
+        file: None,
+        pending: Vec::new(),
+    }),
+};
+
+impl Log for RunLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        let is_notification = record.target() == NOTIFICATION_TARGET;
+
+        if state.json {
+            if record.level() <= state.console_level || is_notification {
+                let plugin = record
+                    .key_values()
+                    .get(Key::from_str(PLUGIN_KEY))
+                    .map(|plugin| plugin.to_string());
+
+                eprintln!(
+                    "{}",
+                    serde_json::json!({
+                        "timestamp": timestamp,
+                        "level": record.level().as_str().to_ascii_lowercase(),
+                        "plugin": plugin,
+                        "message": record.args().to_string(),
+                    })
+                );
+            }
+        } else if record.level() <= state.console_level && !is_notification {
+            match record.level() {
+                Level::Error => eprintln!("[ ERROR ]: {}", record.args()),
+                Level::Warn => eprintln!("[ WARNING ]: {}", record.args()),
+                _ => eprintln!("{}", record.args()),
+            }
+        }
+
+        let line = format!(
+            "{timestamp} {:<5} {}: {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        match &mut state.file {
+            Some(file) => {
+                let _ = writeln!(file, "{line}");
+            }
+            None => state.pending.push(line),
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut state) = self.state.lock()
+            && let Some(file) = &mut state.file
+        {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Installs the logger, printing warnings and errors until set_level is called
+/// Everything from info up is kept for the run log
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+/// Sets what is printed to the console. The run log always holds at least info
+pub fn set_level(level: LevelFilter) {
+    if let Ok(mut state) = LOGGER.state.lock() {
+        state.console_level = level;
+    }
+
+    log::set_max_level(level.max(LevelFilter::Info));
+}
+
+/// Switches console output to one JSON object per line, for launchers to read
+pub fn set_json(json: bool) {
+    if let Ok(mut state) = LOGGER.state.lock() {
+        state.json = json;
+    }
+}
+
+/// Whether console output is JSON, in which case nothing else should be printed to the console
+pub fn is_json() -> bool {
+    LOGGER.state.lock().is_ok_and(|state| state.json)
+}
+
+/// Moves the previous run logs in `dir` along by one, dropping the oldest
+fn rotate(dir: &Path) {
+    let numbered = |index: usize| dir.join(format!("{RUN_LOG_NAME}.{index}"));
+
+    for index in (1..KEPT_RUN_LOGS).rev() {
+        let _ = rename(numbered(index), numbered(index + 1));
+    }
+
+    let _ = rename(dir.join(RUN_LOG_NAME), numbered(1));
+}
+
+/// Starts a fresh run log in `dir`, rotating out the previous ones,
+/// and writes out everything logged so far
+pub fn open_run_log(dir: &Path) -> io::Result<PathBuf> {
+    rotate(dir);
+
+    let path = dir.join(RUN_LOG_NAME);
+    let mut file = File::create(&path)?;
+
+    if let Ok(mut state) = LOGGER.state.lock() {
+        for line in state.pending.drain(..) {
+            writeln!(file, "{line}")?;
+        }
+
+        state.file = Some(file);
+    }
+
+    Ok(path)
+}
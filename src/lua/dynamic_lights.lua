@@ -0,0 +1,121 @@
+-- Applies S3LightFixes' standard and colored multipliers to lights created at runtime by other Lua mods.
+-- Lights from content files are already fixed by the generated plugin, so only records Lua created itself are touched.
+-- Each adjusted record is made once, then every light using the original is swapped for one using the adjusted record.
+
+local types = require('openmw.types')
+local util = require('openmw.util')
+local world = require('openmw.world')
+
+local settings = require('scripts.s3lightfixes.settings')
+
+-- Adjusted record ID for each runtime record ID seen so far
+local adjusted = {}
+-- Record IDs this script created, so its own lights are never adjusted twice
+local created = {}
+
+local function toHsv(r, g, b)
+    local max = math.max(r, g, b)
+    local min = math.min(r, g, b)
+    local delta = max - min
+    local hue = 0
+
+    if delta > 0 then
+        if max == r then
+            hue = 60 * (((g - b) / delta) % 6)
+        elseif max == g then
+            hue = 60 * ((b - r) / delta + 2)
+        else
+            hue = 60 * ((r - g) / delta + 4)
+        end
+    end
+
+    return hue, max > 0 and delta / max or 0, max
+end
+
+local function fromHsv(h, s, v)
+    local c = v * s
+    local x = c * (1 - math.abs((h / 60) % 2 - 1))
+    local m = v - c
+    local r, g, b
+
+    if h < 60 then
+        r, g, b = c, x, 0
+    elseif h < 120 then
+        r, g, b = x, c, 0
+    elseif h < 180 then
+        r, g, b = 0, c, x
+    elseif h < 240 then
+        r, g, b = 0, x, c
+    elseif h < 300 then
+        r, g, b = x, 0, c
+    else
+        r, g, b = c, 0, x
+    end
+
+    return r + m, g + m, b + m
+end
+
+local function clamp(value)
+    return math.min(math.max(value, 0), 1)
+end
+
+local function adjustRecord(record)
+    local hue, saturation, value = toHsv(record.color.r, record.color.g, record.color.b)
+
+    -- Same split as the generated plugin: anything outside the orange range of flames is colored
+    local multipliers = (hue > 64 or hue < 14) and settings.colored or settings.standard
+
+    local r, g, b = fromHsv(
+        (hue * multipliers.hue + multipliers.hueShift) % 360,
+        clamp(saturation * multipliers.saturation),
+        clamp(value * multipliers.value)
+    )
+
+    return world.createRecord(types.Light.createRecordDraft({
+        template = record,
+        color = util.color.rgb(r, g, b),
+        radius = math.floor(record.radius * multipliers.radius),
+        duration = record.duration * settings.durationMult,
+    }))
+end
+
+local function onObjectActive(object)
+    if not types.Light.objectIsInstance(object) then
+        return
+    end
+
+    local recordId = object.recordId
+
+    if created[recordId] or not recordId:find('^generated:') then
+        return
+    end
+
+    local adjustedId = adjusted[recordId]
+
+    if not adjustedId then
+        adjustedId = adjustRecord(types.Light.record(object)).id
+        adjusted[recordId] = adjustedId
+        created[adjustedId] = true
+    end
+
+    local replacement = world.createObject(adjustedId, object.count)
+    replacement:teleport(object.cell, object.position, { rotation = object.rotation })
+    object:remove()
+end
+
+return {
+    engineHandlers = {
+        onObjectActive = onObjectActive,
+        onSave = function()
+            return { adjusted = adjusted }
+        end,
+        onLoad = function(data)
+            adjusted = data and data.adjusted or {}
+            created = {}
+
+            for _, adjustedId in pairs(adjusted) do
+                created[adjustedId] = true
+            end
+        end,
+    },
+}
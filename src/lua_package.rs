@@ -0,0 +1,69 @@
+use std::{
+    fs::{create_dir_all, write},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::LightConfig;
+
+/// The omwscripts file of the package, which is enabled like any other content file
+pub const LUA_PACKAGE_NAME: &str = "S3LightFixes.omwscripts";
+
+/// Where the scripts live inside the package, matching the paths used in LUA_PACKAGE_NAME
+const SCRIPTS_DIR: &str = "scripts/s3lightfixes";
+
+const DYNAMIC_LIGHTS_SCRIPT: &str = include_str!("lua/dynamic_lights.lua");
+
+/// The multipliers the script applies, as a Lua module
+/// Only the plain standard and colored multipliers are carried over: overrides, buckets, and target colors need the whole config
+fn settings_module(light_config: &LightConfig) -> String {
+    let multipliers = |hue: f32, hue_shift: f32, saturation: f32, value: f32, radius: f32| {
+        format!(
+            "{{ hue = {hue}, hueShift = {hue_shift}, saturation = {saturation}, value = {value}, radius = {radius} }}"
+        )
+    };
+
+    format!(
+        "-- Generated by S3LightFixes from lightconfig.toml. Run it again with --emit-lua-package to update this file\n\
+        return {{\n    standard = {},\n    colored = {},\n    durationMult = {},\n}}\n",
+        multipliers(
+            light_config.standard_hue,
+            light_config.standard_hue_shift,
+            light_config.standard_saturation,
+            light_config.standard_value,
+            light_config.standard_radius,
+        ),
+        multipliers(
+            light_config.colored_hue,
+            light_config.colored_hue_shift,
+            light_config.colored_saturation,
+            light_config.colored_value,
+            light_config.colored_radius,
+        ),
+        light_config.duration_mult,
+    )
+}
+
+/// Writes an OpenMW Lua package into `dir` which applies the current multipliers to lights created at runtime,
+/// which the generated plugin can't reach, returning the path of its omwscripts file
+pub fn write_lua_package(dir: &Path, light_config: &LightConfig) -> io::Result<PathBuf> {
+    let scripts_dir = dir.join(SCRIPTS_DIR);
+    create_dir_all(&scripts_dir)?;
+
+    write(
+        scripts_dir.join("dynamic_lights.lua"),
+        DYNAMIC_LIGHTS_SCRIPT,
+    )?;
+    write(
+        scripts_dir.join("settings.lua"),
+        settings_module(light_config),
+    )?;
+
+    let omwscripts_path = dir.join(LUA_PACKAGE_NAME);
+    write(
+        &omwscripts_path,
+        format!("GLOBAL: {SCRIPTS_DIR}/dynamic_lights.lua\n"),
+    )?;
+
+    Ok(omwscripts_path)
+}
@@ -1,285 +1,1571 @@
 use std::{
-    collections::HashSet,
-    env::{current_dir, var},
-    fs::{File, metadata, remove_file},
-    io::{self, Write},
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    env::{args_os, current_dir, current_exe, var},
+    fs::{File, create_dir_all, metadata, remove_file},
+    io::{self, BufRead, IsTerminal, Write},
     mem::take as TakeAndSwitch,
-    path::PathBuf,
-    process::exit,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
 use clap::Parser;
-use palette::{FromColor, GetHue, Hsv, IntoColor, SetHue, rgb::Srgb};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use rayon::prelude::*;
 use tes3::esp::{
-    Cell, CellFlags, EditorId, FixedString, Header, Light, LightFlags, ObjectFlags, Plugin,
-    TES3Object, types::FileType,
+    Cell, CellFlags, EditorId, FixedString, Header, Light, ObjectFlags, Plugin, TES3Object,
+    types::FileType,
 };
 use vfstool_lib::VFS;
 
+use log::LevelFilter;
 use s3lightfixes::{
-    CustomLightData, LOG_NAME, LightArgs, LightConfig, PLUGIN_NAME, get_config_path,
-    is_fixable_plugin, notification_box, save_plugin,
+    CACHE_NAME, CLASSES_NAME, CellRecordContents, ContentFiles, ESP_PLUGIN_NAME, Encoding,
+    ExitCode, GENERATED_MARKER, GenerationMetadata, HEADER_VERSION, INSTALL_DIR_NAME,
+    KNOWN_PLUGINS_NAME, KnownPlugins, LOG_NAME, Language, LightArgs, LightClasses, LightCommand,
+    LightConfig, LightPlacements, LightRegions, LogFormat, METADATA_NAME, MasterStrategy, Message,
+    NotifyBackend, OpenMWConfiguration, OutputFormat, PLUGIN_NAME, PluginErrorPolicy,
+    SKIP_LIST_NAME, SPLIT_PLUGIN_PREFIX, SkipList, SplitOutput, Warning, append_data_directory,
+    archives::UnregisteredArchives,
+    arrange_data_dirs, backup, canonical_path,
+    change_log::{CellChange, ChangeLog, LightChange},
+    classify, cleanup, color, default_fallback_dirs, exit,
+    extract::{LightSet, UsedIds},
+    fallback_archives_from_openmw_cfgs, get_config_path, header_author, header_description,
+    inspect_plugin, install_scan,
+    interop::import_foreign_overrides,
+    inventory, is_fixable_plugin, is_openmw_only, launched_without_console,
+    light_table::{LightRow, import_table, write_table},
+    load_light_records,
+    logging::{self, RUN_LOG_NAME},
+    mesh, mo2, notification_box, notify_backend,
+    openmw_settings::{self, LightingSettings},
+    output_record_flags, plugin_hash, preserve_manual_edits, read_profiles, record_key,
+    reserve_stdout, save_plugin, set_language, set_notify_backend, set_pause_on_exit, shaders,
+    stdout_reserved, strip_profile_args, strip_to_atmosphere,
+    telemetry::{RecordCounts, StageTimings},
+    to_esp_plugin, to_io_error,
+    transform::{self, LightContext, LightOutcome, process_light, process_light_in},
+    update::{self, Release},
+    validate_plugin, wait_for_enter, write_lua_package,
 };
 
-/// Given a LightData reference from an ESP light,
-/// returns the HSV version and whether it is colored or not (for the global modifier)
-pub fn light_to_hsv(light_data: &tes3::esp::LightData) -> (Hsv, bool) {
-    let rgb: palette::rgb::Rgb = Srgb::new(
-        light_data.color[0],
-        light_data.color[1],
-        light_data.color[2],
-    )
-    .into_format();
+/// Counts shown alongside the success message
+#[derive(Debug, Default)]
+struct RunSummary {
+    plugins_scanned: usize,
+    plugins_skipped: usize,
+    standard_lights: usize,
+    colored_lights: usize,
+    negative_lights: usize,
+    disabled_lights: usize,
+    /// Left out by skip_scripted_lights
+    scripted_lights: usize,
+    cells: usize,
+    output_size: u64,
+}
 
-    let hsv: Hsv = Hsv::from_color(rgb);
-    let hue_degrees = hsv.get_hue().into_positive_degrees();
+impl RunSummary {
+    fn count_light(&mut self, outcome: LightOutcome) {
+        match outcome {
+            LightOutcome::Standard => self.standard_lights += 1,
+            LightOutcome::Colored => self.colored_lights += 1,
+            LightOutcome::Negative => self.negative_lights += 1,
+            LightOutcome::Disabled => self.disabled_lights += 1,
+        }
+    }
 
-    (hsv, hue_degrees > 64. || hue_degrees < 14.)
+    fn to_message(&self) -> String {
+        let total_lights = self.standard_lights
+            + self.colored_lights
+            + self.negative_lights
+            + self.disabled_lights;
+
+        Message::SummaryBody.fill(&[
+            &self.plugins_scanned,
+            &self.plugins_skipped,
+            &total_lights,
+            &self.standard_lights,
+            &self.colored_lights,
+            &self.negative_lights,
+            &self.disabled_lights,
+            &self.scripted_lights,
+            &self.cells,
+            &format!("{:.1} KiB", self.output_size as f64 / 1024.0),
+        ])
+    }
 }
 
-pub fn process_light(light_config: &LightConfig, light: &mut tes3::esp::Light) {
-    if light.data.flags.contains(LightFlags::NEGATIVE) {
-        light.data.flags.remove(LightFlags::NEGATIVE);
-        light.data.radius = 0;
-        light.data.color = [0, 0, 0, 0];
-        return;
+/// Finds the context each light in the load order is fixed in, reading each model only once
+struct LightContexts<'a> {
+    light_config: &'a LightConfig,
+    vfs: &'a VFS,
+    /// Only loaded when there are [classes] settings
+    light_classes: Option<LightClasses>,
+    light_regions: LightRegions,
+    light_placements: LightPlacements,
+    /// Many lights share a model, so each one's scale is kept
+    mesh_scales: HashMap<String, f32>,
+}
+
+impl<'a> LightContexts<'a> {
+    fn new(
+        light_config: &'a LightConfig,
+        vfs: &'a VFS,
+        light_regions: LightRegions,
+        light_placements: LightPlacements,
+    ) -> LightContexts<'a> {
+        LightContexts {
+            light_config,
+            vfs,
+            light_classes: (!light_config.classes.is_empty())
+                .then(|| LightClasses::load(&light_config.config_dir.join(CLASSES_NAME))),
+            light_regions,
+            light_placements,
+            mesh_scales: HashMap::new(),
+        }
     }
 
-    if light_config.disable_flickering {
-        light
-            .data
-            .flags
-            .remove(LightFlags::FLICKER | LightFlags::FLICKER_SLOW);
+    /// Classes are judged from the light as the load order has it, so this is called before it's fixed
+    fn context_of(&mut self, light: &Light) -> LightContext {
+        let light_config = self.light_config;
+        let light_id = light_config.encoding.record_id(&light.id);
+
+        let radius_scale = if !light_config.mesh_aware_radius || light.mesh.is_empty() {
+            1.0
+        } else {
+            let mesh_path = mesh::vfs_mesh_path(&light.mesh).to_ascii_lowercase();
+            let vfs = self.vfs;
+
+            *self
+                .mesh_scales
+                .entry(mesh_path)
+                .or_insert_with_key(|mesh_path| {
+                    vfs.get_file(mesh_path)
+                        .and_then(|file| mesh::bounding_radius(file.path()))
+                        .map_or(1.0, |radius| {
+                            mesh::radius_scale(radius, light_config.mesh_reference_radius)
+                        })
+                })
+        };
+
+        LightContext {
+            radius_scale,
+            class: self
+                .light_classes
+                .as_ref()
+                .map(|light_classes| light_classes.class_of(&light_id, light)),
+            region: self.light_regions.region_of(&light_id).map(str::to_owned),
+            placement: self.light_placements.placement_of(&light_id),
+        }
     }
 
-    if light_config.disable_pulse {
-        light
-            .data
-            .flags
-            .remove(LightFlags::PULSE | LightFlags::PULSE_SLOW);
+    /// A copy of the light, fixed as it would be in the generated plugin
+    fn fix(&mut self, light: &Light) -> Light {
+        let context = self.context_of(light);
+        let mut fixed = light.clone();
+        process_light_in(self.light_config, &mut fixed, &context);
+        fixed
     }
+}
 
-    let light_id = light.editor_id_ascii_lowercase();
-    let (mut light_as_hsv, is_colored) = light_to_hsv(&light.data);
+/// Lists the `count` lights whose color would change the most under the current settings,
+/// grouped by the plugin they come from, without generating anything
+/// Each light is fixed in its own context, so the values match the generated plugin
+fn print_preview(
+    light_contexts: &mut LightContexts,
+    lights: &[(usize, Light)],
+    plugin_paths: &[PathBuf],
+    count: usize,
+) {
+    let mut changes: Vec<(f32, usize, &Light, Light)> = lights
+        .iter()
+        .map(|(owner, light)| {
+            let fixed = light_contexts.fix(light);
+            (
+                color::delta_e(light.data.color, fixed.data.color),
+                *owner,
+                light,
+                fixed,
+            )
+        })
+        .collect();
+
+    changes.sort_by(|a, b| b.0.total_cmp(&a.0));
+    changes.truncate(count);
+
+    // Plugin indices run in reverse load order, so walk them backwards to print in load order
+    // Changes were sorted before grouping, so each plugin's lights stay most changed first
+    let mut grouped: BTreeMap<Reverse<usize>, Vec<(f32, &Light, Light)>> = BTreeMap::new();
+    for (difference, owner, original, fixed) in changes {
+        grouped
+            .entry(Reverse(owner))
+            .or_default()
+            .push((difference, original, fixed));
+    }
 
-    let mut replacement_light_data: Option<&CustomLightData> = None;
+    for (Reverse(owner), entries) in grouped {
+        println!("{}:", plugin_paths[owner].display());
+
+        for (difference, original, fixed) in entries {
+            println!(
+                "    {}: {} -> {}, radius {} -> {} (delta-E {:.1})",
+                original.id,
+                color::to_hex(original.data.color),
+                color::to_hex(fixed.data.color),
+                original.data.radius,
+                fixed.data.radius,
+                difference
+            );
+        }
+    }
+}
+
+/// Prints a light's color before and after fixing, for the preview command
+fn print_color_preview(original: &Light, fixed: &Light) {
+    println!(
+        "{} {} -> {} {}",
+        color::ansi_swatch(original.data.color),
+        color::to_hex(original.data.color),
+        color::ansi_swatch(fixed.data.color),
+        color::to_hex(fixed.data.color),
+    );
+}
 
-    for (regex, light_data) in &light_config.light_regexes {
-        if regex.is_match(&light_id) {
-            replacement_light_data = Some(light_data);
-            break;
+/// Writes a copy of the plugin into each of light_config.outputs, exiting if any can't be written
+/// Returns whether any copy differed from the file already there
+fn save_copies(
+    light_config: &LightConfig,
+    plugin_name: &str,
+    plugin: &mut Plugin,
+    failure: Message,
+) -> bool {
+    let mut any_changed = false;
+
+    for copy_dir in &light_config.outputs {
+        match save_plugin(copy_dir, &[], plugin_name, plugin) {
+            Ok(saved) => any_changed |= saved.changed,
+            Err(err) => {
+                notification_box(failure.text(), &format!("{}: {err}", copy_dir.display()));
+                exit(ExitCode::WriteFailure);
+            }
         }
     }
 
-    let (global_radius, global_hue, global_saturation, global_value) = match is_colored {
-        // Red, purple, blue, green, yellow
-        true => (
-            light_config.colored_radius,
-            light_config.colored_hue,
-            light_config.colored_saturation,
-            light_config.colored_value,
-        ),
-        // Everything else
-        false => (
-            light_config.standard_radius,
-            light_config.standard_hue,
-            light_config.standard_saturation,
-            light_config.standard_value,
+    if !light_config.outputs.is_empty() {
+        log::debug!(
+            "Saved copies of {plugin_name} in {} more directories",
+            light_config.outputs.len()
+        );
+    }
+
+    any_changed
+}
+
+/// Splits the records from each plugin into an addon of their own, named after it and with it as the only master,
+/// so that removing a mod only means removing its addon
+/// Records from groundcover get no master, as groundcover can't be one
+fn split_plugins(
+    objects: Vec<TES3Object>,
+    object_owners: &[usize],
+    plugin_paths: &[PathBuf],
+    groundcover: &HashSet<String>,
+    light_config: &LightConfig,
+) -> io::Result<Vec<(String, Plugin)>> {
+    // Plugin indices run in reverse load order, so walk them backwards to save in load order
+    let mut grouped: BTreeMap<Reverse<usize>, Vec<TES3Object>> = BTreeMap::new();
+    for (object, owner) in objects.into_iter().zip(object_owners) {
+        grouped.entry(Reverse(*owner)).or_default().push(object);
+    }
+
+    let mut split = Vec::new();
+
+    for (Reverse(owner), objects) in grouped {
+        let plugin_path = &plugin_paths[owner];
+        let (Some(name), Some(stem)) = (plugin_path.file_name(), plugin_path.file_stem()) else {
+            continue;
+        };
+        let name = name.to_string_lossy().to_string();
+
+        let masters = if groundcover.contains(&name.to_ascii_lowercase()) {
+            Vec::new()
+        } else {
+            vec![(name.clone(), metadata(plugin_path)?.len())]
+        };
+
+        let mut plugin = Plugin::new();
+        plugin.objects.push(TES3Object::Header(Header {
+            version: HEADER_VERSION,
+            author: FixedString(header_author(&light_config.author)),
+            description: FixedString(header_description(
+                light_config.description.as_deref(),
+                format!("{GENERATED_MARKER} for {name}"),
+            )),
+            file_type: FileType::Esp,
+            flags: ObjectFlags::default(),
+            num_objects: objects.len() as u32,
+            masters,
+        }));
+        plugin.objects.extend(objects);
+        plugin.sort_objects();
+
+        let plugin_name = format!("{SPLIT_PLUGIN_PREFIX}{}.omwaddon", stem.to_string_lossy());
+        split.push((plugin_name, plugin));
+    }
+
+    Ok(split)
+}
+
+/// Exits with the validation errors unless the plugin survives a round trip through its serialized form
+fn validate_or_exit(
+    plugin: &Plugin,
+    load_order: &[String],
+    content_file_paths: &ContentFiles,
+    light_config: &LightConfig,
+) {
+    if let Err(errors) = validate_plugin(plugin, load_order, |master| {
+        content_file_paths.resolve(master).is_some()
+    }) {
+        let details = errors
+            .iter()
+            .map(|error| format!("- {error}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        notification_box(
+            Message::ValidationFailed.text(),
+            &Message::ValidationFailedBody.fill(&[&details]),
+        );
+        exit(ExitCode::InvalidPlugin);
+    }
+}
+
+/// Adds the output directory to the user openmw.cfg, unless it's already a data directory
+fn install_output_dir(
+    light_config: &LightConfig,
+    config: &Option<OpenMWConfiguration>,
+    output_dir: &PathBuf,
+) {
+    let Some(config) = config else {
+        return;
+    };
+
+    if config
+        .data_directories()
+        .into_iter()
+        .any(|dir| dir == output_dir)
+    {
+        return;
+    }
+
+    let user_cfg = config.user_config_path().join("openmw.cfg");
+
+    if let Err(err) = append_data_directory(&user_cfg, output_dir) {
+        notification_box(Message::ResaveConfigFailed.text(), &err.to_string());
+        exit(ExitCode::WriteFailure);
+    }
+
+    notification_box(
+        Message::Installed.text(),
+        &Message::InstalledBody.fill(&[&output_dir.display(), &user_cfg.display()]),
+    );
+}
+
+/// Writes the change log next to lightconfig.toml, or with the full log format, a dump of every generated plugin
+fn write_run_log(
+    light_config: &LightConfig,
+    change_log: &ChangeLog,
+    plugins: &[&Plugin],
+) -> io::Result<()> {
+    match light_config.log_format {
+        LogFormat::Full => {
+            let path = light_config.config_dir.join(LOG_NAME);
+            let mut file = File::create(path)?;
+
+            for plugin in plugins {
+                let _ = write!(file, "{}", format!("{plugin:#?}"));
+            }
+        }
+        format => {
+            if let Err(err) = change_log.write(&light_config.config_dir, format) {
+                log::warn!("Could not write the change log: {err}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the run summary and shows the success message, then mentions any newer release
+fn finish_run(
+    light_config: &LightConfig,
+    summary: &RunSummary,
+    written_plugins: &str,
+    output_dir: &Path,
+    any_changed: bool,
+    update_check: Option<JoinHandle<Option<Release>>>,
+) {
+    let summary = summary.to_message();
+
+    // The stdout backend prints the notification itself, as do JSON logs
+    if notify_backend() != NotifyBackend::Stdout
+        && !light_config.quiet
+        && !light_config.json_logs
+        && !stdout_reserved()
+    {
+        println!("{summary}");
+    }
+
+    let result = if any_changed {
+        Message::SuccessfulBody.fill(&[&written_plugins, &output_dir.display()])
+    } else {
+        Message::UnchangedBody.fill(&[&written_plugins, &output_dir.display()])
+    };
+
+    let lights_fixed = format!("{result}\n\n{summary}");
+
+    if !light_config.quiet {
+        notification_box(Message::Successful.text(), &lights_fixed);
+    }
+
+    if let Some(update_check) = update_check
+        && update_check.is_finished()
+        && let Ok(Some(release)) = update_check.join()
+    {
+        report(
+            light_config,
+            &format!(
+                "S3LightFixes {} is available at {}. Run `s3lightfixes update` to install it.",
+                release.tag_name, release.html_url
+            ),
+        );
+    }
+}
+
+/// Nights are weather settings rather than records, so they go into the user openmw.cfg instead of the plugin
+/// Nothing is written without an openmw.cfg, such as when content files come from the command line
+fn write_exterior_nights(
+    light_config: &LightConfig,
+    config: &Option<OpenMWConfiguration>,
+    root_cfg_path: &Option<PathBuf>,
+) {
+    let (Some(config), Some(root_cfg_path)) = (config, root_cfg_path) else {
+        return;
+    };
+
+    if light_config.exterior_nights.is_empty() {
+        return;
+    }
+
+    let user_cfg = config.user_config_path().join("openmw.cfg");
+
+    match light_config.exterior_nights.apply(root_cfg_path, &user_cfg) {
+        Ok(written) => log::info!("Wrote {written} night colors to {}", user_cfg.display()),
+        Err(err) => log::warn!(
+            "Could not write night colors to {}: {err}",
+            user_cfg.display()
         ),
+    }
+}
+
+/// Lists every light defined by more than one plugin, with each plugin's version in load order
+/// The last version listed is the one the game (and lightfixes) uses
+fn print_conflicts(
+    light_versions: &HashMap<String, Vec<(usize, [u8; 4], u32)>>,
+    plugin_paths: &[PathBuf],
+) {
+    let mut conflicting_ids: Vec<&String> = light_versions
+        .iter()
+        .filter(|(_, versions)| versions.len() > 1)
+        .map(|(id, _)| id)
+        .collect();
+
+    conflicting_ids.sort();
+
+    for light_id in conflicting_ids {
+        println!("{light_id}:");
+
+        // Versions were collected in reverse load order, so the first one is the winner
+        let versions = &light_versions[light_id];
+        for (position, (plugin_index, color, radius)) in versions.iter().enumerate().rev() {
+            println!(
+                "    {}: {}, radius {}{}",
+                plugin_paths[*plugin_index].display(),
+                color::to_hex(*color),
+                radius,
+                if position == 0 { " (winner)" } else { "" }
+            );
+        }
+    }
+}
+
+/// Lists the file each content entry resolved to, followed by any it won over
+fn print_content_resolution(content_files: &ContentFiles, load_order: &[String]) {
+    for content_file in load_order {
+        match content_files.resolve(content_file) {
+            Some(path) => match canonical_path(path) {
+                real_path if real_path != path => println!(
+                    "{content_file}: {} (links to {})",
+                    path.display(),
+                    real_path.display()
+                ),
+                _ => println!("{content_file}: {}", path.display()),
+            },
+            None => println!("{content_file}: not found in any data directory"),
+        }
+
+        for shadowed in content_files.shadowed(content_file) {
+            println!("    shadowed: {}", shadowed.display());
+        }
+    }
+}
+
+/// Prints how long each plugin took to load and scan, slowest first, along with how many records it held
+/// Ends with an estimate of the memory held by every plugin's records at once
+fn print_plugin_timings(
+    plugin_paths: &[PathBuf],
+    parse_times: &Mutex<HashMap<PathBuf, Duration>>,
+    record_counts: &Mutex<HashMap<PathBuf, RecordCounts>>,
+    transform_times: &[Duration],
+) {
+    let (Ok(parse_times), Ok(record_counts)) = (parse_times.lock(), record_counts.lock()) else {
+        return;
     };
 
-    if let Some(replacement) = replacement_light_data {
-        if let Some(hue_mult) = replacement.hue_mult {
-            let new_hue =
-                palette::RgbHue::from_degrees(light_as_hsv.hue.into_raw_degrees() * hue_mult);
-            light_as_hsv.set_hue(new_hue);
-        } else if let Some(fixed_hue) = replacement.hue {
-            light_as_hsv.set_hue(palette::RgbHue::from_degrees(fixed_hue as f32));
-        } else {
-            let new_hue =
-                palette::RgbHue::from_degrees(light_as_hsv.hue.into_raw_degrees() * global_hue);
-            light_as_hsv.set_hue(new_hue);
+    let mut timings: Vec<(&Path, Duration, Duration, RecordCounts)> = plugin_paths
+        .iter()
+        .zip(transform_times)
+        .map(|(path, transform_time)| {
+            let parse_time = parse_times.get(path).copied().unwrap_or_default();
+            let counts = record_counts.get(path).copied().unwrap_or_default();
+            (path.as_path(), parse_time, *transform_time, counts)
+        })
+        .collect();
+
+    timings.sort_by(|a, b| (b.1 + b.2).cmp(&(a.1 + a.2)));
+
+    println!(
+        "{:>12} {:>12} {:>8} {:>8} {:>8} {:>8} {:>10}  plugin",
+        "parse", "transform", "records", "lights", "cells", "kept", "memory"
+    );
+
+    for (path, parse_time, transform_time, counts) in &timings {
+        println!(
+            "{:>12.2?} {:>12.2?} {:>8} {:>8} {:>8} {:>8} {:>7} KiB  {}",
+            parse_time,
+            transform_time,
+            counts.total(),
+            counts.lights,
+            counts.cells,
+            counts.kept,
+            counts.estimated_bytes / 1024,
+            path.display()
+        );
+    }
+
+    let total_bytes: usize = timings
+        .iter()
+        .map(|(_, _, _, counts)| counts.estimated_bytes)
+        .sum();
+
+    println!(
+        "Estimated memory held for the records of every plugin: {} KiB",
+        total_bytes / 1024
+    );
+}
+
+/// Lists the meshes and icons of lights which OpenMW won't find,
+/// either because they're missing or because they're only in an archive openmw.cfg doesn't load
+fn print_asset_report(light_set: &LightSet, vfs: &VFS, unregistered: &UnregisteredArchives) {
+    for archive in &unregistered.archives {
+        println!(
+            "{} is in a data directory, but isn't listed as a fallback-archive in openmw.cfg",
+            archive.display()
+        );
+    }
+
+    let mut problems = 0;
+
+    for (owner, light) in &light_set.lights {
+        let assets = [
+            (!light.mesh.is_empty()).then(|| mesh::vfs_mesh_path(&light.mesh)),
+            (!light.icon.is_empty()).then(|| format!("icons/{}", light.icon.replace('\\', "/"))),
+        ];
+
+        for asset in assets.into_iter().flatten() {
+            let asset = asset.to_ascii_lowercase();
+
+            if vfs.get_file(&asset).is_some() {
+                continue;
+            }
+
+            problems += 1;
+            let plugin = light_set.plugins[*owner].display();
+
+            match unregistered.find(&asset) {
+                Some(archive) => println!(
+                    "{} ({plugin}): {asset} is only in {}, which OpenMW doesn't load",
+                    light.id,
+                    archive.display()
+                ),
+                None => println!(
+                    "{} ({plugin}): {asset} is missing from every data directory and archive",
+                    light.id
+                ),
+            }
         }
+    }
 
-        if let Some(saturation_mult) = replacement.saturation_mult {
-            light_as_hsv.saturation *= saturation_mult;
-        } else if let Some(fixed_saturation) = replacement.saturation {
-            light_as_hsv.saturation = fixed_saturation;
-        } else {
-            light_as_hsv.saturation *= global_saturation;
+    println!(
+        "Checked {} lights, and found {problems} assets OpenMW won't load",
+        light_set.lights.len()
+    );
+}
+
+/// Prints how long each stage of the run took, and the total
+fn print_stage_timings(stage_timings: &StageTimings) {
+    println!("{:>12}  stage", "time");
+
+    for (stage, duration) in stage_timings.stages() {
+        println!("{duration:>12.2?}  {stage}");
+    }
+
+    println!("{:>12.2?}  total", stage_timings.total());
+}
+
+/// Asks on the terminal whether to carry on without the plugins which failed to load
+/// Declined when there's nobody to answer
+fn confirm_skipping_failures(failures: usize) -> bool {
+    if !io::stdin().is_terminal() {
+        log::warn!("on_plugin_error is set to prompt, but there is no terminal to ask on");
+        return false;
+    }
+
+    print!("{failures} plugins could not be loaded. Continue without them? [y/N]: ");
+    let _ = io::stdout().flush();
+
+    let mut input = String::new();
+    if io::stdin().lock().read_line(&mut input).is_err() {
+        return false;
+    }
+
+    matches!(input.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// Disables stray copies of earlier outputs, which would otherwise shadow or duplicate the one about to be written,
+/// then drops the content= lines naming them from openmw.cfg, unless another file still answers to that name
+fn clean_stray_outputs(
+    light_config: &LightConfig,
+    config: Option<&mut openmw_config::OpenMWConfiguration>,
+    directories: &[PathBuf],
+    output_dir: &Path,
+) {
+    let mut search_dirs = directories.to_vec();
+    search_dirs.push(light_config.config_dir.clone());
+
+    let mut disabled_names: Vec<String> = Vec::new();
+
+    for stray in cleanup::find_stray_outputs(&search_dirs, output_dir) {
+        match cleanup::disable_output(&stray) {
+            Ok(disabled) => {
+                report(
+                    light_config,
+                    &format!(
+                        "Disabled an old copy of the plugin, {}, renaming it to {}",
+                        stray.display(),
+                        disabled.display()
+                    ),
+                );
+
+                if let Some(name) = stray.file_name() {
+                    disabled_names.push(name.to_string_lossy().to_string());
+                }
+            }
+            Err(err) => log::warn!("Could not disable {}: {err}", stray.display()),
         }
+    }
 
-        if let Some(value_mult) = replacement.value_mult {
-            light_as_hsv.value *= value_mult;
-        } else if let Some(fixed_value) = replacement.value {
-            light_as_hsv.value = fixed_value;
-        } else {
-            light_as_hsv.value *= global_value;
+    let Some(config) = config else {
+        return;
+    };
+
+    let remaining = ContentFiles::scan(directories);
+    let stale: Vec<String> = config
+        .content_files()
+        .iter()
+        .map(|content_file| content_file.to_string())
+        .filter(|content_file| {
+            disabled_names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(content_file))
+                && !content_file.eq_ignore_ascii_case(PLUGIN_NAME)
+                && remaining.resolve(content_file).is_none()
+        })
+        .collect();
+
+    if stale.is_empty() {
+        return;
+    }
+
+    for content_file in &stale {
+        if let Err(err) = config.remove_content_file(content_file) {
+            log::warn!("Could not remove content={content_file} from openmw.cfg: {err}");
         }
+    }
 
-        if let Some(duration_mult) = replacement.duration_mult {
-            light.data.time = (duration_mult * light.data.time as f32) as i32;
-        } else if let Some(fixed_duration) = replacement.duration {
-            light.data.time = fixed_duration as i32;
+    match config.save_user() {
+        Ok(_) => report(
+            light_config,
+            &format!(
+                "Removed content= lines for old copies from openmw.cfg: {}",
+                stale.join(", ")
+            ),
+        ),
+        Err(err) => log::warn!("Could not save openmw.cfg after removing old copies: {err}"),
+    }
+}
+
+/// Prints a message about the run, unless the console is quiet or taken up by JSON logs
+fn report(light_config: &LightConfig, message: &str) {
+    if light_config.json_logs {
+        log::info!("{message}");
+    } else if !light_config.quiet {
+        if stdout_reserved() {
+            eprintln!("{message}");
         } else {
-            light.data.time = (light.data.time as f32 * light_config.duration_mult) as i32;
+            println!("{message}");
+        }
+    }
+}
+
+/// Runs lightfixes once for each profile, with the rest of the command line as given,
+/// then lists which profiles failed and exits with ProfilesFailed if any did
+fn run_profiles(profiles: &[PathBuf], args: &LightArgs) -> io::Result<()> {
+    if !args.output.is_empty() {
+        log::error!(
+            "--output can't be used with several profiles, as each one's plugin would overwrite the last"
+        );
+        exit(ExitCode::BadArguments);
+    }
+
+    let executable = current_exe()?;
+    // Pausing is left to this process, so it only happens once at the very end
+    let profile_args: Vec<_> = strip_profile_args(args_os().skip(1))
+        .into_iter()
+        .filter(|arg| arg != "--pause")
+        .collect();
+
+    let mut failures = Vec::new();
+
+    for (index, profile) in profiles.iter().enumerate() {
+        if !args.quiet {
+            println!(
+                "\n==> Profile {} of {}: {} <==",
+                index + 1,
+                profiles.len(),
+                profile.display()
+            );
         }
 
-        if let Some(radius_mult) = replacement.radius_mult {
-            light.data.radius = (radius_mult * light.data.radius as f32) as u32;
-        } else if let Some(fixed_radius) = replacement.radius {
-            light.data.radius = fixed_radius;
-        } else {
-            light.data.radius = (global_radius * light.data.radius as f32) as u32;
+        let status = Command::new(&executable)
+            .args(&profile_args)
+            .arg("--openmw-cfg")
+            .arg(profile)
+            .env_remove("S3L_PAUSE")
+            .status()?;
+
+        if !status.success() {
+            failures.push((profile, status));
         }
+    }
 
-        if let Some(flag) = &replacement.flag {
-            light.data.flags = flag.to_esp_flag();
+    if failures.is_empty() {
+        if !args.quiet {
+            println!("\nEvery profile was fixed");
         }
-    } else {
-        let new_hue =
-            palette::RgbHue::from_degrees(light_as_hsv.hue.into_raw_degrees() * global_hue);
 
-        light_as_hsv.set_hue(new_hue);
-        light_as_hsv.saturation *= global_saturation;
-        light_as_hsv.value *= global_value;
+        return Ok(());
+    }
+
+    log::error!("{} of {} profiles failed:", failures.len(), profiles.len());
 
-        light.data.radius = (global_radius * light.data.radius as f32) as u32;
-        light.data.time = (light.data.time as f32 * light_config.duration_mult) as i32;
+    for (profile, status) in failures {
+        log::error!("  {}: {status}", profile.display());
     }
 
-    let rgb8_color: Srgb<u8> = <Hsv as IntoColor<Srgb>>::into_color(light_as_hsv).into_format();
-    light.data.color = [rgb8_color.red, rgb8_color.green, rgb8_color.blue, 0];
+    exit(ExitCode::ProfilesFailed);
 }
 
-fn main() -> io::Result<()> {
-    let mut args = LightArgs::parse();
+/// Locates and reads openmw.cfg, exiting if it can't be found or parsed
+/// Also returns the path of the root openmw.cfg, which may not be called openmw.cfg
+fn load_openmw_cfg(args: &mut LightArgs) -> (openmw_config::OpenMWConfiguration, PathBuf) {
+    let config_dir = match get_config_path(args) {
+        Ok(path) => path,
+        Err(error) => {
+            // Most often this is a fresh install which OpenMW has never been run for
+            match install_scan::offer_minimal_cfg() {
+                Ok(Some(cfg_path)) => {
+                    log::warn!(
+                        "Generated a minimal openmw.cfg at {}. Run the OpenMW launcher to set up a full one",
+                        cfg_path.display()
+                    );
 
-    if args.info {
-        println!("S3LightFixes Version: {}", env!("CARGO_PKG_VERSION"),);
-        exit(0);
+                    cfg_path
+                }
+                result => {
+                    if let Err(err) = result {
+                        log::warn!("Could not generate a minimal openmw.cfg: {err}");
+                    }
+
+                    notification_box(Message::LocateConfigFailed.text(), &error.to_string());
+
+                    exit(ExitCode::ConfigNotFound);
+                }
+            }
+        }
     };
 
-    let no_notifications = var("S3L_NO_NOTIFICATIONS").is_ok() || args.no_notifications;
-    let config_dir = get_config_path(&mut args);
+    let root_cfg_path = if config_dir.is_dir() {
+        config_dir.join("openmw.cfg")
+    } else {
+        config_dir.clone()
+    };
 
     // If the openmw.cfg path is provided by the user, force the crate to use
     // whatever they've provided
-    let mut config = match openmw_config::OpenMWConfiguration::new(Some(config_dir)) {
-        Ok(config) => config,
+    match openmw_config::OpenMWConfiguration::new(Some(config_dir)) {
+        Ok(config) => (config, root_cfg_path),
         Err(error) => {
-            notification_box(
-                &"Failed to read configuration file!",
-                &error.to_string(),
-                no_notifications,
-            );
+            notification_box(Message::ReadConfigFailed.text(), &error.to_string());
 
-            exit(127);
+            exit(ExitCode::InvalidConfig);
         }
+    }
+}
+
+fn main() {
+    if let Err(err) = run() {
+        log::error!("{err}");
+        exit(ExitCode::Failure);
+    }
+
+    wait_for_enter();
+}
+
+fn run() -> io::Result<()> {
+    logging::init();
+
+    // Parsed by hand so that bad arguments get their own exit code, instead of clap's default
+    let mut args = match LightArgs::try_parse() {
+        Ok(args) => args,
+        Err(err) => {
+            let _ = err.print();
+            exit(if err.use_stderr() {
+                ExitCode::BadArguments
+            } else {
+                ExitCode::Success
+            });
+        }
+    };
+
+    if args.info {
+        println!("S3LightFixes Version: {}", env!("CARGO_PKG_VERSION"),);
+        exit(ExitCode::Success);
+    };
+
+    set_notify_backend(NotifyBackend::resolve(
+        args.notify,
+        var("S3L_DESKTOP_NOTIFICATIONS").is_ok() || args.desktop_notifications,
+        var("S3L_NO_NOTIFICATIONS").is_ok() || args.no_notifications,
+    ));
+
+    set_language(args.language.unwrap_or_else(Language::detect));
+
+    if args.stdout {
+        reserve_stdout();
+    }
+
+    // Needs nothing but the plugin itself, so it runs before openmw.cfg is even looked for
+    if let Some(LightCommand::Inspect { path }) = &args.command {
+        match inspect_plugin(path) {
+            Ok(report) => println!("{report}"),
+            Err(err) => {
+                log::error!("Couldn't read {}: {err}", path.display());
+                exit(ExitCode::Failure);
+            }
+        }
+
+        return Ok(());
+    }
+
+    // Several profiles are each fixed by a run of their own, so that one failing doesn't stop the rest
+    let profiles = match args.profiles.take() {
+        Some(list_path) => match read_profiles(&list_path) {
+            Ok(profiles) if !profiles.is_empty() => profiles,
+            Ok(_) => {
+                log::error!("{} lists no profiles", list_path.display());
+                exit(ExitCode::InvalidConfig);
+            }
+            Err(err) => {
+                log::error!("Couldn't read {}: {err}", list_path.display());
+                exit(ExitCode::InvalidConfig);
+            }
+        },
+        None if args.openmw_cfg.len() > 1 => TakeAndSwitch(&mut args.openmw_cfg),
+        None => Vec::new(),
     };
 
-    let output_dir = match args.output {
-        Some(ref dir) => {
+    if !profiles.is_empty() {
+        return run_profiles(&profiles, &args);
+    }
+
+    // Double-clicked consoles vanish the moment we exit, taking any printed results with them
+    set_pause_on_exit(
+        args.pause || (notify_backend() == NotifyBackend::Stdout && launched_without_console()),
+    );
+
+    // Content files given on the command line replace openmw.cfg entirely
+    let content_args = TakeAndSwitch(&mut args.content);
+    let data_args = TakeAndSwitch(&mut args.data);
+    let (mut config, root_cfg_path) = content_args
+        .is_empty()
+        .then(|| load_openmw_cfg(&mut args))
+        .unzip();
+
+    let mut output_dir = match (args.output.first(), &config) {
+        (_, Some(config)) if args.install => {
+            let dir = config.user_config_path().join(INSTALL_DIR_NAME);
+
+            if let Err(err) = create_dir_all(&dir) {
+                notification_box(Message::OutputMissing.text(), &err.to_string());
+                exit(ExitCode::BadOutputPath)
+            }
+
+            dir
+        }
+
+        (Some(dir), _) => {
             if dir.is_dir() {
                 dir.to_owned()
             } else {
                 notification_box(
-                    "Can't find output location!",
-                    "WARNING: The requested output path {dir:?} does not exist! Terminating.",
-                    no_notifications,
+                    Message::OutputMissing.text(),
+                    &Message::OutputMissingBody.fill(&[&format!("{dir:?}")]),
+                );
+                exit(ExitCode::BadOutputPath)
+            }
+        }
+
+        (None, _) if args.portable => match s3lightfixes::portable_dir() {
+            Ok(dir) => dir,
+            Err(err) => {
+                notification_box(Message::ExecutableDirMissing.text(), &err.to_string());
+                exit(ExitCode::BadOutputPath)
+            }
+        },
+
+        (_, config) => match config.as_ref().and_then(|config| config.data_local()) {
+            Some(dir) => dir.parsed().to_owned(),
+            None => match current_dir() {
+                Ok(dir) => dir,
+                Err(_) => {
+                    notification_box(
+                        Message::WorkdirMissing.text(),
+                        Message::WorkdirMissingBody.text(),
+                    );
+                    exit(ExitCode::BadOutputPath);
+                }
+            },
+        },
+    };
+
+    let command = args.command.take();
+    let preview_top = args.preview_top;
+    let report_conflicts = args.conflicts;
+    let vfs_debug = args.vfs_debug;
+    let retry_failed = args.retry_failed;
+    let encoding = args.encoding;
+    let install = args.install;
+    let clean = args.clean;
+    let to_stdout = args.stdout;
+    let check_update = args.check_update;
+    let mut light_config = LightConfig::get(args, config.as_ref())?;
+
+    // Installing always enables the plugin, without saving auto_enable to lightconfig.toml
+    light_config.auto_enable |= install;
+
+    // Later openmw.cfg files override earlier ones, as they do in OpenMW
+    let cfg_paths: Vec<PathBuf> = root_cfg_path
+        .iter()
+        .cloned()
+        .chain(
+            config
+                .as_ref()
+                .map(|config| config.user_config_path().join("openmw.cfg")),
+        )
+        .collect();
+
+    light_config.encoding = encoding
+        .or_else(|| Encoding::from_openmw_cfgs(&cfg_paths))
+        .unwrap_or_default();
+
+    set_notify_backend(light_config.notify_backend());
+    backup::set_backup_count(light_config.backup_count);
+    set_language(light_config.language.unwrap_or_else(Language::detect));
+
+    logging::set_json(light_config.json_logs);
+    logging::set_level(if light_config.quiet {
+        LevelFilter::Error
+    } else {
+        light_config.log_level.unwrap_or(if light_config.debug {
+            LevelFilter::Debug
+        } else if light_config.json_logs {
+            LevelFilter::Info
+        } else {
+            LevelFilter::Warn
+        })
+    });
+
+    if let Err(err) = logging::open_run_log(&light_config.config_dir) {
+        log::warn!(
+            "Could not write {RUN_LOG_NAME} to {}: {err}",
+            light_config.config_dir.display()
+        );
+    }
+
+    log::info!(
+        "S3LightFixes {} using lightconfig.toml in {}",
+        env!("CARGO_PKG_VERSION"),
+        light_config.config_dir.display()
+    );
+
+    if let Some(LightCommand::Import { path } | LightCommand::ImportOverrides { path }) = &command {
+        let light_overrides = match &command {
+            Some(LightCommand::ImportOverrides { .. }) => import_foreign_overrides(path)?,
+            _ => import_table(path)?,
+        };
+        let override_count = light_overrides.len();
+        let config_path = light_config.add_light_overrides(light_overrides)?;
+
+        notification_box(
+            Message::LightsImported.text(),
+            &Message::LightsImportedBody.fill(&[
+                &override_count,
+                &path.display(),
+                &config_path.display(),
+            ]),
+        );
+
+        return Ok(());
+    }
+
+    // A bare color doesn't need the load order, so it's previewed before any plugins are read
+    if let Some(LightCommand::Preview { target }) = &command
+        && target.starts_with('#')
+    {
+        let Some([red, green, blue]) = color::from_hex(target) else {
+            log::warn!("{target} is not a valid hex color, like #FFB46E");
+            exit(ExitCode::Failure);
+        };
+
+        let mut light = Light::default();
+        light.data.color = [red, green, blue, 0];
+
+        let mut fixed = light.clone();
+        process_light(&light_config, &mut fixed);
+        print_color_preview(&light, &fixed);
+
+        return Ok(());
+    }
+
+    if let Some(LightCommand::Rollback) = &command {
+        let mut restored = Vec::new();
+
+        for plugin_path in backup::backed_up_plugins(&output_dir)? {
+            if backup::restore(&plugin_path)?
+                && let Some(name) = plugin_path.file_name()
+            {
+                restored.push(name.to_string_lossy().to_string());
+            }
+        }
+
+        if restored.is_empty() {
+            notification_box(
+                Message::NoBackups.text(),
+                &Message::NoBackupsBody.fill(&[&output_dir.display()]),
+            );
+            exit(ExitCode::Failure);
+        }
+
+        // A restored plugin is only useful if the game loads it
+        if light_config.auto_enable
+            && let Some(config) = &mut config
+        {
+            for name in &restored {
+                if !name.ends_with(".esp")
+                    && !config.has_content_file(name)
+                    && let Err(err) = config.add_content_file(name)
+                {
+                    log::error!("{err}");
+                    exit(ExitCode::WriteFailure);
+                }
+            }
+
+            if let Err(err) = config.save_user() {
+                notification_box(Message::ResaveConfigFailed.text(), &err);
+                exit(ExitCode::WriteFailure);
+            }
+        }
+
+        notification_box(
+            Message::RolledBack.text(),
+            &Message::RolledBackBody.fill(&[&restored.join(", "), &output_dir.display()]),
+        );
+
+        return Ok(());
+    }
+
+    if let Some(LightCommand::Update) = &command {
+        if !light_config.self_update {
+            log::warn!(
+                "Updating is disabled. Set self_update = true in lightconfig.toml to allow it."
+            );
+            exit(ExitCode::Failure);
+        }
+
+        let release = Release::latest()?;
+
+        if !release.is_newer() {
+            println!(
+                "S3LightFixes {} is already the latest version",
+                env!("CARGO_PKG_VERSION")
+            );
+            return Ok(());
+        }
+
+        release.install()?;
+        println!("Updated S3LightFixes to {}", release.tag_name);
+
+        return Ok(());
+    }
+
+    let update_check = (light_config.self_update || check_update).then(update::check_in_background);
+
+    log::debug!("{light_config:#?}\n{config:#?}");
+
+    let content_files: Vec<String> = match &config {
+        Some(config) => config
+            .content_files()
+            .iter()
+            .map(|plugin| plugin.to_string())
+            .collect(),
+        None => content_args,
+    };
+
+    if content_files.is_empty() {
+        notification_box(Message::NoPlugins.text(), Message::NoPluginsBody.text());
+        exit(ExitCode::NoPlugins);
+    }
+
+    let mut generated_plugin = Plugin::new();
+    let mut used_ids = UsedIds::new();
+
+    let mut header = Header {
+        version: 1.3,
+        author: FixedString(header_author(&light_config.author)),
+        description: FixedString(GENERATED_MARKER.to_string()),
+        file_type: FileType::Esp,
+        flags: ObjectFlags::default(),
+        num_objects: 0,
+        masters: Vec::new(),
+    };
+
+    let mut directories: Vec<PathBuf> = match &config {
+        Some(config) => config.data_directories().into_iter().cloned().collect(),
+        None if data_args.is_empty() => vec![current_dir()?],
+        None => data_args,
+    };
+
+    // MO2 keeps each mod in its own directory, which openmw.cfg may not know about
+    if let Some(profile) = light_config
+        .mo2_profile
+        .clone()
+        .or_else(mo2::detect_profile)
+    {
+        match mo2::mod_directories(&profile) {
+            Ok(mod_directories) => {
+                log::debug!(
+                    "Using {} mod directories from MO2 profile {}",
+                    mod_directories.len(),
+                    profile.display()
                 );
-                exit(1)
+
+                for dir in mod_directories {
+                    if !directories.contains(&dir) {
+                        directories.push(dir);
+                    }
+                }
+            }
+            Err(err) => log::warn!(
+                "Could not read MO2 profile {}: {err}. Only data directories from openmw.cfg will be used.",
+                profile.display()
+            ),
+        }
+    }
+
+    // OpenMW always searches data-local last, so it overrides every other directory
+    let data_local = light_config.data_local.clone().or_else(|| {
+        config
+            .as_ref()
+            .and_then(|config| config.data_local())
+            .map(|dir| dir.parsed().to_owned())
+    });
+
+    let directories = arrange_data_dirs(directories, data_local, &light_config.skip_data_dirs);
+
+    let fallback_archives = if light_config.fallback_archives.is_empty() {
+        fallback_archives_from_openmw_cfgs(&cfg_paths)
+    } else {
+        light_config.fallback_archives.clone()
+    };
+
+    log::debug!(
+        "Searching {} data directories and {} archives",
+        directories.len(),
+        fallback_archives.len()
+    );
+
+    let vfs = VFS::from_directories(
+        directories.iter().collect(),
+        (!fallback_archives.is_empty()).then(|| fallback_archives.clone()),
+    );
+    let content_file_paths = ContentFiles::scan(&directories);
+
+    let shader_packs = shaders::detect_shader_packs(&directories);
+    log::debug!("Shader packs found in the data directories: {shader_packs:?}");
+
+    for advice in shaders::shader_advice(&shader_packs, &light_config) {
+        log::warn!("{advice}");
+    }
+
+    for warning in light_config.fit_target_engine() {
+        log::warn!("{warning}");
+    }
+
+    // settings.cfg lives next to the user openmw.cfg, so there's none to read without one
+    if let Some(lighting) = config.as_ref().and_then(|config| {
+        LightingSettings::read(
+            &config
+                .user_config_path()
+                .join(openmw_settings::SETTINGS_NAME),
+        )
+    }) {
+        log::debug!("Lighting settings from settings.cfg: {lighting:?}");
+
+        for warning in light_config.fit_lighting_method(&lighting) {
+            log::warn!("{warning}");
+        }
+    }
+
+    for warning in light_config.large_multipliers() {
+        log::warn!("{warning}");
+    }
+
+    let mut load_order = content_files;
+
+    // OpenMW loads groundcover after every content file, and never as a master
+    let groundcover_files: Vec<String> = config
+        .iter()
+        .flat_map(|config| config.groundcover().iter().map(|plugin| plugin.to_string()))
+        .collect();
+
+    let groundcover: HashSet<String> = groundcover_files
+        .iter()
+        .map(|plugin| plugin.to_ascii_lowercase())
+        .collect();
+
+    if light_config.process_groundcover {
+        load_order.extend(groundcover_files);
+    } else if !groundcover.is_empty() {
+        log::debug!(
+            "Skipping {} groundcover plugins. Use --process-groundcover to fix their lights as well.",
+            groundcover.len()
+        );
+    }
+
+    let load_order = content_file_paths.dedupe_links(load_order);
+
+    content_file_paths
+        .fuzzy_matches(&load_order)
+        .iter()
+        .for_each(Warning::log);
+
+    if vfs_debug {
+        print_content_resolution(&content_file_paths, &load_order);
+    }
+
+    let progress = ProgressBar::new(load_order.len() as u64);
+    progress.set_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {bar:40} {pos}/{len} {wide_msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar()),
+    );
+
+    if light_config.quiet || light_config.json_logs {
+        progress.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
+    let parse_failures = AtomicUsize::new(0);
+    let excluded_plugins = AtomicUsize::new(0);
+
+    let known_plugins = KnownPlugins::load(&light_config.config_dir);
+
+    let skip_list_path = light_config.config_dir.join(SKIP_LIST_NAME);
+    let mut skip_list = SkipList::load(&skip_list_path);
+    let skipped_plugins = AtomicUsize::new(0);
+    let new_failures: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+
+    // Only filled in when there are [regions] settings
+    let light_regions: Mutex<LightRegions> = Mutex::new(LightRegions::default());
+    // Only filled in when exterior_radius or interior_radius is used
+    let light_placements: Mutex<LightPlacements> = Mutex::new(LightPlacements::default());
+
+    // Only filled in under --debug
+    let parse_times: Mutex<HashMap<PathBuf, Duration>> = Mutex::new(HashMap::new());
+    let record_counts: Mutex<HashMap<PathBuf, RecordCounts>> = Mutex::new(HashMap::new());
+    let mut stage_timings = StageTimings::new();
+
+    // A dedicated pool bounds how many plugins are held in memory while being read
+    // Zero threads leaves the choice to rayon, which uses one per CPU core
+    let load_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(light_config.load_threads.unwrap_or_default())
+        .build()
+        .map_err(to_io_error)?;
+
+    let plugins = load_pool.install(|| load_order
+    .par_iter()
+    .rev()
+    .filter_map(|plugin| {
+        progress.inc(1);
+
+        let path = content_file_paths.resolve(plugin)?;
+
+        if !is_fixable_plugin(path) {
+            return None;
+        }
+
+        if light_config.is_excluded_plugin(path) {
+            excluded_plugins.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        if !retry_failed && skip_list.should_skip(path) {
+            skipped_plugins.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        progress.set_message(plugin.to_string());
+        let parse_start = Instant::now();
+
+        // Exterior cells are dropped while loading, so their references are counted first
+        let mut plugin_regions = LightRegions::default();
+        let mut plugin_placements = LightPlacements::default();
+        let mut counts = RecordCounts::default();
+        let loaded = load_light_records(path, light_config.strict_parse, |plugin| {
+            if light_config.debug {
+                counts = RecordCounts::read_from(plugin);
+            }
+
+            if !light_config.regions.is_empty() {
+                plugin_regions.record_references(plugin, light_config.encoding);
+            }
+
+            if light_config.uses_placement_radius() {
+                plugin_placements.record_references(plugin, light_config.encoding);
+            }
+        });
+
+        if !plugin_regions.is_empty() && let Ok(mut light_regions) = light_regions.lock() {
+            light_regions.extend(plugin_regions);
+        }
+
+        if !plugin_placements.is_empty() && let Ok(mut light_placements) = light_placements.lock() {
+            light_placements.extend(plugin_placements);
+        }
+
+        if light_config.debug {
+            if let Ok(mut parse_times) = parse_times.lock() {
+                parse_times.insert(path.to_path_buf(), parse_start.elapsed());
+            }
+
+            if let Ok((plugin, _)) = &loaded
+                && let Ok(mut record_counts) = record_counts.lock()
+            {
+                counts.set_kept(plugin);
+                record_counts.insert(path.to_path_buf(), counts);
+            }
+        }
+
+        // Logged by where the plugin really lives, as links in a mod manager's symlink farm all look alike
+        let real_path = canonical_path(path);
+
+        match loaded {
+            Ok((plugin, false)) => Some((plugin, path)),
+            Ok((plugin, true)) => {
+                progress.suspend(|| Warning::RecordsSkipped { path: real_path }.log());
+                Some((plugin, path))
+            }
+            Err(err) => {
+                parse_failures.fetch_add(1, Ordering::Relaxed);
+
+                if let Ok(mut new_failures) = new_failures.lock() {
+                    new_failures.push((path.to_path_buf(), err.to_string()));
+                }
+
+                match known_plugins.find(path) {
+                    Some(known) => progress.suspend(|| log::warn!(
+                        plugin = real_path.display().to_string().as_str();
+                        "Plugin {}: could not be loaded due to error: {}. {}{}\n",
+                        real_path.display(),
+                        err,
+                        known.explanation,
+                        known.link.as_ref().map_or(String::new(), |link| format!(" See {link}"))
+                    )),
+                    None => progress.suspend(|| log::warn!(
+                        plugin = real_path.display().to_string().as_str();
+                        "Plugin {}: could not be loaded due to error: {}. Continuing light fixes without this mod .  . . Everything will be okay. Yes, it's still working.\nIts hash is {}, for adding it to {KNOWN_PLUGINS_NAME}.\n",
+                        real_path.display(),
+                        err,
+                        plugin_hash(path).unwrap_or_else(|_| "unknown".to_string())
+                    )),
+                }
+                None
             }
         }
+    })
+    .collect::<Vec<_>>());
 
-        None => match &mut config.data_local() {
-            Some(dir) => dir.parsed().to_owned(),
-            None => match current_dir() {
-                Ok(dir) => dir,
-                Err(_) => {
-                    notification_box(
-                        "Can't get workdir!",
-                        "[ CRITICAL FAILURE ]: FAILED TO READ CURRENT WORKING DIRECTORY!",
-                        no_notifications,
-                    );
-                    std::process::exit(256);
-                }
-            },
-        },
-    };
+    progress.finish_and_clear();
+    stage_timings.finish_stage("load");
 
-    let light_config = LightConfig::get(args, &config)?;
+    let parse_failures = parse_failures.into_inner();
+    let skipped_plugins = skipped_plugins.into_inner();
 
-    if light_config.debug {
-        dbg!(&light_config, &config);
+    for (path, error) in new_failures.into_inner().unwrap_or_default() {
+        skip_list.record(&path, error);
     }
 
-    if config.content_files().len() == 0 {
+    // Anything which loaded this time, most likely thanks to --retry-failed, isn't broken anymore
+    for (_, path) in &plugins {
+        skip_list.forget(path);
+    }
+
+    if let Err(err) = skip_list.save_if_changed(&skip_list_path) {
+        log::warn!(
+            "Could not save the list of broken plugins to {}: {err}",
+            skip_list_path.display()
+        );
+    }
+
+    if skipped_plugins > 0 {
+        log::warn!(
+            "Skipped {skipped_plugins} plugins which failed to load on a previous run, and haven't changed since. They are listed in {}. Use --retry-failed to try loading them again.",
+            skip_list_path.display()
+        );
+    }
+
+    if plugins.is_empty() && parse_failures + skipped_plugins > 0 {
         notification_box(
-            "No Plugins!",
-            "No plugins were found in openmw.cfg! No lights to fix!",
-            light_config.no_notifications,
+            Message::AllPluginsFailed.text(),
+            &Message::AllPluginsFailedBody.fill(&[&(parse_failures + skipped_plugins)]),
         );
-        std::process::exit(4);
+        exit(ExitCode::ParseFailuresOnly);
     }
 
-    let mut generated_plugin = Plugin::new();
-    let mut used_ids: HashSet<String> = HashSet::new();
+    let abort_for_failures = match light_config.on_plugin_error {
+        PluginErrorPolicy::Skip => false,
+        PluginErrorPolicy::Abort => parse_failures + skipped_plugins > 0,
+        // Plugins on the skip list were already asked about when they first failed
+        PluginErrorPolicy::Prompt => {
+            parse_failures > 0 && !confirm_skipping_failures(parse_failures)
+        }
+    };
 
-    let mut header = Header {
-        version: 1.3,
-        author: FixedString("S3".to_string()),
-        description: FixedString("Plugin generated by s3-lightfixes".to_string()),
-        file_type: FileType::Esp,
-        flags: ObjectFlags::default(),
-        num_objects: 0,
-        masters: Vec::new(),
+    if abort_for_failures {
+        notification_box(
+            Message::PluginsFailed.text(),
+            &Message::PluginsFailedBody.fill(&[&(parse_failures + skipped_plugins)]),
+        );
+        exit(ExitCode::PluginLoadFailed);
+    }
+
+    let mut summary = RunSummary {
+        plugins_scanned: plugins.len(),
+        plugins_skipped: parse_failures + skipped_plugins + excluded_plugins.into_inner(),
+        ..Default::default()
     };
 
-    let directories: Vec<&PathBuf> = config.data_directories();
+    // Number of generated records each plugin is responsible for, indexed like `plugins`
+    // Any plugin left at zero is not written as a master
+    let mut contributions = vec![0u32; plugins.len()];
+    // The plugin each generated record came from, in the order they were generated, for --split-output
+    let mut object_owners: Vec<usize> = Vec::new();
 
-    let vfs = VFS::from_directories(directories, None);
+    // Lights are held back until every plugin has been scanned,
+    // so that identical copies further down the load order can claim ownership of them
+    let mut light_set = LightSet::new(light_config.encoding, light_config.merge_strategy)
+        .with_master_strategy(light_config.master_strategy);
 
-    let plugins = config
-    .content_files()
-    .par_iter()
-    .rev()
-    .filter_map(|plugin| {
-        let vfs_file = vfs.get_file(plugin)?;
-        let path = vfs_file.path();
+    // Every version of every light, only collected for --conflicts
+    let mut light_versions: HashMap<String, Vec<(usize, [u8; 4], u32)>> = HashMap::new();
 
-        if !is_fixable_plugin(path) {
-            return None;
-        }
+    // Old and new values of every generated record, for save_log
+    let log_changes = light_config.save_log && light_config.log_format != LogFormat::Full;
+    let mut change_log = ChangeLog::default();
 
-        match Plugin::from_path_filtered(path, |tag| matches!(&tag, Cell::TAG | Light::TAG)) {
-            Ok(plugin) => Some((plugin, path)),
-            Err(err) => {
-                eprintln!(
-                    "[ WARNING ]: Plugin {}: could not be loaded due to error: {}. Continuing light fixes without this mod .  . . Everything will be okay. Yes, it's still working.\n",
-                    path.display(),
-                    err
-                );
-                None
-            }
-        }
-    })
-    .collect::<Vec<_>>();
+    // Time spent on the cell pass and light scan of each plugin, indexed like `plugins`
+    let mut transform_times = vec![Duration::ZERO; plugins.len()];
+
+    for (plugin_index, (mut plugin, path)) in plugins.into_iter().enumerate() {
+        let transform_start = Instant::now();
 
-    let mut used_objects = 0;
-    for (mut plugin, plugin_path) in plugins {
         // Disable sunlight color for true interiors
         // Only do this for `classic` mode
         for cell in plugin.objects_of_type_mut::<Cell>().filter(|cell| {
-            cell.data.flags.contains(CellFlags::IS_INTERIOR) && cell.atmosphere_data.is_some()
+            light_config.only.includes_cells()
+                && cell.data.flags.contains(CellFlags::IS_INTERIOR)
+                && (cell.atmosphere_data.is_some() || cell.flags.contains(ObjectFlags::DELETED))
         }) {
-            let cell_id = cell.editor_id_ascii_lowercase().into_owned();
+            let cell_id = light_config.encoding.record_id(&cell.editor_id());
+            let quasi_exterior = cell.data.flags.contains(CellFlags::BEHAVES_LIKE_EXTERIOR);
 
             if used_ids.contains(&cell_id) {
                 continue;
             };
 
+            // Later plugins are seen first, so marking a deleted cell as used keeps earlier versions from coming back
+            if cell.flags.contains(ObjectFlags::DELETED) {
+                used_ids.insert(&cell_id);
+
+                if light_config.emit_deletions {
+                    cell.references.clear();
+                    cell.flags = output_record_flags(cell.flags, light_config.mark_modified);
+                    generated_plugin.objects.push(TakeAndSwitch(cell).into());
+                    contributions[plugin_index] += 1;
+                    object_owners.push(plugin_index);
+                }
+
+                continue;
+            }
+
+            let original_atmosphere =
+                log_changes.then(|| (cell.editor_id().to_string(), cell.atmosphere_data.clone()));
+
             match cell.atmosphere_data {
                 Some(ref mut atmo) => {
                     // Need additional handling here for instance replacements!
@@ -288,9 +1574,20 @@ fn main() -> io::Result<()> {
 
                     let mut replaced = false;
 
-                    if light_config.disable_interior_sun {
-                        atmo.sunlight_color = [0, 0, 0, 0];
-                        replaced = true;
+                    if quasi_exterior {
+                        replaced |= light_config.quasi_exterior.apply(atmo);
+                    } else {
+                        if light_config.disable_interior_sun {
+                            atmo.sunlight_color = [0, 0, 0, 0];
+                            replaced = true;
+                        }
+
+                        if !light_config.interior_fog.is_empty() {
+                            light_config
+                                .interior_fog
+                                .apply(&mut atmo.fog_color, &mut atmo.fog_density);
+                            replaced = true;
+                        }
                     }
 
                     for (pattern, replacement_data) in &light_config.ambient_regexes {
@@ -299,45 +1596,16 @@ fn main() -> io::Result<()> {
                         };
 
                         if let Some(ambient) = &replacement_data.ambient {
-                            let hsv: Hsv = Hsv::from_components((
-                                palette::RgbHue::from_degrees(ambient.hue as f32),
-                                ambient.saturation,
-                                ambient.value,
-                            ));
-
-                            let rgb8_color: Srgb<u8> =
-                                <Hsv as IntoColor<Srgb>>::into_color(hsv).into_format();
-
-                            atmo.ambient_color =
-                                [rgb8_color.red, rgb8_color.green, rgb8_color.blue, 0];
+                            atmo.ambient_color = ambient.to_rgba();
                             replaced = true;
                         }
                         if let Some(fog) = &replacement_data.fog {
-                            let hsv: Hsv = Hsv::from_components((
-                                palette::RgbHue::from_degrees(fog.hue as f32),
-                                fog.saturation,
-                                fog.value,
-                            ));
-
-                            let rgb8_color: Srgb<u8> =
-                                <Hsv as IntoColor<Srgb>>::into_color(hsv).into_format();
-
-                            atmo.fog_color = [rgb8_color.red, rgb8_color.green, rgb8_color.blue, 0];
+                            atmo.fog_color = fog.to_rgba();
                             replaced = true;
                         }
 
                         if let Some(sunlight) = &replacement_data.sunlight {
-                            let hsv: Hsv = Hsv::from_components((
-                                palette::RgbHue::from_degrees(sunlight.hue as f32),
-                                sunlight.saturation,
-                                sunlight.value,
-                            ));
-
-                            let rgb8_color: Srgb<u8> =
-                                <Hsv as IntoColor<Srgb>>::into_color(hsv).into_format();
-
-                            atmo.sunlight_color =
-                                [rgb8_color.red, rgb8_color.green, rgb8_color.blue, 0];
+                            atmo.sunlight_color = sunlight.to_rgba();
                             replaced = true;
                         }
 
@@ -348,134 +1616,667 @@ fn main() -> io::Result<()> {
                     }
 
                     if replaced {
+                        if let Some((name, Some(before))) = &original_atmosphere {
+                            change_log
+                                .cells
+                                .push(CellChange::new(name, &path, before, atmo));
+                        }
+
+                        if light_config.cell_records == CellRecordContents::Atmosphere {
+                            strip_to_atmosphere(cell);
+                        }
+
+                        cell.flags = output_record_flags(cell.flags, light_config.mark_modified);
                         generated_plugin.objects.push(TakeAndSwitch(cell).into());
 
-                        used_ids.insert(cell_id);
-                        used_objects += 1;
+                        used_ids.insert(&cell_id);
+                        contributions[plugin_index] += 1;
+                        object_owners.push(plugin_index);
+                        summary.cells += 1;
                     }
                 }
                 None => {}
             }
         }
 
-        plugin
-            .into_objects_of_type::<Light>()
-            .filter_map(|light| {
-                let light_id = light.editor_id_ascii_lowercase().into_owned();
+        let lights: Vec<Light> = plugin.into_objects_of_type::<Light>().collect();
 
-                if !used_ids.contains(&light_id) && !light_config.is_excluded_id(&light_id) {
-                    used_ids.insert(light_id);
-                    Some(light)
-                } else {
-                    None
-                }
+        if report_conflicts {
+            for light in &lights {
+                light_versions
+                    .entry(light_config.encoding.record_id(&light.id))
+                    .or_default()
+                    .push((plugin_index, light.data.color, light.data.radius));
+            }
+        }
+
+        light_set.add_plugin(path.to_path_buf(), lights, |light_id| {
+            !used_ids.contains(light_id) && !light_config.is_excluded_id(light_id)
+        });
+
+        transform_times[plugin_index] = transform_start.elapsed();
+    }
+
+    light_set.finish();
+
+    if light_config.skip_scripted_lights && light_config.only.includes_lights() {
+        summary.scripted_lights = light_set.remove_scripted();
+    }
+    stage_timings.finish_stage("scan");
+    let plugin_paths = &light_set.plugins;
+
+    if light_config.debug {
+        print_plugin_timings(plugin_paths, &parse_times, &record_counts, &transform_times);
+    }
+
+    if report_conflicts {
+        print_conflicts(&light_versions, plugin_paths);
+        return Ok(());
+    }
+
+    if let Some(LightCommand::Classify) = &command {
+        let classes_path = light_config.config_dir.join(CLASSES_NAME);
+        let mut light_classes = LightClasses::load(&classes_path);
+        let mut guessed = 0;
+
+        for (_, light) in &light_set.lights {
+            let light_id = light_config.encoding.record_id(&light.id);
+
+            if !light_classes.lights.contains_key(&light_id) {
+                light_classes.lights.insert(light_id, classify(light));
+                guessed += 1;
+            }
+        }
+
+        light_classes.save(&classes_path)?;
+
+        println!(
+            "Classified {guessed} new lights in {}. Correct any there, and set multipliers for each class under [classes] in lightconfig.toml.",
+            classes_path.display()
+        );
+
+        return Ok(());
+    }
+
+    if let Some(LightCommand::Doctor) = &command {
+        let unregistered = UnregisteredArchives::scan(&directories, &fallback_archives);
+        print_asset_report(&light_set, &vfs, &unregistered);
+        return Ok(());
+    }
+
+    let mut light_contexts = LightContexts::new(
+        &light_config,
+        &vfs,
+        light_regions.into_inner().unwrap_or_default(),
+        light_placements.into_inner().unwrap_or_default(),
+    );
+
+    if let Some(LightCommand::Preview { target }) = &command {
+        let target_id = light_config.encoding.record_id(target);
+
+        let Some((owner, light)) = light_set
+            .lights
+            .iter()
+            .find(|(_, light)| light_config.encoding.record_id(&light.id) == target_id)
+        else {
+            log::warn!("No light with the ID {target} was found in the load order");
+            exit(ExitCode::Failure);
+        };
+
+        let fixed = light_contexts.fix(light);
+
+        println!("{} ({}):", light.id, plugin_paths[*owner].display());
+        print_color_preview(light, &fixed);
+        println!("radius {} -> {}", light.data.radius, fixed.data.radius);
+
+        return Ok(());
+    }
+
+    if let Some(LightCommand::Export { format, path }) = command {
+        let path = path.unwrap_or_else(|| {
+            PathBuf::from(format!("s3lightfixes_lights.{}", format.extension()))
+        });
+
+        let rows: Vec<LightRow> = light_set
+            .lights
+            .iter()
+            .map(|(owner, light)| {
+                let fixed = light_contexts.fix(light);
+
+                let plugin_name = plugin_paths[*owner]
+                    .file_name()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_default();
+
+                LightRow::new(&plugin_name, light, &fixed)
             })
-            .for_each(|mut light| {
-                process_light(&light_config, &mut light);
+            .collect();
 
-                generated_plugin.objects.push(light.into());
-                used_objects += 1;
-            });
+        write_table(&rows, format, File::create(&path)?)?;
+
+        notification_box(
+            Message::LightsExported.text(),
+            &Message::LightsExportedBody.fill(&[&rows.len(), &path.display()]),
+        );
+
+        return Ok(());
+    }
+
+    if let Some(count) = preview_top {
+        print_preview(&mut light_contexts, &light_set.lights, plugin_paths, count);
+        return Ok(());
+    }
+
+    // Indexed like light_set.lights
+    let original_lights: Vec<Light> = if log_changes {
+        light_set
+            .lights
+            .iter()
+            .map(|(_, light)| light.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // Class, region, placement, and model multipliers are part of the transform, so previews and exports match the plugin
+    let outcomes = transform::apply_in(&mut light_set, &light_config, |light| {
+        light_contexts.context_of(light)
+    });
+
+    stage_timings.finish_stage("transform");
+    let plugin_paths = light_set.plugins;
+
+    // Lights are still collected with --only cells, so that previews and exports work the same
+    if !light_config.only.includes_lights() {
+        light_set.lights.clear();
+        light_set.deleted.clear();
+    }
+
+    for (index, ((owner, mut light), outcome)) in
+        light_set.lights.into_iter().zip(outcomes).enumerate()
+    {
+        summary.count_light(outcome);
+
+        if let Some(original) = original_lights.get(index) {
+            change_log
+                .lights
+                .push(LightChange::new(&plugin_paths[owner], original, &light));
+        }
+
+        light.flags = output_record_flags(light.flags, light_config.mark_modified);
+        generated_plugin.objects.push(light.into());
+        contributions[owner] += 1;
+        object_owners.push(owner);
+    }
+
+    // Deleted lights are otherwise left out, which is enough unless something loads after the patch
+    if light_config.emit_deletions {
+        for (owner, mut light) in light_set.deleted {
+            light.flags = output_record_flags(light.flags, light_config.mark_modified);
+            generated_plugin.objects.push(light.into());
+            contributions[owner] += 1;
+            object_owners.push(owner);
+        }
+    }
+
+    // Lights handed out by leveled lists, NPCs, and creatures, eg so guards carry lanterns which don't flicker
+    if light_config.only.includes_lights() && !light_config.inventory_light_swaps.is_empty() {
+        let mut inventory_ids = UsedIds::new();
+
+        for (plugin_index, plugin_path) in plugin_paths.iter().enumerate() {
+            let plugin = match inventory::load_inventory_records(plugin_path) {
+                Ok(plugin) => plugin,
+                Err(err) => {
+                    log::warn!(
+                        "Plugin {}: could not be read for inventory swaps: {err}",
+                        plugin_path.display()
+                    );
+                    continue;
+                }
+            };
+
+            for mut record in inventory::swap_inventory_lights(
+                plugin,
+                &light_config.inventory_light_swaps,
+                light_config.encoding,
+                &mut inventory_ids,
+            ) {
+                match &mut record {
+                    TES3Object::LeveledItem(leveled_item) => {
+                        leveled_item.flags =
+                            output_record_flags(leveled_item.flags, light_config.mark_modified)
+                    }
+                    TES3Object::Npc(npc) => {
+                        npc.flags = output_record_flags(npc.flags, light_config.mark_modified)
+                    }
+                    TES3Object::Creature(creature) => {
+                        creature.flags =
+                            output_record_flags(creature.flags, light_config.mark_modified)
+                    }
+                    _ => {}
+                }
+
+                generated_plugin.objects.push(record);
+                contributions[plugin_index] += 1;
+                object_owners.push(plugin_index);
+            }
+        }
+    }
+
+    let fallback_dirs = match &light_config.fallback_output_dirs {
+        dirs if dirs.is_empty() => default_fallback_dirs(&light_config.config_dir),
+        dirs => dirs.to_owned(),
+    };
+
+    if let Some(package_dir) = &light_config.lua_package_dir
+        && !to_stdout
+    {
+        match write_lua_package(package_dir, &light_config) {
+            Ok(omwscripts_path) => log::info!(
+                "Wrote the Lua package for runtime lights to {}",
+                omwscripts_path.display()
+            ),
+            Err(err) => log::warn!(
+                "Couldn't write the Lua package to {}: {err}",
+                package_dir.display()
+            ),
+        }
+    }
+
+    // preserve_edits and ESP output are refused alongside split output when the config is loaded
+    if light_config.split_output == SplitOutput::PerPlugin && !to_stdout {
+        let mut split = split_plugins(
+            generated_plugin.objects,
+            &object_owners,
+            &plugin_paths,
+            &groundcover,
+            &light_config,
+        )?;
+
+        // Nothing is written unless every plugin survives a round trip through its serialized form
+        for (_, plugin) in &split {
+            validate_or_exit(plugin, &load_order, &content_file_paths, &light_config);
+        }
+
+        if clean {
+            clean_stray_outputs(&light_config, config.as_mut(), &directories, &output_dir);
+        }
+
+        let mut saved = Vec::new();
+        let mut any_changed = false;
+
+        for (plugin_name, plugin) in &mut split {
+            match save_plugin(&output_dir, &fallback_dirs, plugin_name, plugin) {
+                Ok(saved_plugin) => {
+                    saved_plugin.warnings.iter().for_each(Warning::log);
+
+                    summary.output_size +=
+                        metadata(&saved_plugin.path).map_or(0, |meta| meta.len());
+                    any_changed |= saved_plugin.changed;
+                    any_changed |=
+                        save_copies(&light_config, plugin_name, plugin, Message::SaveFailed);
+                    saved.push(saved_plugin);
+                }
+                Err(err) => {
+                    notification_box(Message::SaveFailed.text(), &err.to_string());
+                    exit(ExitCode::WriteFailure);
+                }
+            }
+        }
+
+        // Plugins which fell back to another directory take the rest of the output with them
+        if let Some(dir) = saved
+            .last()
+            .and_then(|saved_plugin| saved_plugin.path.parent())
+        {
+            output_dir = dir.to_path_buf();
+        }
+
+        if light_config.auto_enable
+            && let Some(config) = &mut config
+        {
+            for saved_plugin in &saved {
+                if let Some(name) = saved_plugin.path.file_name()
+                    && !config.has_content_file(&name.to_string_lossy())
+                    && let Err(err) = config.add_content_file(&name.to_string_lossy())
+                {
+                    log::error!("{err}");
+                    exit(ExitCode::WriteFailure);
+                }
+            }
+
+            if let Err(err) = config.save_user() {
+                notification_box(Message::ResaveConfigFailed.text(), &err);
+                exit(ExitCode::WriteFailure);
+            }
+        }
+
+        if install {
+            install_output_dir(&light_config, &config, &output_dir);
+        }
+
+        write_exterior_nights(&light_config, &config, &root_cfg_path);
+
+        if light_config.save_log {
+            let plugins: Vec<&Plugin> = split.iter().map(|(_, plugin)| plugin).collect();
+            write_run_log(&light_config, &change_log, &plugins)?;
+        }
+
+        stage_timings.finish_stage("save");
+
+        if light_config.debug {
+            print_stage_timings(&stage_timings);
+        }
+
+        finish_run(
+            &light_config,
+            &summary,
+            &Message::SplitPlugins.fill(&[&saved.len()]),
+            &output_dir,
+            any_changed,
+            update_check,
+        );
+
+        return Ok(());
+    }
+
+    // Plugins were collected in reverse, so walking them backwards appends masters in load order
+    // A plugin listed twice in the load order is still only one master
+    let mut master_names: HashSet<String> = HashSet::new();
+
+    for (plugin_index, plugin_path) in plugin_paths.iter().enumerate().rev() {
+        let used_objects = contributions[plugin_index];
 
         if used_objects > 0 {
+            // The size OpenMW checks is that of the file the VFS resolved the plugin to
             let plugin_size = metadata(plugin_path)?.len();
             let plugin_string = match plugin_path.file_name() {
                 Some(name) => name.to_string_lossy().to_string(),
                 None => {
                     notification_box(
-                        "Bad plugin path!",
-                        "Lightfixes could not resolve the name of one of your plugins! This is UBER Bad and should never happen!",
-                        light_config.no_notifications,
+                        Message::BadPluginPath.text(),
+                        Message::BadPluginPathBody.text(),
                     );
-                    std::process::exit(3);
+                    exit(ExitCode::Failure);
                 }
             };
 
-            header.masters.insert(0, (plugin_string, plugin_size));
+            // Groundcover can't be a master, as OpenMW would report it missing from the content list
+            let master_name = plugin_string.to_ascii_lowercase();
+            if !groundcover.contains(&master_name) && master_names.insert(master_name) {
+                header.masters.push((plugin_string, plugin_size));
+            }
 
-            header.num_objects += TakeAndSwitch(&mut used_objects);
+            header.num_objects += used_objects;
         }
     }
 
-    if light_config.debug {
-        dbg!(&header);
+    log::debug!("{header:#?}");
+
+    if let Some(max_masters) = light_config.max_masters
+        && header.masters.len() > max_masters
+    {
+        match light_config.master_strategy {
+            MasterStrategy::Full => log::warn!(
+                "The generated plugin needs {} masters, more than max_masters allows ({max_masters}). Use --master-strategy minimal to leave out mods which only change parts of lights that lightfixes doesn't touch.",
+                header.masters.len()
+            ),
+            MasterStrategy::Minimal => log::warn!(
+                "The generated plugin needs {} masters, more than max_masters allows ({max_masters}), even with the minimal master strategy.",
+                header.masters.len()
+            ),
+        }
     }
 
     if header.masters.len() == 0 {
-        notification_box(
-            "No masters found!",
-            "The generated plugin was not found to have any master files! It's empty! Try running lightfixes again using the S3L_DEBUG environment variable",
-            light_config.no_notifications,
-        );
-        std::process::exit(2);
+        notification_box(Message::NoMasters.text(), Message::NoMastersBody.text());
+        exit(ExitCode::NothingGenerated);
     }
 
+    let mut generation = GenerationMetadata::new(&light_config, header.masters.len());
+
+    // The ESP copy can't have OpenMW-only masters, so the records taken from them are left out of it
+    let openmw_only_records: HashSet<(String, String)> = generated_plugin
+        .objects
+        .iter()
+        .zip(&object_owners)
+        .filter(|(_, owner)| {
+            plugin_paths[**owner]
+                .file_name()
+                .is_some_and(|name| is_openmw_only(&name.to_string_lossy()))
+        })
+        .map(|(object, _)| record_key(object))
+        .collect();
+
     generated_plugin.objects.push(TES3Object::Header(header));
     generated_plugin.sort_objects();
 
+    // Cached as generated, before any hand edits are carried over, so that they're still detected next time
+    let untouched_plugin = light_config
+        .preserve_edits
+        .then(|| generated_plugin.clone());
+
+    if light_config.preserve_edits {
+        let cache_path = light_config.config_dir.join(CACHE_NAME);
+
+        if let (Ok(previous_output), Ok(previous_cache)) = (
+            Plugin::from_path(output_dir.join(PLUGIN_NAME)),
+            Plugin::from_path(&cache_path),
+        ) {
+            let preserved =
+                preserve_manual_edits(&mut generated_plugin, &previous_output, &previous_cache);
+
+            if preserved > 0 {
+                report(
+                    &light_config,
+                    &format!(
+                        "Kept {preserved} hand-edited records from the previous {PLUGIN_NAME}"
+                    ),
+                );
+            }
+        }
+    }
+
+    if light_config.output_format.writes_omwaddon() {
+        generation.stamp_unless_unchanged(
+            &mut generated_plugin,
+            &output_dir.join(PLUGIN_NAME),
+            Plugin::clone,
+        );
+    } else {
+        generation.stamp_unless_unchanged(
+            &mut generated_plugin,
+            &output_dir.join(ESP_PLUGIN_NAME),
+            |plugin| to_esp_plugin(plugin, &openmw_only_records),
+        );
+    }
+
+    // Nothing is written unless the plugin survives a round trip through its serialized form
+    validate_or_exit(
+        &generated_plugin,
+        &load_order,
+        &content_file_paths,
+        &light_config,
+    );
+
+    // Nothing else is written, so that the plugin can be piped straight into something else
+    if to_stdout {
+        let bytes = if light_config.output_format == OutputFormat::Esp {
+            to_esp_plugin(&generated_plugin, &openmw_only_records).save_bytes()
+        } else {
+            generated_plugin.save_bytes()
+        };
+
+        if let Err(err) = bytes.and_then(|bytes| {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(&bytes)?;
+            stdout.flush()
+        }) {
+            notification_box(Message::SaveFailed.text(), &err.to_string());
+            exit(ExitCode::WriteFailure);
+        }
+
+        return Ok(());
+    }
+
     // If the old plugin format exists, remove it
-    // Do it before serializing the new plugin, as the target dir may still be the old one
-    if let Some(dir) = &mut config.data_local() {
+    // Leave it alone when it is also the output, so that an unchanged plugin isn't rewritten
+    if let Some(dir) = config.as_ref().and_then(|config| config.data_local()) {
         let old_plug_path = dir.parsed().join(PLUGIN_NAME);
-        if old_plug_path.is_file() {
+        if old_plug_path.is_file() && old_plug_path != output_dir.join(PLUGIN_NAME) {
             let _ = remove_file(old_plug_path);
         }
     }
 
-    if let Err(err) = save_plugin(&output_dir, &mut generated_plugin) {
-        notification_box(
-            "Failed to save plugin!",
-            &err.to_string(),
-            light_config.no_notifications,
-        );
-    };
+    if clean {
+        clean_stray_outputs(&light_config, config.as_mut(), &directories, &output_dir);
+    }
+
+    // Stays false only when every plugin written was identical to the one already on disk
+    let mut any_changed = false;
+
+    if light_config.output_format.writes_omwaddon() {
+        match save_plugin(
+            &output_dir,
+            &fallback_dirs,
+            PLUGIN_NAME,
+            &mut generated_plugin,
+        ) {
+            Ok(saved) => {
+                saved.warnings.iter().for_each(Warning::log);
+
+                // Everything else follows the plugin if it had to go elsewhere
+                if let Some(dir) = saved.path.parent() {
+                    output_dir = dir.to_path_buf();
+                }
+
+                summary.output_size += metadata(&saved.path).map_or(0, |meta| meta.len());
+                any_changed |= saved.changed;
+                any_changed |= save_copies(
+                    &light_config,
+                    PLUGIN_NAME,
+                    &mut generated_plugin,
+                    Message::SaveFailed,
+                );
+            }
+            Err(err) => {
+                notification_box(Message::SaveFailed.text(), &err.to_string());
+                exit(ExitCode::WriteFailure);
+            }
+        };
+    }
+
+    if light_config.output_format.writes_esp() {
+        let mut esp_plugin = to_esp_plugin(&generated_plugin, &openmw_only_records);
+
+        match save_plugin(
+            &output_dir,
+            &fallback_dirs,
+            ESP_PLUGIN_NAME,
+            &mut esp_plugin,
+        ) {
+            Ok(saved) => {
+                saved.warnings.iter().for_each(Warning::log);
+
+                if let Some(dir) = saved.path.parent() {
+                    output_dir = dir.to_path_buf();
+                }
+
+                summary.output_size += metadata(&saved.path).map_or(0, |meta| meta.len());
+                any_changed |= saved.changed;
+                any_changed |= save_copies(
+                    &light_config,
+                    ESP_PLUGIN_NAME,
+                    &mut esp_plugin,
+                    Message::SaveEspFailed,
+                );
+            }
+            Err(err) => {
+                notification_box(Message::SaveEspFailed.text(), &err.to_string());
+                exit(ExitCode::WriteFailure);
+            }
+        };
+    }
+
+    if light_config.write_metadata {
+        let metadata_path = output_dir.join(METADATA_NAME);
+
+        if let Err(err) = serde_json::to_string_pretty(&generation)
+            .map_err(to_io_error)
+            .and_then(|json| std::fs::write(&metadata_path, json))
+        {
+            log::warn!("Could not write {}: {err}", metadata_path.display());
+        }
+    }
+
+    // Failing to cache only means hand edits can't be detected on the next run
+    if let Some(untouched_plugin) = &untouched_plugin {
+        let cache_path = light_config.config_dir.join(CACHE_NAME);
+
+        if let Err(err) = untouched_plugin.save_path(&cache_path) {
+            log::error!(
+                "Could not cache the generated plugin at {}: {err}",
+                cache_path.display()
+            );
+        }
+    }
 
     // Handle this arg via clap
-    if light_config.auto_enable {
+    // The ESP copy is meant for the original engine, so only the omwaddon is ever enabled
+    // There's no openmw.cfg to enable it in when content files come from the command line
+    if light_config.auto_enable
+        && light_config.output_format.writes_omwaddon()
+        && let Some(config) = &mut config
+    {
         if !config.has_content_file(&PLUGIN_NAME) {
             match config.add_content_file(&PLUGIN_NAME) {
                 Ok(_) => {
                     if let Err(err) = config.save_user() {
-                        notification_box(
-                            "Failed to resave openmw.cfg!",
-                            &err,
-                            light_config.no_notifications,
-                        );
+                        notification_box(Message::ResaveConfigFailed.text(), &err);
+                        exit(ExitCode::WriteFailure);
                     } else {
-                        let lightfix_enabled_msg = format!(
-                            "Wrote user openmw.cfg at {} successfully!",
-                            config.user_config_path().display()
-                        );
-                        notification_box(
-                            "Lightfixes enabled!",
-                            &lightfix_enabled_msg,
-                            light_config.no_notifications,
-                        );
+                        let lightfix_enabled_msg =
+                            Message::EnabledBody.fill(&[&config.user_config_path().display()]);
+                        notification_box(Message::Enabled.text(), &lightfix_enabled_msg);
                     }
                 }
                 Err(err) => {
-                    eprintln!("{err}");
-                    std::process::exit(256);
+                    log::error!("{err}");
+                    exit(ExitCode::WriteFailure);
                 }
             };
         }
     }
 
+    if install {
+        install_output_dir(&light_config, &config, &output_dir);
+    }
+
+    write_exterior_nights(&light_config, &config, &root_cfg_path);
+
     if light_config.save_log {
-        let path = config.user_config_path().join(LOG_NAME);
-        let mut file = File::create(path)?;
-        let _ = write!(file, "{}", format!("{:#?}", &generated_plugin));
+        write_run_log(&light_config, &change_log, &[&generated_plugin])?;
     }
 
-    let lights_fixed = format!(
-        "S3LightFixes.omwaddon generated, enabled, and saved in {}",
-        output_dir.display()
-    );
+    stage_timings.finish_stage("save");
 
-    notification_box(
-        &"Lightfixes successful!",
-        &lights_fixed,
-        light_config.no_notifications,
+    if light_config.debug {
+        print_stage_timings(&stage_timings);
+    }
+
+    let written_plugins = match light_config.output_format {
+        OutputFormat::Omwaddon => PLUGIN_NAME.to_string(),
+        OutputFormat::Esp => ESP_PLUGIN_NAME.to_string(),
+        OutputFormat::Both => Message::PluginsAnd.fill(&[&PLUGIN_NAME, &ESP_PLUGIN_NAME]),
+    };
+
+    finish_run(
+        &light_config,
+        &summary,
+        &written_plugins,
+        &output_dir,
+        any_changed,
+        update_check,
     );
 
     Ok(())
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use tes3::esp::Light;
+
+/// How lights defined by more than one plugin are combined
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Use the light from the last plugin defining it, ignoring all others
+    #[default]
+    LastWins,
+    /// Start from the first plugin defining the light, and take each field from the last plugin which changed it,
+    /// so that edits to different parts of a light by different mods are all kept
+    Merge,
+}
+
+/// Overwrites `merged` with `field` from `version`, if `version` changed it from `base`
+macro_rules! merge_field {
+    ($merged:ident, $base:ident, $version:ident, $($field:tt)+) => {
+        if $version.$($field)+ != $base.$($field)+ {
+            $merged.$($field)+ = $version.$($field)+.clone();
+        }
+    };
+}
+
+/// Combines every version of a light, given in load order, like a merged objects patch would
+/// Returns None if there are no versions
+pub fn merge_lights(versions: &[Light]) -> Option<Light> {
+    let (base, overrides) = versions.split_first()?;
+    let mut merged = base.clone();
+
+    for version in overrides {
+        merge_field!(merged, base, version, flags);
+        merge_field!(merged, base, version, name);
+        merge_field!(merged, base, version, script);
+        merge_field!(merged, base, version, mesh);
+        merge_field!(merged, base, version, icon);
+        merge_field!(merged, base, version, sound);
+        merge_field!(merged, base, version, data.weight);
+        merge_field!(merged, base, version, data.value);
+        merge_field!(merged, base, version, data.time);
+        merge_field!(merged, base, version, data.radius);
+        merge_field!(merged, base, version, data.color);
+        merge_field!(merged, base, version, data.flags);
+    }
+
+    // The ID of the last version is kept, in case plugins disagree on its case
+    if let Some(last) = overrides.last() {
+        merged.id = last.id.clone();
+    }
+
+    Some(merged)
+}
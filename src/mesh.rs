@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use tes3::nif::{NiStream, NiTriShapeData};
+
+/// Radius of the largest piece of geometry in a NIF, in game units
+/// Node transforms aren't applied, so this is an approximation of the model's real size,
+/// but it's more than enough to tell a candle from a chandelier
+pub fn bounding_radius(path: &Path) -> Option<f32> {
+    let stream = NiStream::from_path(path).ok()?;
+
+    stream
+        .objects_of_type::<NiTriShapeData>()
+        .map(|shape| shape.bound.radius)
+        .filter(|radius| radius.is_finite() && *radius > 0.0)
+        .reduce(f32::max)
+}
+
+/// How much to scale a light's radius by, given the size of its model
+/// Models the size of `reference_radius` are left alone, and the effect is dampened
+/// and bounded so that huge or tiny meshes can't produce absurd radii
+pub fn radius_scale(mesh_radius: f32, reference_radius: f32) -> f32 {
+    (mesh_radius / reference_radius).sqrt().clamp(0.5, 2.0)
+}
+
+/// Turns the model path stored in a record into a path inside the VFS
+pub fn vfs_mesh_path(record_mesh: &str) -> String {
+    format!("meshes/{}", record_mesh.replace('\\', "/"))
+}
@@ -0,0 +1,548 @@
+use std::{
+    env::var,
+    fmt::{Display, Write},
+    sync::RwLock,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Languages which notifications can be shown in
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    #[value(alias = "en")]
+    #[serde(alias = "en")]
+    English,
+    #[value(alias = "de")]
+    #[serde(alias = "de")]
+    German,
+    #[value(alias = "fr")]
+    #[serde(alias = "fr")]
+    French,
+    #[value(alias = "ru")]
+    #[serde(alias = "ru")]
+    Russian,
+    #[value(alias = "pl")]
+    #[serde(alias = "pl")]
+    Polish,
+}
+
+impl Language {
+    /// Reads a POSIX-style locale, eg `de_DE.UTF-8`
+    pub fn from_locale(locale: &str) -> Option<Language> {
+        match locale.get(..2)?.to_ascii_lowercase().as_str() {
+            "en" => Some(Self::English),
+            "de" => Some(Self::German),
+            "fr" => Some(Self::French),
+            "ru" => Some(Self::Russian),
+            "pl" => Some(Self::Polish),
+            _ => None,
+        }
+    }
+
+    /// Guesses the user's language from the environment, falling back to English
+    pub fn detect() -> Language {
+        Self::from_environment(|name| var(name).ok())
+    }
+
+    /// The first supported language among the locale variables `lookup` finds, in the order POSIX checks them
+    fn from_environment(lookup: impl Fn(&str) -> Option<String>) -> Language {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| lookup(name))
+            .find_map(|locale| Self::from_locale(&locale))
+            .unwrap_or_default()
+    }
+}
+
+/// Every message lightfixes shows through notification_box
+/// `{}` in a message is filled in, in order, by Message::fill
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Message {
+    LocateConfigFailed,
+    ReadConfigFailed,
+    ReadLightConfigFailed,
+    ReadLightConfigFailedBody,
+    UnknownPreset,
+    UnknownPresetBody,
+    InvalidLightConfig,
+    InvalidLightConfigBody,
+    OutputMissing,
+    OutputMissingBody,
+    ExecutableDirMissing,
+    WorkdirMissing,
+    WorkdirMissingBody,
+    LightsImported,
+    LightsImportedBody,
+    LightsExported,
+    LightsExportedBody,
+    NoPlugins,
+    NoPluginsBody,
+    BadPluginPath,
+    BadPluginPathBody,
+    NoMasters,
+    NoMastersBody,
+    SaveFailed,
+    SaveEspFailed,
+    ResaveConfigFailed,
+    Enabled,
+    EnabledBody,
+    Successful,
+    SuccessfulBody,
+    PluginsAnd,
+    SplitPlugins,
+    ValidationFailed,
+    ValidationFailedBody,
+    AllPluginsFailed,
+    AllPluginsFailedBody,
+    PluginsFailed,
+    PluginsFailedBody,
+    SummaryBody,
+    UnchangedBody,
+    Installed,
+    InstalledBody,
+    RolledBack,
+    RolledBackBody,
+    NoBackups,
+    NoBackupsBody,
+}
+
+impl Message {
+    /// English, German, French, Russian, and Polish, in the same order as Language
+    fn translations(self) -> [&'static str; 5] {
+        match self {
+            Self::LocateConfigFailed => [
+                "Failed to locate openmw.cfg!",
+                "openmw.cfg konnte nicht gefunden werden!",
+                "Impossible de trouver openmw.cfg !",
+                "Не удалось найти openmw.cfg!",
+                "Nie można odnaleźć openmw.cfg!",
+            ],
+            Self::ReadConfigFailed => [
+                "Failed to read configuration file!",
+                "Konfigurationsdatei konnte nicht gelesen werden!",
+                "Impossible de lire le fichier de configuration !",
+                "Не удалось прочитать файл конфигурации!",
+                "Nie można odczytać pliku konfiguracyjnego!",
+            ],
+            Self::ReadLightConfigFailed => [
+                "Failed to read light config!",
+                "Lichtkonfiguration konnte nicht gelesen werden!",
+                "Impossible de lire la configuration des lumières !",
+                "Не удалось прочитать настройки освещения!",
+                "Nie można odczytać konfiguracji świateł!",
+            ],
+            Self::ReadLightConfigFailedBody => [
+                "Lightconfig.toml couldn't be read: {}",
+                "lightConfig.toml konnte nicht gelesen werden: {}",
+                "Impossible de lire lightConfig.toml : {}",
+                "Не удалось прочитать lightConfig.toml: {}",
+                "Nie można odczytać lightConfig.toml: {}",
+            ],
+            Self::UnknownPreset => [
+                "Unknown preset!",
+                "Unbekannte Voreinstellung!",
+                "Préréglage inconnu !",
+                "Неизвестный пресет!",
+                "Nieznany preset!",
+            ],
+            Self::UnknownPresetBody => [
+                "No preset named {} exists! Built-in presets are: {}",
+                "Es gibt keine Voreinstellung namens {}! Eingebaute Voreinstellungen sind: {}",
+                "Aucun préréglage nommé {} n'existe ! Les préréglages intégrés sont : {}",
+                "Пресета с именем {} не существует! Встроенные пресеты: {}",
+                "Preset o nazwie {} nie istnieje! Wbudowane presety: {}",
+            ],
+            Self::InvalidLightConfig => [
+                "Invalid light config!",
+                "Ungültige Lichtkonfiguration!",
+                "Configuration des lumières invalide !",
+                "Недопустимые настройки освещения!",
+                "Nieprawidłowa konfiguracja świateł!",
+            ],
+            Self::InvalidLightConfigBody => [
+                "Some settings can't be used:\n{}",
+                "Einige Einstellungen können nicht verwendet werden:\n{}",
+                "Certains paramètres ne peuvent pas être utilisés :\n{}",
+                "Некоторые настройки нельзя использовать:\n{}",
+                "Niektórych ustawień nie można użyć:\n{}",
+            ],
+            Self::OutputMissing => [
+                "Can't find output location!",
+                "Ausgabeort nicht gefunden!",
+                "Emplacement de sortie introuvable !",
+                "Не удаётся найти папку вывода!",
+                "Nie można znaleźć lokalizacji wyjściowej!",
+            ],
+            Self::OutputMissingBody => [
+                "WARNING: The requested output path {} does not exist! Terminating.",
+                "WARNUNG: Der angegebene Ausgabepfad {} existiert nicht! Abbruch.",
+                "ATTENTION : le chemin de sortie demandé {} n'existe pas ! Arrêt.",
+                "ВНИМАНИЕ: указанный путь вывода {} не существует! Завершение работы.",
+                "UWAGA: podana ścieżka wyjściowa {} nie istnieje! Przerywanie.",
+            ],
+            Self::ExecutableDirMissing => [
+                "Can't find executable directory!",
+                "Programmverzeichnis nicht gefunden!",
+                "Impossible de trouver le dossier de l'exécutable !",
+                "Не удаётся найти папку программы!",
+                "Nie można znaleźć katalogu programu!",
+            ],
+            Self::WorkdirMissing => [
+                "Can't get workdir!",
+                "Arbeitsverzeichnis nicht verfügbar!",
+                "Impossible d'obtenir le dossier de travail !",
+                "Не удаётся получить рабочую папку!",
+                "Nie można ustalić katalogu roboczego!",
+            ],
+            Self::WorkdirMissingBody => [
+                "[ CRITICAL FAILURE ]: FAILED TO READ CURRENT WORKING DIRECTORY!",
+                "[ KRITISCHER FEHLER ]: AKTUELLES ARBEITSVERZEICHNIS KONNTE NICHT GELESEN WERDEN!",
+                "[ ÉCHEC CRITIQUE ] : IMPOSSIBLE DE LIRE LE DOSSIER DE TRAVAIL ACTUEL !",
+                "[ КРИТИЧЕСКАЯ ОШИБКА ]: НЕ УДАЛОСЬ ПРОЧИТАТЬ ТЕКУЩУЮ РАБОЧУЮ ПАПКУ!",
+                "[ BŁĄD KRYTYCZNY ]: NIE MOŻNA ODCZYTAĆ BIEŻĄCEGO KATALOGU ROBOCZEGO!",
+            ],
+            Self::LightsImported => [
+                "Lights imported!",
+                "Lichter importiert!",
+                "Lumières importées !",
+                "Источники света импортированы!",
+                "Światła zaimportowane!",
+            ],
+            Self::LightsImportedBody => [
+                "Saved {} light overrides from {} into {}",
+                "{} Lichtüberschreibungen aus {} in {} gespeichert",
+                "{} remplacements de lumières de {} enregistrés dans {}",
+                "Сохранено переопределений света: {} из {} в {}",
+                "Zapisano {} nadpisań świateł z {} do {}",
+            ],
+            Self::LightsExported => [
+                "Lights exported!",
+                "Lichter exportiert!",
+                "Lumières exportées !",
+                "Источники света экспортированы!",
+                "Światła wyeksportowane!",
+            ],
+            Self::LightsExportedBody => [
+                "Wrote {} lights to {}",
+                "{} Lichter nach {} geschrieben",
+                "{} lumières écrites dans {}",
+                "Записано источников света: {} в {}",
+                "Zapisano {} świateł do {}",
+            ],
+            Self::NoPlugins => [
+                "No Plugins!",
+                "Keine Plugins!",
+                "Aucun plugin !",
+                "Нет плагинов!",
+                "Brak pluginów!",
+            ],
+            Self::NoPluginsBody => [
+                "No plugins were found in openmw.cfg! No lights to fix!",
+                "In openmw.cfg wurden keine Plugins gefunden! Keine Lichter zu korrigieren!",
+                "Aucun plugin trouvé dans openmw.cfg ! Aucune lumière à corriger !",
+                "В openmw.cfg не найдено плагинов! Нечего исправлять!",
+                "Nie znaleziono pluginów w openmw.cfg! Brak świateł do poprawienia!",
+            ],
+            Self::BadPluginPath => [
+                "Bad plugin path!",
+                "Ungültiger Plugin-Pfad!",
+                "Chemin de plugin invalide !",
+                "Неверный путь к плагину!",
+                "Nieprawidłowa ścieżka pluginu!",
+            ],
+            Self::BadPluginPathBody => [
+                "Lightfixes could not resolve the name of one of your plugins! This is UBER Bad and should never happen!",
+                "Lightfixes konnte den Namen eines deiner Plugins nicht ermitteln! Das ist SEHR schlecht und sollte nie passieren!",
+                "Lightfixes n'a pas pu déterminer le nom d'un de vos plugins ! C'est TRÈS grave et ne devrait jamais arriver !",
+                "Lightfixes не удалось определить имя одного из ваших плагинов! Это ОЧЕНЬ плохо и никогда не должно происходить!",
+                "Lightfixes nie mógł ustalić nazwy jednego z twoich pluginów! To BARDZO źle i nigdy nie powinno się zdarzyć!",
+            ],
+            Self::NoMasters => [
+                "No masters found!",
+                "Keine Master gefunden!",
+                "Aucun master trouvé !",
+                "Мастер-файлы не найдены!",
+                "Nie znaleziono plików master!",
+            ],
+            Self::NoMastersBody => [
+                "The generated plugin was not found to have any master files! It's empty! Try running lightfixes again using the S3L_DEBUG environment variable",
+                "Das erzeugte Plugin hat keine Master-Dateien! Es ist leer! Versuche, Lightfixes erneut mit der Umgebungsvariable S3L_DEBUG auszuführen",
+                "Le plugin généré n'a aucun fichier master ! Il est vide ! Relancez lightfixes avec la variable d'environnement S3L_DEBUG",
+                "У созданного плагина нет мастер-файлов! Он пуст! Попробуйте снова запустить lightfixes с переменной окружения S3L_DEBUG",
+                "Wygenerowany plugin nie ma żadnych plików master! Jest pusty! Spróbuj ponownie uruchomić lightfixes ze zmienną środowiskową S3L_DEBUG",
+            ],
+            Self::SaveFailed => [
+                "Failed to save plugin!",
+                "Plugin konnte nicht gespeichert werden!",
+                "Impossible d'enregistrer le plugin !",
+                "Не удалось сохранить плагин!",
+                "Nie można zapisać pluginu!",
+            ],
+            Self::SaveEspFailed => [
+                "Failed to save ESP plugin!",
+                "ESP-Plugin konnte nicht gespeichert werden!",
+                "Impossible d'enregistrer le plugin ESP !",
+                "Не удалось сохранить плагин ESP!",
+                "Nie można zapisać pluginu ESP!",
+            ],
+            Self::ResaveConfigFailed => [
+                "Failed to resave openmw.cfg!",
+                "openmw.cfg konnte nicht erneut gespeichert werden!",
+                "Impossible de réenregistrer openmw.cfg !",
+                "Не удалось пересохранить openmw.cfg!",
+                "Nie można ponownie zapisać openmw.cfg!",
+            ],
+            Self::Enabled => [
+                "Lightfixes enabled!",
+                "Lightfixes aktiviert!",
+                "Lightfixes activé !",
+                "Lightfixes включён!",
+                "Lightfixes włączony!",
+            ],
+            Self::EnabledBody => [
+                "Wrote user openmw.cfg at {} successfully!",
+                "Benutzer-openmw.cfg unter {} erfolgreich geschrieben!",
+                "openmw.cfg utilisateur écrit avec succès dans {} !",
+                "Пользовательский openmw.cfg успешно записан в {}!",
+                "Pomyślnie zapisano openmw.cfg użytkownika w {}!",
+            ],
+            Self::Installed => [
+                "Lightfixes installed!",
+                "Lightfixes installiert!",
+                "Lightfixes installé !",
+                "Lightfixes установлен!",
+                "Lightfixes zainstalowany!",
+            ],
+            Self::InstalledBody => [
+                "Added {} as a data directory in {}",
+                "{} als Datenverzeichnis in {} hinzugefügt",
+                "{} ajouté comme répertoire de données dans {}",
+                "{} добавлен как каталог данных в {}",
+                "Dodano {} jako katalog danych w {}",
+            ],
+            Self::RolledBack => [
+                "Lightfixes rolled back!",
+                "Lightfixes zurückgesetzt!",
+                "Lightfixes restauré !",
+                "Lightfixes откачен!",
+                "Lightfixes przywrócony!",
+            ],
+            Self::RolledBackBody => [
+                "Restored the previous version of {} in {}",
+                "Vorherige Version von {} in {} wiederhergestellt",
+                "Version précédente de {} restaurée dans {}",
+                "Восстановлена предыдущая версия {} в {}",
+                "Przywrócono poprzednią wersję {} w {}",
+            ],
+            Self::NoBackups => [
+                "Nothing to roll back!",
+                "Nichts zum Zurücksetzen!",
+                "Rien à restaurer !",
+                "Нечего откатывать!",
+                "Nie ma czego przywrócić!",
+            ],
+            Self::NoBackupsBody => [
+                "No backups of generated plugins were found in {}",
+                "In {} wurden keine Sicherungen erzeugter Plugins gefunden",
+                "Aucune sauvegarde de plugin généré trouvée dans {}",
+                "Резервные копии созданных плагинов не найдены в {}",
+                "Nie znaleziono kopii zapasowych wygenerowanych pluginów w {}",
+            ],
+            Self::Successful => [
+                "Lightfixes successful!",
+                "Lightfixes erfolgreich!",
+                "Lightfixes réussi !",
+                "Lightfixes выполнен успешно!",
+                "Lightfixes zakończony sukcesem!",
+            ],
+            Self::SuccessfulBody => [
+                "{} generated and saved in {}",
+                "{} erzeugt und in {} gespeichert",
+                "{} généré et enregistré dans {}",
+                "{} создан и сохранён в {}",
+                "{} wygenerowano i zapisano w {}",
+            ],
+            Self::PluginsAnd => ["{} and {}", "{} und {}", "{} et {}", "{} и {}", "{} i {}"],
+            Self::SplitPlugins => [
+                "{} addons",
+                "{} Addons",
+                "{} addons",
+                "{} аддонов",
+                "{} dodatków",
+            ],
+            Self::AllPluginsFailed => [
+                "No plugins could be loaded!",
+                "Keine Plugins konnten geladen werden!",
+                "Aucun plugin n'a pu être chargé !",
+                "Не удалось загрузить ни одного плагина!",
+                "Nie udało się wczytać żadnego pluginu!",
+            ],
+            Self::AllPluginsFailedBody => [
+                "All {} plugins with lights to fix failed to load. See the warnings above for details.",
+                "Alle {} Plugins mit zu korrigierenden Lichtern konnten nicht geladen werden. Details stehen in den Warnungen oben.",
+                "Les {} plugins contenant des lumières à corriger n'ont pas pu être chargés. Consultez les avertissements ci-dessus pour plus de détails.",
+                "Не удалось загрузить ни один из {} плагинов со светом для исправления. Подробности смотрите в предупреждениях выше.",
+                "Nie udało się wczytać żadnego z {} pluginów ze światłami do poprawienia. Szczegóły znajdziesz w ostrzeżeniach powyżej.",
+            ],
+            Self::PluginsFailed => [
+                "Plugins failed to load!",
+                "Plugins konnten nicht geladen werden!",
+                "Des plugins n'ont pas pu être chargés !",
+                "Не удалось загрузить плагины!",
+                "Nie udało się wczytać pluginów!",
+            ],
+            Self::PluginsFailedBody => [
+                "{} plugins could not be loaded, so nothing was written. See the warnings above for details, or set on_plugin_error to skip to fix the rest of the load order anyway.",
+                "{} Plugins konnten nicht geladen werden, daher wurde nichts geschrieben. Details stehen in den Warnungen oben. Setze on_plugin_error auf skip, um den Rest der Ladereihenfolge trotzdem zu korrigieren.",
+                "{} plugins n'ont pas pu être chargés, rien n'a donc été écrit. Consultez les avertissements ci-dessus pour plus de détails, ou réglez on_plugin_error sur skip pour corriger quand même le reste de l'ordre de chargement.",
+                "Не удалось загрузить плагинов: {}, поэтому ничего не было записано. Подробности смотрите в предупреждениях выше, или установите on_plugin_error в skip, чтобы всё равно исправить остальной порядок загрузки.",
+                "Nie udało się wczytać {} pluginów, więc nic nie zostało zapisane. Szczegóły znajdziesz w ostrzeżeniach powyżej. Ustaw on_plugin_error na skip, aby mimo to poprawić resztę kolejności wczytywania.",
+            ],
+            Self::UnchangedBody => [
+                "No changes: {} in {} is already up to date, and was left untouched",
+                "Keine Änderungen: {} in {} ist bereits aktuell und wurde nicht verändert",
+                "Aucun changement : {} dans {} est déjà à jour et n'a pas été modifié",
+                "Изменений нет: {} в {} уже актуален и не был перезаписан",
+                "Brak zmian: {} w {} jest już aktualny i nie został zmieniony",
+            ],
+            Self::SummaryBody => [
+                "Plugins scanned: {}, skipped: {}\nLights adjusted: {} ({} standard, {} colored, {} negative, {} disabled)\nScripted lights left out: {}\nCells modified: {}\nOutput size: {}",
+                "Durchsuchte Plugins: {}, übersprungen: {}\nAngepasste Lichter: {} ({} normal, {} farbig, {} negativ, {} deaktiviert)\nAusgelassene Lichter mit Skript: {}\nGeänderte Zellen: {}\nAusgabegröße: {}",
+                "Plugins analysés : {}, ignorés : {}\nLumières ajustées : {} ({} standard, {} colorées, {} négatives, {} désactivées)\nLumières scriptées exclues : {}\nCellules modifiées : {}\nTaille du fichier : {}",
+                "Просканировано плагинов: {}, пропущено: {}\nИзменено источников света: {} ({} обычных, {} цветных, {} отрицательных, {} отключённых)\nПропущено источников света со скриптами: {}\nИзменено ячеек: {}\nРазмер файла: {}",
+                "Przeskanowane pluginy: {}, pominięte: {}\nDostosowane światła: {} ({} standardowe, {} kolorowe, {} negatywne, {} wyłączone)\nPominięte światła ze skryptami: {}\nZmienione komórki: {}\nRozmiar pliku: {}",
+            ],
+            Self::ValidationFailed => [
+                "Generated plugin is invalid!",
+                "Erzeugtes Plugin ist ungültig!",
+                "Le plugin généré est invalide !",
+                "Созданный плагин повреждён!",
+                "Wygenerowany plugin jest nieprawidłowy!",
+            ],
+            Self::ValidationFailedBody => [
+                "The generated plugin failed validation and was not saved:\n{}",
+                "Das erzeugte Plugin hat die Prüfung nicht bestanden und wurde nicht gespeichert:\n{}",
+                "Le plugin généré n'a pas passé la validation et n'a pas été enregistré :\n{}",
+                "Созданный плагин не прошёл проверку и не был сохранён:\n{}",
+                "Wygenerowany plugin nie przeszedł weryfikacji i nie został zapisany:\n{}",
+            ],
+        }
+    }
+
+    /// This message in the language set by set_language
+    pub fn text(self) -> &'static str {
+        self.translations()[language() as usize]
+    }
+
+    /// This message with each `{}` replaced by the next argument
+    pub fn fill(self, args: &[&dyn Display]) -> String {
+        self.fill_in(language(), args)
+    }
+
+    /// Same as fill, in `language` rather than the one set by set_language
+    fn fill_in(self, language: Language, args: &[&dyn Display]) -> String {
+        let mut args = args.iter();
+        let mut pieces = self.translations()[language as usize].split("{}");
+        let mut filled = pieces.next().unwrap_or_default().to_string();
+
+        for piece in pieces {
+            if let Some(arg) = args.next() {
+                let _ = write!(filled, "{arg}");
+            }
+
+            filled.push_str(piece);
+        }
+
+        filled
+    }
+}
+
+static LANGUAGE: RwLock<Language> = RwLock::new(Language::English);
+
+/// Sets which language Message::text and Message::fill use
+pub fn set_language(new_language: Language) {
+    if let Ok(mut current) = LANGUAGE.write() {
+        *current = new_language;
+    }
+}
+
+pub fn language() -> Language {
+    LANGUAGE
+        .read()
+        .map(|language| *language)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_replaces_placeholders_in_order() {
+        assert_eq!(
+            Message::PluginsAnd.fill_in(Language::English, &[&"A.omwaddon", &"B.esp"]),
+            "A.omwaddon and B.esp"
+        );
+        assert_eq!(
+            Message::PluginsAnd.fill_in(Language::German, &[&"A.omwaddon", &"B.esp"]),
+            "A.omwaddon und B.esp"
+        );
+    }
+
+    #[test]
+    fn fill_leaves_missing_arguments_empty_and_ignores_extras() {
+        assert_eq!(
+            Message::PluginsAnd.fill_in(Language::English, &[&1]),
+            "1 and "
+        );
+        assert_eq!(
+            Message::SplitPlugins.fill_in(Language::English, &[&3, &"unused"]),
+            "3 addons"
+        );
+    }
+
+    #[test]
+    fn fill_uses_the_language_set_by_default() {
+        assert_eq!(language(), Language::English);
+        assert_eq!(Message::SplitPlugins.fill(&[&2]), "2 addons");
+    }
+
+    #[test]
+    fn locales_are_read_by_their_language_code() {
+        assert_eq!(Language::from_locale("de_DE.UTF-8"), Some(Language::German));
+        assert_eq!(Language::from_locale("FR"), Some(Language::French));
+        assert_eq!(Language::from_locale("pt_BR.UTF-8"), None);
+        assert_eq!(Language::from_locale("C"), None);
+    }
+
+    #[test]
+    fn unsupported_locales_fall_back_to_later_variables_then_english() {
+        let environment = |variables: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                variables
+                    .iter()
+                    .find(|(variable, _)| *variable == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert_eq!(
+            Language::from_environment(environment(&[("LC_ALL", "ru_RU"), ("LANG", "pl_PL")])),
+            Language::Russian
+        );
+        assert_eq!(
+            Language::from_environment(environment(&[("LC_ALL", "C"), ("LANG", "pl_PL.UTF-8")])),
+            Language::Polish
+        );
+        assert_eq!(
+            Language::from_environment(environment(&[("LANG", "ja_JP.UTF-8")])),
+            Language::English
+        );
+        assert_eq!(
+            Language::from_environment(environment(&[])),
+            Language::English
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    env::current_dir,
+    fs::read_to_string,
+    io,
+    path::{Path, PathBuf},
+};
+
+pub const MO2_INI_NAME: &str = "ModOrganizer.ini";
+
+/// Reads a single value out of ModOrganizer.ini, ignoring sections
+/// Qt wraps some strings as `@ByteArray(...)`, which is unwrapped here
+fn ini_value(ini: &str, key: &str) -> Option<String> {
+    ini.lines().find_map(|line| {
+        let (line_key, value) = line.split_once('=')?;
+
+        if !line_key.trim().eq_ignore_ascii_case(key) {
+            return None;
+        }
+
+        let value = value.trim();
+        let value = value
+            .strip_prefix("@ByteArray(")
+            .and_then(|value| value.strip_suffix(')'))
+            .unwrap_or(value);
+
+        Some(value.replace("\\\\", "/").replace('\\', "/"))
+    })
+}
+
+/// Looks for a Mod Organizer 2 instance in `start` or any directory above it,
+/// and returns the directory of its selected profile
+pub fn find_profile(start: &Path) -> Option<PathBuf> {
+    start.ancestors().find_map(|dir| {
+        let ini = read_to_string(dir.join(MO2_INI_NAME)).ok()?;
+        let profile = dir
+            .join("profiles")
+            .join(ini_value(&ini, "selected_profile")?);
+
+        profile.join("modlist.txt").is_file().then_some(profile)
+    })
+}
+
+/// Finds the profile of an MO2 instance lightfixes is being run from, if any
+/// Checks the working directory and then the executable's directory
+pub fn detect_profile() -> Option<PathBuf> {
+    [current_dir().ok(), crate::portable_dir().ok()]
+        .into_iter()
+        .flatten()
+        .find_map(|dir| find_profile(&dir))
+}
+
+/// Resolves one of the directory settings in ModOrganizer.ini, which may be relative to the base directory
+fn instance_dir(ini: &str, key: &str, base_dir: &Path, default: &str) -> PathBuf {
+    match ini_value(ini, key) {
+        Some(value) => {
+            let value = value.replace("%BASE_DIR%", &base_dir.to_string_lossy());
+            base_dir.join(value)
+        }
+        None => base_dir.join(default),
+    }
+}
+
+/// Data directories for every enabled mod in an MO2 profile,
+/// in the order OpenMW should load them, with the overwrite directory last
+pub fn mod_directories(profile: &Path) -> io::Result<Vec<PathBuf>> {
+    let modlist = read_to_string(profile.join("modlist.txt"))?;
+
+    // Profiles live in <instance>/profiles/<name>
+    let instance = profile
+        .parent()
+        .and_then(Path::parent)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not inside an MO2 instance", profile.display()),
+            )
+        })?
+        .to_path_buf();
+
+    let ini = read_to_string(instance.join(MO2_INI_NAME)).unwrap_or_default();
+    let base_dir = match ini_value(&ini, "base_directory") {
+        Some(base_dir) => instance.join(base_dir),
+        None => instance,
+    };
+
+    let mods_dir = instance_dir(&ini, "mod_directory", &base_dir, "mods");
+    let overwrite_dir = instance_dir(&ini, "overwrite_directory", &base_dir, "overwrite");
+
+    // modlist.txt is written highest priority first
+    let mut directories: Vec<PathBuf> = modlist
+        .lines()
+        .rev()
+        .filter_map(|line| line.trim().strip_prefix('+'))
+        .filter(|mod_name| !mod_name.ends_with("_separator"))
+        .map(|mod_name| mods_dir.join(mod_name))
+        .filter(|dir| dir.is_dir())
+        .collect();
+
+    if overwrite_dir.is_dir() {
+        directories.push(overwrite_dir);
+    }
+
+    Ok(directories)
+}
@@ -0,0 +1,141 @@
+use std::sync::{
+    RwLock,
+    atomic::{AtomicBool, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Something which can show the user a message
+pub trait Notifier {
+    fn notify(&self, title: &str, message: &str);
+}
+
+/// Blocking native message dialogs
+/// Android has no dialog support, so messages are printed instead
+pub struct DialogNotifier;
+
+impl Notifier for DialogNotifier {
+    fn notify(&self, title: &str, message: &str) {
+        #[cfg(target_os = "android")]
+        {
+            let _ = title;
+            println!("{}", message);
+        }
+
+        #[cfg(not(target_os = "android"))]
+        let _ = native_dialog::DialogBuilder::message()
+            .set_title(title)
+            .set_text(message)
+            .alert()
+            .show();
+    }
+}
+
+/// Plain text on stdout, for terminals and scripts
+pub struct StdoutNotifier;
+
+impl Notifier for StdoutNotifier {
+    fn notify(&self, _title: &str, message: &str) {
+        if stdout_reserved() {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+}
+
+/// Freedesktop notifications, which don't steal focus
+/// Falls back to dialogs on other platforms or when no notification daemon is running
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn notify(&self, title: &str, message: &str) {
+        #[cfg(target_os = "linux")]
+        if notify_rust::Notification::new()
+            .appname("S3LightFixes")
+            .summary(title)
+            .body(message)
+            .show()
+            .is_ok()
+        {
+            return;
+        }
+
+        DialogNotifier.notify(title, message);
+    }
+}
+
+/// Shows nothing at all, leaving the exit code to report the outcome
+pub struct SilentNotifier;
+
+impl Notifier for SilentNotifier {
+    fn notify(&self, _title: &str, _message: &str) {}
+}
+
+/// Selects which Notifier messages are sent to
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyBackend {
+    /// Native message dialogs
+    #[default]
+    Dialog,
+    /// Print messages to stdout
+    Stdout,
+    /// Desktop notifications (Linux only, falls back to dialogs elsewhere)
+    Desktop,
+    /// Don't show messages at all
+    Silent,
+}
+
+impl NotifyBackend {
+    /// Picks a backend from an explicit choice, falling back to the older boolean switches
+    pub fn resolve(
+        requested: Option<NotifyBackend>,
+        desktop_notifications: bool,
+        no_notifications: bool,
+    ) -> NotifyBackend {
+        match requested {
+            Some(backend) => backend,
+            None if no_notifications => NotifyBackend::Stdout,
+            None if desktop_notifications => NotifyBackend::Desktop,
+            None => NotifyBackend::Dialog,
+        }
+    }
+
+    pub fn notifier(&self) -> Box<dyn Notifier> {
+        match self {
+            Self::Dialog => Box::new(DialogNotifier),
+            Self::Stdout => Box::new(StdoutNotifier),
+            Self::Desktop => Box::new(DesktopNotifier),
+            Self::Silent => Box::new(SilentNotifier),
+        }
+    }
+}
+
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Keeps messages off stdout, as the generated plugin is being written there
+/// Anything which would have been printed goes to stderr instead
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+pub fn stdout_reserved() -> bool {
+    STDOUT_RESERVED.load(Ordering::Relaxed)
+}
+
+static NOTIFY_BACKEND: RwLock<NotifyBackend> = RwLock::new(NotifyBackend::Dialog);
+
+/// Sets which backend notification_box sends messages to
+pub fn set_notify_backend(backend: NotifyBackend) {
+    if let Ok(mut current) = NOTIFY_BACKEND.write() {
+        *current = backend;
+    }
+}
+
+pub fn notify_backend() -> NotifyBackend {
+    NOTIFY_BACKEND
+        .read()
+        .map(|backend| *backend)
+        .unwrap_or_default()
+}
@@ -0,0 +1,108 @@
+use std::{fs::read_to_string, path::Path};
+
+/// OpenMW's own settings, kept next to the user openmw.cfg
+pub const SETTINGS_NAME: &str = "settings.cfg";
+
+/// Radius multipliers above this make lights pop in and out under legacy lighting
+pub const LEGACY_MAX_RADIUS: f32 = 1.0;
+
+/// Lights legacy lighting can show on one object at once, whatever `max lights` says
+pub const LEGACY_MAX_LIGHTS: u32 = 8;
+
+/// The `lighting method` setting, which decides how many lights can touch an object and how they fall off
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LightingMethod {
+    /// Fixed-function lighting, limited to eight lights per object
+    Legacy,
+    /// Shader lighting, which falls off like legacy lighting does
+    #[default]
+    ShadersCompatibility,
+    /// Shader lighting, with a falloff of its own
+    Shaders,
+}
+
+impl LightingMethod {
+    fn from_setting(value: &str) -> Option<LightingMethod> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "legacy" => Some(LightingMethod::Legacy),
+            "shaders compatibility" => Some(LightingMethod::ShadersCompatibility),
+            "shaders" => Some(LightingMethod::Shaders),
+            _ => None,
+        }
+    }
+}
+
+/// The lighting settings from settings.cfg which decide how generated lights are shown
+/// Anything left out keeps OpenMW's default
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightingSettings {
+    pub lighting_method: LightingMethod,
+    pub max_lights: u32,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        LightingSettings {
+            lighting_method: LightingMethod::default(),
+            max_lights: 8,
+        }
+    }
+}
+
+impl LightingSettings {
+    /// Reads the [Shaders] section of a settings.cfg, or returns None if it can't be read
+    pub fn read(path: &Path) -> Option<LightingSettings> {
+        Some(Self::parse(&read_to_string(path).ok()?))
+    }
+
+    pub fn parse(contents: &str) -> LightingSettings {
+        let mut settings = LightingSettings::default();
+        let mut in_shaders = false;
+
+        for line in contents.lines().map(str::trim) {
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+
+            if let Some(section) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                in_shaders = section.trim().eq_ignore_ascii_case("shaders");
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            if !in_shaders {
+                continue;
+            }
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "lighting method" => {
+                    if let Some(method) = LightingMethod::from_setting(value) {
+                        settings.lighting_method = method;
+                    }
+                }
+                "max lights" => {
+                    if let Ok(max_lights) = value.trim().parse() {
+                        settings.max_lights = max_lights;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        settings
+    }
+
+    /// How many lights can touch one object at once
+    pub fn lights_per_object(&self) -> u32 {
+        match self.lighting_method {
+            LightingMethod::Legacy => LEGACY_MAX_LIGHTS,
+            _ => self.max_lights,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use tes3::esp::{Cell, EditorId, Light, Plugin};
+
+/// Name of the untouched copy of the last generated plugin, kept next to lightconfig.toml
+/// Comparing against it is how hand-edited records in the real output are found
+pub const CACHE_NAME: &str = "S3LightFixes.omwaddon.cache";
+
+/// Copies every record of one type which was hand-edited in `previous_output` into `generated`
+/// Records are only carried over when they are still part of the new generation
+macro_rules! carry_over_edits {
+    ($record_type:ty, $generated:expr, $previous_output:expr, $previous_cache:expr) => {{
+        let cached: HashMap<String, &$record_type> = $previous_cache
+            .objects_of_type::<$record_type>()
+            .map(|record| (record.editor_id_ascii_lowercase().into_owned(), record))
+            .collect();
+
+        let edited: HashMap<String, &$record_type> = $previous_output
+            .objects_of_type::<$record_type>()
+            .map(|record| (record.editor_id_ascii_lowercase().into_owned(), record))
+            .filter(|(id, record)| cached.get(id).is_some_and(|original| original != record))
+            .collect();
+
+        let mut carried_over = 0;
+        for record in $generated.objects_of_type_mut::<$record_type>() {
+            let id = record.editor_id_ascii_lowercase().into_owned();
+
+            if let Some(edited_record) = edited.get(&id) {
+                *record = (*edited_record).clone();
+                carried_over += 1;
+            }
+        }
+
+        carried_over
+    }};
+}
+
+/// Carries hand edits made to a previously generated plugin over into a new one
+/// A record counts as hand-edited when it differs from the copy cached when it was generated
+/// Returns how many records were kept
+pub fn preserve_manual_edits(
+    generated: &mut Plugin,
+    previous_output: &Plugin,
+    previous_cache: &Plugin,
+) -> usize {
+    carry_over_edits!(Light, generated, previous_output, previous_cache)
+        + carry_over_edits!(Cell, generated, previous_output, previous_cache)
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{LightConfig, default};
+
+pub const BUILTIN_PRESETS: [&str; 4] = [
+    "momw",
+    "vanilla-plus",
+    "vtastek-classic",
+    "zesterer-shaders",
+];
+
+/// A named set of values to populate LightConfig with
+/// Any field left unset keeps whatever lightconfig.toml (or the default) says
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Preset {
+    pub standard_hue: Option<f32>,
+    pub standard_saturation: Option<f32>,
+    pub standard_value: Option<f32>,
+    pub standard_radius: Option<f32>,
+    pub colored_hue: Option<f32>,
+    pub colored_saturation: Option<f32>,
+    pub colored_value: Option<f32>,
+    pub colored_radius: Option<f32>,
+    pub duration_mult: Option<f32>,
+    pub disable_flickering: Option<bool>,
+    pub disable_pulse: Option<bool>,
+    pub disable_interior_sun: Option<bool>,
+}
+
+impl Preset {
+    /// Returns one of the presets shipped with lightfixes, by name
+    pub fn builtin(name: &str) -> Option<Preset> {
+        match name.to_ascii_lowercase().as_str() {
+            // Same as the defaults, for going back to them after using another preset
+            "momw" => Some(Preset {
+                standard_hue: Some(default::standard_hue()),
+                standard_saturation: Some(default::standard_saturation()),
+                standard_value: Some(default::standard_value()),
+                standard_radius: Some(default::standard_radius()),
+                colored_hue: Some(default::colored_hue()),
+                colored_saturation: Some(default::colored_saturation()),
+                colored_value: Some(default::colored_value()),
+                colored_radius: Some(default::colored_radius()),
+                duration_mult: Some(default::duration_mult()),
+                disable_flickering: Some(default::disable_flicker()),
+                disable_pulse: Some(default::disable_pulse()),
+                disable_interior_sun: Some(LightConfig::default().disable_interior_sun),
+            }),
+            // Keeps the original look of lights, only taking the edge off oversaturated ones
+            "vanilla-plus" => Some(Preset {
+                standard_hue: Some(0.9),
+                standard_saturation: Some(0.9),
+                standard_value: Some(0.8),
+                standard_radius: Some(1.1),
+                colored_hue: Some(1.0),
+                colored_saturation: Some(0.95),
+                colored_value: Some(0.85),
+                colored_radius: Some(1.0),
+                duration_mult: Some(1.5),
+                disable_flickering: Some(false),
+                disable_pulse: Some(false),
+                disable_interior_sun: Some(false),
+            }),
+            // The original lightfixes.pl values, for vtastek's 0.47 shaders
+            "vtastek-classic" => Some(Preset {
+                standard_hue: Some(0.62),
+                standard_saturation: Some(0.8),
+                standard_value: Some(0.57),
+                standard_radius: Some(2.0),
+                colored_hue: Some(1.0),
+                colored_saturation: Some(0.9),
+                colored_value: Some(0.7),
+                colored_radius: Some(1.1),
+                duration_mult: Some(2.5),
+                disable_flickering: Some(true),
+                disable_pulse: Some(false),
+                disable_interior_sun: Some(true),
+            }),
+            // Volumetric lighting already spreads light further, so keep radii down and lights dimmer
+            "zesterer-shaders" => Some(Preset {
+                standard_hue: Some(0.62),
+                standard_saturation: Some(0.75),
+                standard_value: Some(0.5),
+                standard_radius: Some(1.0),
+                colored_hue: Some(1.0),
+                colored_saturation: Some(0.85),
+                colored_value: Some(0.6),
+                colored_radius: Some(1.0),
+                duration_mult: Some(2.5),
+                disable_flickering: Some(true),
+                disable_pulse: Some(false),
+                disable_interior_sun: Some(false),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Copies every value set by this preset into the config
+    pub fn apply(&self, config: &mut LightConfig) {
+        let float_fields = [
+            (&mut config.standard_hue, self.standard_hue),
+            (&mut config.standard_saturation, self.standard_saturation),
+            (&mut config.standard_value, self.standard_value),
+            (&mut config.standard_radius, self.standard_radius),
+            (&mut config.colored_hue, self.colored_hue),
+            (&mut config.colored_saturation, self.colored_saturation),
+            (&mut config.colored_value, self.colored_value),
+            (&mut config.colored_radius, self.colored_radius),
+            (&mut config.duration_mult, self.duration_mult),
+        ];
+
+        for (field, value) in float_fields {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+
+        let bool_fields = [
+            (&mut config.disable_flickering, self.disable_flickering),
+            (&mut config.disable_pulse, self.disable_pulse),
+            (&mut config.disable_interior_sun, self.disable_interior_sun),
+        ];
+
+        for (field, value) in bool_fields {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::{
+    ffi::OsString,
+    fs::read_to_string,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Reads a profile list: one openmw.cfg, or directory holding one, per line
+/// Blank lines and lines starting with `#` are skipped, and relative paths are taken from the list's own directory
+pub fn read_profiles(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let list_dir = path.parent().unwrap_or(Path::new("."));
+
+    Ok(read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| list_dir.join(line))
+        .collect())
+}
+
+/// The command line without any profile selection, so it can be run again once per profile
+/// Handles `-c path`, `-cpath`, `--openmw-cfg path`, `--openmw-cfg=path`, and the same forms of `--profiles`
+pub fn strip_profile_args(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut stripped = Vec::new();
+    let mut skip_value = false;
+
+    for arg in args {
+        if skip_value {
+            skip_value = false;
+            continue;
+        }
+
+        let text = arg.to_string_lossy();
+
+        if text == "-c" || text == "--openmw-cfg" || text == "--profiles" {
+            skip_value = true;
+        } else if !(text.starts_with("--openmw-cfg=")
+            || text.starts_with("--profiles=")
+            || (text.starts_with("-c") && !text.starts_with("--")))
+        {
+            stripped.push(arg);
+        }
+    }
+
+    stripped
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tes3::esp::{Cell, CellFlags, LightData, Plugin};
+
+use crate::{Encoding, color};
+
+/// Extra multipliers for lights placed in one region, or of one class, applied on top of every other setting
+/// Any value left out leaves that part of the light alone
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ExtraMultipliers {
+    pub hue: Option<f32>,
+    pub saturation: Option<f32>,
+    pub value: Option<f32>,
+    pub radius: Option<f32>,
+}
+
+impl ExtraMultipliers {
+    pub fn apply(&self, light_data: &mut LightData) {
+        light_data.color = color::scale_hsv(
+            light_data.color,
+            self.hue.unwrap_or(1.0),
+            self.saturation.unwrap_or(1.0),
+            self.value.unwrap_or(1.0),
+        );
+
+        if let Some(radius) = self.radius {
+            light_data.radius = (light_data.radius as f32 * radius) as u32;
+        }
+    }
+}
+
+/// Whether an object is placed anywhere outdoors, indoors, or both
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Placement {
+    /// Placed in an exterior cell, or an interior which behaves like one
+    pub exterior: bool,
+    pub interior: bool,
+}
+
+/// Where each object is placed, keyed by lowercased ID
+#[derive(Debug, Default)]
+pub struct LightPlacements {
+    placements: HashMap<String, Placement>,
+}
+
+impl LightPlacements {
+    /// Notes whether every object referenced by the plugin's cells is placed outdoors or indoors
+    /// Interiors which behave like exteriors are lit like them, so they count as exterior
+    pub fn record_references(&mut self, plugin: &Plugin, encoding: Encoding) {
+        for cell in plugin.objects_of_type::<Cell>() {
+            let exterior = !cell.data.flags.contains(CellFlags::IS_INTERIOR)
+                || cell.data.flags.contains(CellFlags::BEHAVES_LIKE_EXTERIOR);
+
+            for reference in cell.references.values() {
+                let placement = self
+                    .placements
+                    .entry(encoding.record_id(&reference.id))
+                    .or_default();
+
+                if exterior {
+                    placement.exterior = true;
+                } else {
+                    placement.interior = true;
+                }
+            }
+        }
+    }
+
+    /// Adds the placements from another plugin or set of plugins
+    pub fn extend(&mut self, other: LightPlacements) {
+        for (id, other_placement) in other.placements {
+            let placement = self.placements.entry(id).or_default();
+            placement.exterior |= other_placement.exterior;
+            placement.interior |= other_placement.interior;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.placements.is_empty()
+    }
+
+    /// Where an object is placed, by lowercased ID, or the default of nowhere for objects which are only carried or spawned
+    pub fn placement_of(&self, record_id: &str) -> Placement {
+        self.placements.get(record_id).copied().unwrap_or_default()
+    }
+}
+
+/// How often each object is placed in the exterior cells of each region, keyed by lowercased ID and region name
+/// Interiors don't belong to a region, so only exterior references are counted
+#[derive(Debug, Default)]
+pub struct LightRegions {
+    references: HashMap<String, HashMap<String, usize>>,
+}
+
+impl LightRegions {
+    /// Counts the references of every exterior cell in the plugin which has a region
+    pub fn record_references(&mut self, plugin: &Plugin, encoding: Encoding) {
+        for cell in plugin.objects_of_type::<Cell>() {
+            if cell.data.flags.contains(CellFlags::IS_INTERIOR) {
+                continue;
+            }
+
+            let Some(region) = &cell.region else {
+                continue;
+            };
+
+            let region = encoding.record_id(region);
+
+            for reference in cell.references.values() {
+                *self
+                    .references
+                    .entry(encoding.record_id(&reference.id))
+                    .or_default()
+                    .entry(region.clone())
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    /// Adds the counts from another plugin or set of plugins
+    pub fn extend(&mut self, other: LightRegions) {
+        for (id, regions) in other.references {
+            let counts = self.references.entry(id).or_default();
+
+            for (region, count) in regions {
+                *counts.entry(region).or_default() += count;
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.references.is_empty()
+    }
+
+    /// The lowercased region an object is placed in most often, ties going to the name sorting first
+    /// A light is one record no matter where it's placed, so it can only be adjusted for one region
+    pub fn region_of(&self, record_id: &str) -> Option<&str> {
+        self.references
+            .get(record_id)?
+            .iter()
+            .max_by(|(a_region, a_count), (b_region, b_count)| {
+                a_count.cmp(b_count).then_with(|| b_region.cmp(a_region))
+            })
+            .map(|(region, _)| region.as_str())
+    }
+}
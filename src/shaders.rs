@@ -0,0 +1,105 @@
+use std::{
+    collections::BTreeSet,
+    fs::read_dir,
+    path::{Path, PathBuf},
+};
+
+use crate::LightConfig;
+
+/// Shader setups whose lighting the presets are tuned for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShaderPack {
+    /// vtastek's shaders, which the original lightfixes.pl values and --classic were made for
+    Vtastek,
+    /// zesterer's volumetric shaders
+    Zesterer,
+    /// Any other OpenMW post-processing shaders
+    PostProcessing,
+}
+
+impl ShaderPack {
+    /// Which pack a file under a data directory's shaders folder belongs to, from its lowercased path
+    fn from_shader_path(path: &str) -> Option<ShaderPack> {
+        if path.contains("vtastek") {
+            Some(ShaderPack::Vtastek)
+        } else if path.contains("zesterer") {
+            Some(ShaderPack::Zesterer)
+        } else if path.ends_with(".omwfx") {
+            Some(ShaderPack::PostProcessing)
+        } else {
+            None
+        }
+    }
+}
+
+/// Lowercased paths of every file in `dir` and the directories below it
+fn shader_files(dir: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = read_dir(dir) else {
+        return;
+    };
+
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+        if path.is_dir() {
+            shader_files(&path, files);
+        } else {
+            files.push(path.to_string_lossy().to_ascii_lowercase());
+        }
+    }
+}
+
+/// Finds the shader packs installed into the `shaders` folder of any data directory
+/// Shaders which replace OpenMW's own, as vtastek's 0.47 release did, live in its resources folder instead and aren't found
+pub fn detect_shader_packs(directories: &[PathBuf]) -> BTreeSet<ShaderPack> {
+    let mut files = Vec::new();
+
+    for directory in directories {
+        let Ok(entries) = read_dir(directory) else {
+            continue;
+        };
+
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            if path.is_dir()
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.eq_ignore_ascii_case("shaders"))
+            {
+                shader_files(&path, &mut files);
+            }
+        }
+    }
+
+    files
+        .iter()
+        .filter_map(|path| ShaderPack::from_shader_path(path))
+        .collect()
+}
+
+/// Warnings about settings which don't suit the shaders found, or shaders which settings were never chosen for
+pub fn shader_advice(packs: &BTreeSet<ShaderPack>, light_config: &LightConfig) -> Vec<String> {
+    let preset_is = |name: &str| {
+        light_config
+            .preset
+            .as_deref()
+            .is_some_and(|preset| preset.eq_ignore_ascii_case(name))
+    };
+
+    let mut advice = Vec::new();
+
+    if packs.contains(&ShaderPack::Vtastek) {
+        if !light_config.disable_interior_sun {
+            advice.push("vtastek's shaders are installed, but lights are set up for other shaders. Use --classic or --preset vtastek-classic to match them.".to_string());
+        }
+    } else if preset_is("vtastek-classic") {
+        advice.push("The vtastek-classic preset is meant for vtastek's shaders, which weren't found in any data directory. If they're installed into OpenMW's resources folder this can be ignored, otherwise lights will look too large.".to_string());
+    }
+
+    if packs.contains(&ShaderPack::Zesterer) {
+        if !preset_is("zesterer-shaders") {
+            advice.push("zesterer's shaders are installed, which spread light further by themselves. Use --preset zesterer-shaders to keep lights from washing out.".to_string());
+        }
+    } else if preset_is("zesterer-shaders") {
+        advice.push("The zesterer-shaders preset is meant for zesterer's shaders, which weren't found in any data directory, so lights will look dim and small.".to_string());
+    }
+
+    advice
+}
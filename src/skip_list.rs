@@ -0,0 +1,85 @@
+use std::{
+    collections::BTreeMap,
+    fs::{metadata, read_to_string, write},
+    io,
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::to_io_error;
+
+pub const SKIP_LIST_NAME: &str = "lightconfig_skipped.toml";
+
+/// A plugin which failed to load, and the state of the file when it did
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SkippedPlugin {
+    pub error: String,
+    /// Modification time of the plugin, in seconds since the unix epoch
+    pub modified: u64,
+}
+
+/// Plugins which failed to load on a previous run, keyed by their full path
+/// They're skipped on later runs until the file changes, so the same warnings aren't repeated every time
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SkipList {
+    #[serde(default)]
+    pub plugins: BTreeMap<String, SkippedPlugin>,
+
+    #[serde(skip)]
+    changed: bool,
+}
+
+fn modified_secs(path: &Path) -> Option<u64> {
+    metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+impl SkipList {
+    /// A missing or unreadable skip list is treated as empty
+    pub fn load(path: &Path) -> SkipList {
+        read_to_string(path)
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the list out, if anything was recorded or forgotten since it was loaded
+    pub fn save_if_changed(&self, path: &Path) -> io::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        write(path, toml::to_string_pretty(self).map_err(to_io_error)?)
+    }
+
+    /// Whether the plugin failed to load before and hasn't been modified since
+    pub fn should_skip(&self, plugin_path: &Path) -> bool {
+        self.plugins
+            .get(plugin_path.to_string_lossy().as_ref())
+            .is_some_and(|skipped| Some(skipped.modified) == modified_secs(plugin_path))
+    }
+
+    pub fn record(&mut self, plugin_path: &Path, error: String) {
+        self.changed = true;
+        self.plugins.insert(
+            plugin_path.to_string_lossy().to_string(),
+            SkippedPlugin {
+                error,
+                modified: modified_secs(plugin_path).unwrap_or_default(),
+            },
+        );
+    }
+
+    pub fn forget(&mut self, plugin_path: &Path) {
+        self.changed |= self
+            .plugins
+            .remove(plugin_path.to_string_lossy().as_ref())
+            .is_some();
+    }
+}
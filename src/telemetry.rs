@@ -0,0 +1,76 @@
+use std::{
+    mem::size_of,
+    time::{Duration, Instant},
+};
+
+use tes3::esp::{Cell, Light, Plugin, TES3Object};
+
+/// Records read from one plugin, shown under --debug
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RecordCounts {
+    pub lights: usize,
+    pub cells: usize,
+    /// Records still held once everything lightfixes doesn't need was dropped
+    pub kept: usize,
+    /// Rough size of the kept records, not counting strings or references
+    pub estimated_bytes: usize,
+}
+
+impl RecordCounts {
+    /// Counts the lights and cells of a plugin as it was read, before anything is dropped
+    pub fn read_from(plugin: &Plugin) -> RecordCounts {
+        RecordCounts {
+            lights: plugin.objects_of_type::<Light>().count(),
+            cells: plugin.objects_of_type::<Cell>().count(),
+            ..Default::default()
+        }
+    }
+
+    /// Fills in what's left of the plugin after trimming
+    pub fn set_kept(&mut self, plugin: &Plugin) {
+        self.kept = plugin.objects.len();
+        self.estimated_bytes = self.kept * size_of::<TES3Object>();
+    }
+
+    pub fn total(&self) -> usize {
+        self.lights + self.cells
+    }
+}
+
+/// Time spent in each stage of a run, in the order they happened
+#[derive(Debug)]
+pub struct StageTimings {
+    stages: Vec<(&'static str, Duration)>,
+    stage_start: Instant,
+}
+
+impl Default for StageTimings {
+    fn default() -> Self {
+        Self {
+            stages: Vec::new(),
+            stage_start: Instant::now(),
+        }
+    }
+}
+
+impl StageTimings {
+    /// Starts timing the first stage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ends the current stage under `name`, and starts the next one
+    pub fn finish_stage(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.stages.push((name, now - self.stage_start));
+        self.stage_start = now;
+    }
+
+    pub fn stages(&self) -> &[(&'static str, Duration)] {
+        &self.stages
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|(_, duration)| *duration).sum()
+    }
+}
@@ -0,0 +1,419 @@
+use palette::{FromColor, GetHue, Hsv, SetHue, rgb::Srgb};
+use rayon::prelude::*;
+use tes3::esp::{Light, LightData, LightFlags};
+
+use crate::{
+    CustomLightData, ExtraMultipliers, LightClass, LightConfig, Placement, RadiusMode,
+    TransformMode, color, extract::LightSet, light_override::LightFlag,
+};
+
+/// Given a LightData reference from an ESP light,
+/// returns the HSV version and whether it is colored or not (for the global modifier)
+pub fn light_to_hsv(light_data: &tes3::esp::LightData) -> (Hsv, bool) {
+    let rgb: palette::rgb::Rgb = Srgb::new(
+        light_data.color[0],
+        light_data.color[1],
+        light_data.color[2],
+    )
+    .into_format();
+
+    let hsv: Hsv = Hsv::from_color(rgb);
+    let hue_degrees = hsv.get_hue().into_positive_degrees();
+
+    (hsv, hue_degrees > 64. || hue_degrees < 14.)
+}
+
+/// Radius, hue, saturation, value, and hue shift multipliers for a light no override replaces
+fn global_multipliers(
+    light_config: &LightConfig,
+    light_as_hsv: Hsv,
+    is_colored: bool,
+) -> (f32, f32, f32, f32, f32) {
+    match is_colored {
+        // Red, purple, blue, green, yellow
+        true => {
+            let (radius, hue, saturation, value) =
+                light_config.colored_multipliers(light_as_hsv.hue.into_positive_degrees());
+            (
+                radius,
+                hue,
+                saturation,
+                value,
+                light_config.colored_hue_shift,
+            )
+        }
+        // Everything else
+        false => (
+            light_config.standard_radius,
+            light_config.standard_hue,
+            light_config.standard_saturation,
+            light_config.standard_value,
+            light_config.standard_hue_shift,
+        ),
+    }
+}
+
+/// The color and radius a light ends up with through the global settings alone,
+/// before its radius is bounded and its color rounded
+fn global_transform(
+    light_config: &LightConfig,
+    mut light_as_hsv: Hsv,
+    is_colored: bool,
+    base_radius: f32,
+) -> (Hsv, u32) {
+    let (global_radius, global_hue, global_saturation, global_value, global_hue_shift) =
+        global_multipliers(light_config, light_as_hsv, is_colored);
+
+    if let Some((target, strength)) = light_config.target_color(is_colored) {
+        light_as_hsv = color::blend_toward(light_as_hsv, target, strength);
+    } else if light_config.transform_mode == TransformMode::Kelvin && !is_colored {
+        light_as_hsv = color::blend_toward_kelvin(
+            light_as_hsv,
+            light_config.target_kelvin,
+            light_config.kelvin_strength,
+        );
+    } else {
+        light_as_hsv.set_hue(adjust_hue(light_as_hsv.hue, global_hue, global_hue_shift));
+        light_as_hsv.saturation *= global_saturation;
+    }
+
+    light_as_hsv = color::scale_value(light_as_hsv, global_value, light_config.color_space);
+
+    (light_as_hsv, (global_radius * base_radius) as u32)
+}
+
+/// Follows the radius mode, then bounds the radius by min_radius and max_radius
+fn finish_radius(light_config: &LightConfig, radius: u32, value: f32) -> u32 {
+    let radius = match light_config.radius_mode {
+        RadiusMode::Flat => radius,
+        RadiusMode::Adaptive => {
+            let falloff = value.clamp(0.0, 1.0).powf(light_config.radius_curve);
+            (radius as f32 * falloff) as u32
+        }
+    };
+
+    light_config.clamp_radius(radius)
+}
+
+/// The color and radius math lightfixes applies to a light no override matches, as a pure function of its data
+/// Only color and radius change: flags, duration, and carryable limits are left to process_light
+/// Dithering is seeded as if the light had no ID, since LightData doesn't carry one
+pub fn transform_light(mut light_data: LightData, light_config: &LightConfig) -> LightData {
+    let (light_as_hsv, is_colored) = light_to_hsv(&light_data);
+    let (mut light_as_hsv, radius) = global_transform(
+        light_config,
+        light_as_hsv,
+        is_colored,
+        light_data.radius as f32,
+    );
+
+    light_data.radius = finish_radius(light_config, radius, light_as_hsv.value);
+
+    if let Some(min_luminance) = light_config.min_perceived_brightness {
+        light_as_hsv = color::raise_to_luminance(light_as_hsv, min_luminance);
+    }
+
+    light_data.color = color::quantize(light_as_hsv, light_config.color_rounding, "");
+    light_data
+}
+
+/// Multiplies a hue, as the *_hue settings always have, then rotates it by `shift` degrees
+/// The rotation wraps around at 360, so a reddish hue near 0 can be nudged either way
+fn adjust_hue(hue: palette::RgbHue, mult: f32, shift: f32) -> palette::RgbHue {
+    palette::RgbHue::from_degrees((hue.into_raw_degrees() * mult + shift).rem_euclid(360.0))
+}
+
+/// light_to_hsv over a contiguous batch of light data, converted in parallel
+/// Results are in the same order as `light_data`
+pub fn batch_to_hsv(light_data: &[LightData]) -> Vec<(Hsv, bool)> {
+    light_data.par_iter().map(light_to_hsv).collect()
+}
+
+/// What process_light did with a light, for the summary shown at the end of a run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightOutcome {
+    Standard,
+    Colored,
+    Negative,
+    Disabled,
+}
+
+/// What the rest of the load order says about a light, which changes how it's fixed beyond its own record
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightContext {
+    /// Multiplies the original radius, eg to suit the size of the light's model
+    pub radius_scale: f32,
+    pub class: Option<LightClass>,
+    /// The lowercased region the light is placed in most often
+    pub region: Option<String>,
+    pub placement: Placement,
+}
+
+impl Default for LightContext {
+    fn default() -> Self {
+        LightContext {
+            radius_scale: 1.0,
+            class: None,
+            region: None,
+            placement: Placement::default(),
+        }
+    }
+}
+
+pub fn process_light(light_config: &LightConfig, light: &mut Light) -> LightOutcome {
+    process_light_in(light_config, light, &LightContext::default())
+}
+
+/// Same as process_light, but with the class, region, and placement multipliers for `context` on top,
+/// and the original radius first multiplied by its radius_scale
+/// Scaling is folded into the rest of the radius math, so the radius is only rounded once
+pub fn process_light_in(
+    light_config: &LightConfig,
+    light: &mut Light,
+    context: &LightContext,
+) -> LightOutcome {
+    let hsv = light_to_hsv(&light.data);
+    process_converted_light(light_config, light, context, hsv)
+}
+
+/// Multiplies a fixed light by the settings for its class, region, and placement, in that order
+/// Anything fixed by `replacement` is exactly what the user asked for, so it's left alone
+fn apply_context(
+    light_config: &LightConfig,
+    light_data: &mut LightData,
+    context: &LightContext,
+    replacement: Option<&CustomLightData>,
+) {
+    let hue_fixed = replacement.is_some_and(|replacement| replacement.hue.is_some());
+    let saturation_fixed = replacement.is_some_and(|replacement| replacement.saturation.is_some());
+    let value_fixed = replacement.is_some_and(|replacement| replacement.value.is_some());
+    let radius_fixed = replacement.is_some_and(|replacement| replacement.radius.is_some());
+
+    // Overrides fixing the whole color and radius, such as rows imported from a light table, are final
+    if hue_fixed && saturation_fixed && value_fixed && radius_fixed {
+        return;
+    }
+
+    let apply_extra = |multipliers: &ExtraMultipliers, light_data: &mut LightData| {
+        ExtraMultipliers {
+            hue: multipliers.hue.filter(|_| !hue_fixed),
+            saturation: multipliers.saturation.filter(|_| !saturation_fixed),
+            value: multipliers.value.filter(|_| !value_fixed),
+            radius: multipliers.radius.filter(|_| !radius_fixed),
+        }
+        .apply(light_data);
+
+        if !radius_fixed {
+            light_data.radius = light_config.clamp_radius(light_data.radius);
+        }
+    };
+
+    if let Some(class) = &context.class
+        && let Some(multipliers) = light_config.classes.get(class)
+    {
+        apply_extra(multipliers, light_data);
+    }
+
+    if let Some(region) = &context.region
+        && let Some(multipliers) = light_config.regions.get(region)
+    {
+        apply_extra(multipliers, light_data);
+    }
+
+    if light_config.uses_placement_radius() && !radius_fixed {
+        let radius = light_data.radius as f32 * light_config.placement_radius(context.placement);
+        light_data.radius = light_config.clamp_radius(radius as u32);
+    }
+}
+
+/// Same as process_light_in, with the color of the light already converted by light_to_hsv
+fn process_converted_light(
+    light_config: &LightConfig,
+    light: &mut Light,
+    context: &LightContext,
+    (mut light_as_hsv, is_colored): (Hsv, bool),
+) -> LightOutcome {
+    // decorative_radius is compared against the radius the load order gave the light
+    let original_radius = light.data.radius;
+
+    if light_config.remove_light_sounds {
+        light.sound.clear();
+    } else if let Some(replacement) = light_config
+        .light_sound_replacements
+        .get(&light.sound.to_ascii_lowercase())
+    {
+        light.sound = replacement.to_owned();
+    }
+
+    if light.data.flags.contains(LightFlags::NEGATIVE) {
+        light.data.flags.remove(LightFlags::NEGATIVE);
+        light.data.radius = 0;
+        light.data.color = [0, 0, 0, 0];
+        return LightOutcome::Negative;
+    }
+
+    if light_config.is_decorative(light, original_radius) {
+        light.data.radius = 0;
+        light.data.color = [0, 0, 0, 0];
+        return LightOutcome::Disabled;
+    }
+
+    if light.data.flags.contains(LightFlags::CAN_CARRY) {
+        light_config
+            .carried_light_flags
+            .apply(&mut light.data.flags);
+    }
+
+    if light_config.disable_flickering {
+        light
+            .data
+            .flags
+            .remove(LightFlags::FLICKER | LightFlags::FLICKER_SLOW);
+    }
+
+    if light_config.disable_pulse {
+        light
+            .data
+            .flags
+            .remove(LightFlags::PULSE | LightFlags::PULSE_SLOW);
+    }
+
+    let light_id = light_config.encoding.record_id(&light.id);
+    let base_radius = light.data.radius as f32 * context.radius_scale;
+
+    let mut replacement_light_data: Option<&CustomLightData> = None;
+
+    for (regex, light_data) in &light_config.light_regexes {
+        if regex.is_match(&light_id) {
+            replacement_light_data = Some(light_data);
+            break;
+        }
+    }
+
+    if let Some(replacement) = replacement_light_data {
+        let (global_radius, global_hue, global_saturation, global_value, global_hue_shift) =
+            global_multipliers(light_config, light_as_hsv, is_colored);
+
+        if let Some(hue_mult) = replacement.hue_mult {
+            let new_hue =
+                palette::RgbHue::from_degrees(light_as_hsv.hue.into_raw_degrees() * hue_mult);
+            light_as_hsv.set_hue(new_hue);
+        } else if let Some(fixed_hue) = replacement.hue {
+            light_as_hsv.set_hue(palette::RgbHue::from_degrees(fixed_hue as f32));
+        } else {
+            light_as_hsv.set_hue(adjust_hue(light_as_hsv.hue, global_hue, global_hue_shift));
+        }
+
+        if let Some(saturation_mult) = replacement.saturation_mult {
+            light_as_hsv.saturation *= saturation_mult;
+        } else if let Some(fixed_saturation) = replacement.saturation {
+            light_as_hsv.saturation = fixed_saturation;
+        } else {
+            light_as_hsv.saturation *= global_saturation;
+        }
+
+        if let Some(value_mult) = replacement.value_mult {
+            light_as_hsv = color::scale_value(light_as_hsv, value_mult, light_config.color_space);
+        } else if let Some(fixed_value) = replacement.value {
+            light_as_hsv.value = fixed_value;
+        } else {
+            light_as_hsv = color::scale_value(light_as_hsv, global_value, light_config.color_space);
+        }
+
+        if let Some(duration_mult) = replacement.duration_mult {
+            light.data.time = (duration_mult * light.data.time as f32) as i32;
+        } else if let Some(fixed_duration) = replacement.duration {
+            light.data.time = fixed_duration as i32;
+        } else {
+            light.data.time = (light.data.time as f32 * light_config.duration_mult) as i32;
+        }
+
+        if let Some(radius_mult) = replacement.radius_mult {
+            light.data.radius = (radius_mult * base_radius) as u32;
+        } else if let Some(fixed_radius) = replacement.radius {
+            light.data.radius = fixed_radius;
+        } else {
+            light.data.radius = (global_radius * base_radius) as u32;
+        }
+
+        if let Some(flag) = &replacement.flag {
+            light.data.flags = flag.to_esp_flag();
+        } else if let Some(animation) = &replacement.animation {
+            // Only swap the animation, so carryable or negative lights don't lose those flags
+            light.data.flags.remove(LightFlag::animation_flags());
+            light.data.flags.insert(animation.to_esp_flag());
+        }
+    } else {
+        (light_as_hsv, light.data.radius) =
+            global_transform(light_config, light_as_hsv, is_colored, base_radius);
+        light.data.time = (light.data.time as f32 * light_config.duration_mult) as i32;
+    }
+
+    // Value may follow duration, so this waits until the duration is final
+    if light.data.flags.contains(LightFlags::CAN_CARRY) {
+        light_config.carryable.apply(&mut light.data);
+    }
+
+    // Radii fixed by an override are exactly what the user asked for
+    if !replacement_light_data.is_some_and(|replacement| replacement.radius.is_some()) {
+        light.data.radius = finish_radius(light_config, light.data.radius, light_as_hsv.value);
+    }
+
+    // Values fixed by an override are exactly what the user asked for
+    if let Some(min_luminance) = light_config.min_perceived_brightness
+        && !replacement_light_data.is_some_and(|replacement| replacement.value.is_some())
+    {
+        light_as_hsv = color::raise_to_luminance(light_as_hsv, min_luminance);
+    }
+
+    light.data.color = color::quantize(light_as_hsv, light_config.color_rounding, &light.id);
+
+    // Negative and decorative lights have already returned, so their context can't turn them back on
+    apply_context(
+        light_config,
+        &mut light.data,
+        context,
+        replacement_light_data,
+    );
+
+    if is_colored {
+        LightOutcome::Colored
+    } else {
+        LightOutcome::Standard
+    }
+}
+
+/// Applies process_light to every light in the set, returning what was done to each, in the same order
+pub fn apply(light_set: &mut LightSet, light_config: &LightConfig) -> Vec<LightOutcome> {
+    apply_in(light_set, light_config, |_| LightContext::default())
+}
+
+/// Same as apply, but with each light fixed as process_light_in does for `context(light)`
+/// `context` is called once per light, in order, and the lights themselves are then processed in parallel
+pub fn apply_in(
+    light_set: &mut LightSet,
+    light_config: &LightConfig,
+    mut context: impl FnMut(&Light) -> LightContext,
+) -> Vec<LightOutcome> {
+    let contexts: Vec<LightContext> = light_set
+        .lights
+        .iter()
+        .map(|(_, light)| context(light))
+        .collect();
+
+    let light_data: Vec<LightData> = light_set
+        .lights
+        .iter()
+        .map(|(_, light)| light.data.clone())
+        .collect();
+
+    light_set
+        .lights
+        .par_iter_mut()
+        .zip(contexts)
+        .zip(batch_to_hsv(&light_data))
+        .map(|(((_, light), context), hsv)| {
+            process_converted_light(light_config, light, &context, hsv)
+        })
+        .collect()
+}
@@ -0,0 +1,234 @@
+use std::{
+    env::{
+        consts::{ARCH, OS},
+        current_exe,
+    },
+    fs::{remove_file, rename, write},
+    io::{self, Read},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::to_io_error;
+
+pub const RELEASES_URL: &str =
+    "https://api.github.com/repos/magicaldave/S3LightFixes/releases/latest";
+
+/// Checks are given up on rather than holding up a run
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Files listing the checksums of every asset in a release, rather than a single one
+const CHECKSUM_LISTS: [&str; 3] = ["sha256sums", "sha256sums.txt", "checksums.txt"];
+
+#[derive(Debug, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// The parts of a GitHub release lightfixes cares about
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// Splits a version like `v0.4.4` into its numbers, so they can be compared
+fn version_numbers(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// Names this architecture goes by in release assets, as builds don't all use Rust's
+fn arch_names() -> &'static [&'static str] {
+    match ARCH {
+        "x86_64" => &["x86_64", "amd64", "x64"],
+        "aarch64" => &["aarch64", "arm64"],
+        "x86" => &["i686", "i386"],
+        _ => std::slice::from_ref(&ARCH),
+    }
+}
+
+/// Lowercase hex digest of `bytes`, as sha256sum prints it
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Finds the checksum for `asset_name` in a sha256sum listing
+fn find_checksum(listing: &str, asset_name: &str) -> Option<String> {
+    listing.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let checksum = parts.next()?;
+
+        match parts.next() {
+            // sha256sum marks files read in binary mode with a leading *
+            Some(name) if name.trim_start_matches('*') != asset_name => None,
+            _ => Some(checksum.to_ascii_lowercase()),
+        }
+    })
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(TIMEOUT)
+        .user_agent(concat!("s3lightfixes/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+impl Release {
+    pub fn latest() -> io::Result<Release> {
+        agent()
+            .get(RELEASES_URL)
+            .call()
+            .map_err(to_io_error)?
+            .into_json()
+    }
+
+    /// Whether this release is newer than the running binary
+    pub fn is_newer(&self) -> bool {
+        version_numbers(&self.tag_name) > version_numbers(env!("CARGO_PKG_VERSION"))
+    }
+
+    /// The bare executable built for this platform and architecture, if the release has one
+    /// Archives and checksums are left out, as they can't be swapped in for the running binary
+    pub fn platform_asset(&self) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| {
+            let name = asset.name.to_ascii_lowercase();
+            let is_executable = ![".zip", ".tar", ".gz", ".7z", ".sha256", ".txt"]
+                .iter()
+                .any(|extension| name.ends_with(extension))
+                && !CHECKSUM_LISTS.contains(&name.as_str());
+
+            name.contains(OS)
+                && arch_names().iter().any(|arch| name.contains(arch))
+                && is_executable
+        })
+    }
+
+    /// The published SHA256 checksum of `asset`, from either `<asset>.sha256` or a checksum list in the release
+    pub fn published_checksum(&self, asset: &ReleaseAsset) -> io::Result<String> {
+        let checksum_name = format!("{}.sha256", asset.name).to_ascii_lowercase();
+
+        let own_checksum = self
+            .assets
+            .iter()
+            .find(|candidate| candidate.name.to_ascii_lowercase() == checksum_name);
+
+        let checksum_asset = own_checksum
+            .or_else(|| {
+                self.assets.iter().find(|candidate| {
+                    CHECKSUM_LISTS.contains(&candidate.name.to_ascii_lowercase().as_str())
+                })
+            })
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "Release {} has no SHA256 checksum for {}, so it can't be verified. Download it from {}",
+                        self.tag_name, asset.name, self.html_url
+                    ),
+                )
+            })?;
+
+        let listing = agent()
+            .get(&checksum_asset.browser_download_url)
+            .call()
+            .map_err(to_io_error)?
+            .into_string()?;
+
+        // A file of its own only holds the one checksum, whatever name is written after it
+        let checksum = match own_checksum {
+            Some(_) => listing
+                .split_whitespace()
+                .next()
+                .map(str::to_ascii_lowercase),
+            None => find_checksum(&listing, &asset.name),
+        };
+
+        checksum.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} doesn't list a checksum for {}",
+                    checksum_asset.name, asset.name
+                ),
+            )
+        })
+    }
+
+    /// Downloads the executable for this platform, checks it against the published checksum, and swaps it in for the running binary
+    /// The old binary is kept next to it with a .old extension until the next update,
+    /// since Windows won't delete a running executable
+    pub fn install(&self) -> io::Result<()> {
+        let asset = self.platform_asset().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Release {} has no executable for {OS} ({ARCH}). Download it from {}",
+                    self.tag_name, self.html_url
+                ),
+            )
+        })?;
+
+        let mut bytes = Vec::new();
+        agent()
+            .get(&asset.browser_download_url)
+            .call()
+            .map_err(to_io_error)?
+            .into_reader()
+            .read_to_end(&mut bytes)?;
+
+        // Nothing is replaced unless the download is exactly what was published
+        let expected = self.published_checksum(asset)?;
+        let actual = sha256_hex(&bytes);
+        if actual != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} doesn't match its published checksum (expected {expected}, got {actual})",
+                    asset.name
+                ),
+            ));
+        }
+
+        let exe = current_exe()?;
+        let new_exe = exe.with_extension("new");
+        let old_exe = exe.with_extension("old");
+
+        write(&new_exe, bytes)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&new_exe, std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        let _ = remove_file(&old_exe);
+        rename(&exe, &old_exe)?;
+
+        if let Err(err) = rename(&new_exe, &exe) {
+            // Put the running binary back, so a failed update doesn't leave nothing behind
+            let _ = rename(&old_exe, &exe);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+}
+
+/// Looks for a newer release in the background, so the run isn't held up by the network
+/// Join the handle only once it is finished, or the notice is skipped for this run
+pub fn check_in_background() -> JoinHandle<Option<Release>> {
+    thread::spawn(|| Release::latest().ok().filter(Release::is_newer))
+}
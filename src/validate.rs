@@ -0,0 +1,178 @@
+use std::{collections::HashSet, fmt, io};
+
+use tes3::esp::{Cell, EditorId, Light, Plugin, TES3Object};
+
+/// Radii beyond this can only come from a multiplier overflowing
+pub const MAX_VALID_RADIUS: u32 = u16::MAX as u32;
+
+/// Something wrong with a generated plugin, found before it is saved
+#[derive(Debug)]
+pub enum ValidationError {
+    /// The plugin couldn't be serialized, or what was serialized couldn't be read back
+    Reparse(io::Error),
+    MissingHeader,
+    ObjectCount {
+        expected: u32,
+        found: usize,
+    },
+    NoMasters,
+    UnresolvedMaster(String),
+    DuplicateMaster(String),
+    /// A master which isn't in the load order, so OpenMW would refuse to load the plugin
+    MasterNotLoaded(String),
+    /// A master listed after one which loads later than it
+    MasterOutOfOrder {
+        master: String,
+        previous: String,
+    },
+    OutOfRange {
+        id: String,
+        field: &'static str,
+        value: String,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ValidationError::*;
+        match self {
+            Reparse(err) => write!(f, "The plugin could not be read back after saving: {err}"),
+            MissingHeader => write!(f, "The plugin has no header"),
+            ObjectCount { expected, found } => write!(
+                f,
+                "The header lists {expected} records, but the plugin contains {found}"
+            ),
+            NoMasters => write!(f, "The plugin has no masters"),
+            UnresolvedMaster(name) => {
+                write!(f, "Master `{name}` was not found in any data directory")
+            }
+            DuplicateMaster(name) => write!(f, "Master `{name}` is listed more than once"),
+            MasterNotLoaded(name) => write!(f, "Master `{name}` is not in the load order"),
+            MasterOutOfOrder { master, previous } => write!(
+                f,
+                "Master `{master}` is listed after `{previous}`, but loads before it"
+            ),
+            OutOfRange { id, field, value } => {
+                write!(f, "Record `{id}` has an out-of-range {field}: {value}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Round-trips a plugin through its serialized form and checks that the result is sane
+/// `is_resolvable` is given the name of each master, and should report whether it can be found
+/// Masters must also appear in `load_order` once each, and in the same order, as OpenMW requires
+/// Every problem found is returned, rather than only the first
+pub fn validate_plugin(
+    plugin: &Plugin,
+    load_order: &[String],
+    is_resolvable: impl Fn(&str) -> bool,
+) -> Result<(), Vec<ValidationError>> {
+    let reparsed = match plugin.save_bytes().and_then(|bytes| {
+        let mut reparsed = Plugin::new();
+        reparsed.load_bytes(&bytes)?;
+        Ok(reparsed)
+    }) {
+        Ok(reparsed) => reparsed,
+        Err(err) => return Err(vec![ValidationError::Reparse(err)]),
+    };
+
+    let mut errors = Vec::new();
+
+    match reparsed.objects.iter().find_map(|object| match object {
+        TES3Object::Header(header) => Some(header),
+        _ => None,
+    }) {
+        None => errors.push(ValidationError::MissingHeader),
+        Some(header) => {
+            let found = reparsed.objects.len() - 1;
+
+            if header.num_objects as usize != found {
+                errors.push(ValidationError::ObjectCount {
+                    expected: header.num_objects,
+                    found,
+                });
+            }
+
+            if header.masters.is_empty() {
+                errors.push(ValidationError::NoMasters);
+            }
+
+            for (name, _) in &header.masters {
+                if !is_resolvable(name) {
+                    errors.push(ValidationError::UnresolvedMaster(name.to_owned()));
+                }
+            }
+
+            let mut seen = HashSet::new();
+            let mut previous: Option<(usize, &str)> = None;
+
+            for (name, _) in &header.masters {
+                if !seen.insert(name.to_ascii_lowercase()) {
+                    errors.push(ValidationError::DuplicateMaster(name.to_owned()));
+                    continue;
+                }
+
+                let Some(position) = load_order
+                    .iter()
+                    .position(|plugin| plugin.eq_ignore_ascii_case(name))
+                else {
+                    errors.push(ValidationError::MasterNotLoaded(name.to_owned()));
+                    continue;
+                };
+
+                if let Some((previous_position, previous_name)) = previous
+                    && position < previous_position
+                {
+                    errors.push(ValidationError::MasterOutOfOrder {
+                        master: name.to_owned(),
+                        previous: previous_name.to_owned(),
+                    });
+                }
+
+                previous = Some((position, name));
+            }
+        }
+    }
+
+    for light in reparsed.objects_of_type::<Light>() {
+        if light.data.radius > MAX_VALID_RADIUS {
+            errors.push(ValidationError::OutOfRange {
+                id: light.editor_id().to_string(),
+                field: "radius",
+                value: light.data.radius.to_string(),
+            });
+        }
+
+        // -1 is used for lights which never burn out
+        if light.data.time < -1 {
+            errors.push(ValidationError::OutOfRange {
+                id: light.editor_id().to_string(),
+                field: "duration",
+                value: light.data.time.to_string(),
+            });
+        }
+    }
+
+    for cell in reparsed.objects_of_type::<Cell>() {
+        let Some(atmosphere) = &cell.atmosphere_data else {
+            continue;
+        };
+
+        if !atmosphere.fog_density.is_finite() || atmosphere.fog_density < 0.0 {
+            errors.push(ValidationError::OutOfRange {
+                id: cell.editor_id().to_string(),
+                field: "fog density",
+                value: atmosphere.fog_density.to_string(),
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
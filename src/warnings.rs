@@ -0,0 +1,81 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Something which went wrong, or not quite as asked, without stopping the run
+/// Library functions hand these back instead of logging them, so embedders can show them together once the plugin is ready
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    /// A plugin which couldn't be read, and was left out
+    PluginSkipped { path: PathBuf, reason: String },
+    /// A plugin of which only the lights or only the cells could be read
+    RecordsSkipped { path: PathBuf },
+    /// A content entry which only matched a file after trimming it or adding an extension
+    FuzzyMatch {
+        content_file: String,
+        matched: String,
+    },
+    /// The output directory couldn't be written to, so the plugin was saved in a fallback directory
+    FallbackPath {
+        plugin_name: String,
+        output_dir: PathBuf,
+        saved_in: PathBuf,
+    },
+}
+
+impl Warning {
+    /// The plugin the warning is about, if any
+    pub fn plugin(&self) -> Option<&Path> {
+        match self {
+            Warning::PluginSkipped { path, .. } | Warning::RecordsSkipped { path } => Some(path),
+            Warning::FuzzyMatch { .. } | Warning::FallbackPath { .. } => None,
+        }
+    }
+
+    /// Logs the warning, at debug for fuzzy matches as they're usually harmless, and as a warning otherwise
+    pub fn log(&self) {
+        match (self, self.plugin()) {
+            (Warning::FuzzyMatch { .. }, _) => log::debug!("{self}"),
+            (_, Some(plugin)) => log::warn!(
+                plugin = plugin.display().to_string().as_str();
+                "{self}"
+            ),
+            (_, None) => log::warn!("{self}"),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::PluginSkipped { path, reason } => write!(
+                f,
+                "Plugin {}: could not be loaded due to error: {reason}",
+                path.display()
+            ),
+            Warning::RecordsSkipped { path } => write!(
+                f,
+                "Plugin {}: could only be partly loaded, as either its lights or cells contain records which could not be read. Use --strict-parse to skip such plugins entirely.",
+                path.display()
+            ),
+            Warning::FuzzyMatch {
+                content_file,
+                matched,
+            } => write!(
+                f,
+                "Content entry {content_file:?} only matched {matched} after trimming it or adding an extension"
+            ),
+            Warning::FallbackPath {
+                plugin_name,
+                output_dir,
+                saved_in,
+            } => write!(
+                f,
+                "Couldn't write {plugin_name} to {}, so it was saved in {} instead!",
+                output_dir.display(),
+                saved_in.display()
+            ),
+        }
+    }
+}
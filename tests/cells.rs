@@ -0,0 +1,114 @@
+//! Checks the interior cells written into the patch, and what OpenMW makes of them once loaded over the rest of the load order
+
+mod common;
+
+use common::{Fixture, find_cell, interior_cell, merge_like_openmw};
+use tes3::esp::{Cell, Plugin, TES3Object};
+
+const CELL_NAME: &str = "Test Interior";
+
+/// Base.esp lights the cell, then WaterFix.esp raises its water without touching the lighting
+fn water_fix_fixture(test_name: &str) -> Fixture {
+    let fixture = Fixture::new(test_name);
+
+    let mut water_fix = interior_cell(CELL_NAME, [200, 180, 150], 50.0);
+    water_fix.atmosphere_data = None;
+
+    fixture
+        .add_records(
+            "Base.esp",
+            vec![TES3Object::from(interior_cell(
+                CELL_NAME,
+                [200, 180, 150],
+                10.0,
+            ))],
+        )
+        .add_records("WaterFix.esp", vec![TES3Object::from(water_fix)]);
+
+    fixture
+}
+
+/// The cell as OpenMW sees it after the whole load order, followed by the patch
+fn merged_cell(generated: &Plugin) -> Cell {
+    let patched = find_cell(generated, CELL_NAME).expect("the cell should be patched");
+
+    assert!(
+        patched.references.is_empty(),
+        "references should never be copied into the patch"
+    );
+
+    let mut water_fixed = interior_cell(CELL_NAME, [200, 180, 150], 10.0);
+    water_fixed.water_height = Some(50.0);
+
+    merge_like_openmw(&water_fixed, patched)
+}
+
+#[test]
+fn atmosphere_records_keep_fields_from_the_load_order() {
+    let fixture = water_fix_fixture("atmosphere_records");
+
+    let generated = fixture.run(
+        &["Base.esp", "WaterFix.esp"],
+        &["--classic", "--cell-records", "atmosphere"],
+    );
+    let patched = find_cell(&generated, CELL_NAME).expect("the cell should be patched");
+
+    assert_eq!(patched.water_height, None);
+    assert_eq!(patched.map_color, None);
+    assert_eq!(patched.region, None);
+
+    let merged = merged_cell(&generated);
+
+    // WaterFix.esp still decides the water, and the patch still decides the sunlight
+    assert_eq!(merged.water_height, Some(50.0));
+    assert_eq!(merged.map_color, Some([12, 34, 56, 0]));
+    assert_eq!(
+        merged
+            .atmosphere_data
+            .expect("the cell should stay lit")
+            .sunlight_color,
+        [0, 0, 0, 0]
+    );
+}
+
+#[test]
+fn full_records_carry_fields_from_the_lighting_source() {
+    let fixture = water_fix_fixture("full_records");
+
+    let generated = fixture.run(
+        &["Base.esp", "WaterFix.esp"],
+        &["--classic", "--cell-records", "full"],
+    );
+    let merged = merged_cell(&generated);
+
+    // The whole cell comes from Base.esp, the last plugin to light it, undoing WaterFix.esp
+    assert_eq!(merged.water_height, Some(10.0));
+    assert_eq!(
+        merged
+            .atmosphere_data
+            .expect("the cell should stay lit")
+            .sunlight_color,
+        [0, 0, 0, 0]
+    );
+}
+
+#[test]
+fn older_engines_get_full_records() {
+    let fixture = water_fix_fixture("older_engine_records");
+
+    let generated = fixture.run(
+        &["Base.esp", "WaterFix.esp"],
+        &[
+            "--classic",
+            "--cell-records",
+            "atmosphere",
+            "--target-engine",
+            "0.47",
+        ],
+    );
+    let patched = find_cell(&generated, CELL_NAME).expect("the cell should be patched");
+
+    // 0.47 replaces the whole cell, so leaving the water out would drop it rather than keep WaterFix.esp's
+    assert_eq!(patched.water_height, Some(10.0));
+    assert_eq!(patched.map_color, Some([12, 34, 56, 0]));
+}
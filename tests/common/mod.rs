@@ -0,0 +1,270 @@
+//! Fixture plugins and golden outputs shared by the integration tests
+//!
+//! Fixtures are built in code with tes3 and saved into a scratch directory, so each test shows exactly what it feeds in.
+//! Each run of lightfixes is dumped to text and compared against `tests/golden/<name>.txt`.
+//! A missing or outdated golden file fails the test; rerun with `S3L_BLESS=1` to write out an intended change.
+
+#![allow(dead_code)]
+
+use std::{
+    env::var_os,
+    fs::{create_dir_all, read_to_string, remove_dir_all, write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use palette::{FromColor, Hsv, rgb::Srgb};
+use tes3::esp::{
+    AtmosphereData, Cell, CellData, CellFlags, FixedString, Header, Light, LightData, LightFlags,
+    ObjectFlags, Plugin, TES3Object, types::FileType,
+};
+
+pub const OUTPUT_NAME: &str = "S3LightFixes.omwaddon";
+
+/// A light with the given color and radius, burning for the usual 600 seconds
+pub fn light(id: &str, color: [u8; 3], radius: u32, flags: LightFlags) -> Light {
+    Light {
+        id: id.to_string(),
+        mesh: "l\\light_com_candle_01.nif".to_string(),
+        data: LightData {
+            weight: 1.0,
+            value: 5,
+            time: 600,
+            radius,
+            color: [color[0], color[1], color[2], 0],
+            flags,
+        },
+        ..Default::default()
+    }
+}
+
+/// An interior cell with water, lit by the given sunlight
+pub fn interior_cell(name: &str, sunlight: [u8; 3], water_height: f32) -> Cell {
+    Cell {
+        name: name.to_string(),
+        data: CellData {
+            flags: CellFlags::IS_INTERIOR | CellFlags::HAS_WATER,
+            ..Default::default()
+        },
+        water_height: Some(water_height),
+        map_color: Some([12, 34, 56, 0]),
+        atmosphere_data: Some(AtmosphereData {
+            ambient_color: [40, 40, 40, 0],
+            sunlight_color: [sunlight[0], sunlight[1], sunlight[2], 0],
+            fog_color: [10, 10, 10, 0],
+            fog_density: 0.5,
+        }),
+        ..Default::default()
+    }
+}
+
+/// A directory of fixture plugins, and somewhere for lightfixes to write its output and config
+pub struct Fixture {
+    pub root: PathBuf,
+}
+
+impl Fixture {
+    /// Starts from an empty scratch directory, unique to the test
+    pub fn new(test_name: &str) -> Fixture {
+        let root = std::env::temp_dir().join(format!(
+            "s3lightfixes-test-{test_name}-{}",
+            std::process::id()
+        ));
+
+        let _ = remove_dir_all(&root);
+        create_dir_all(root.join("data")).expect("scratch data directory should be creatable");
+        create_dir_all(root.join("output")).expect("scratch output directory should be creatable");
+
+        Fixture { root }
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        self.root.join("data")
+    }
+
+    pub fn output_dir(&self) -> PathBuf {
+        self.root.join("output")
+    }
+
+    /// Saves a plugin holding `lights` into the data directory
+    pub fn add_plugin(&self, name: &str, lights: Vec<Light>) -> &Self {
+        self.add_records(name, lights.into_iter().map(TES3Object::from).collect())
+    }
+
+    /// Saves a plugin holding any records into the data directory
+    pub fn add_records(&self, name: &str, records: Vec<TES3Object>) -> &Self {
+        let mut plugin = Plugin::new();
+
+        plugin.objects.push(TES3Object::Header(Header {
+            version: 1.3,
+            author: FixedString("fixture".to_string()),
+            description: FixedString(String::new()),
+            file_type: FileType::Esp,
+            flags: ObjectFlags::default(),
+            num_objects: records.len() as u32,
+            masters: Vec::new(),
+        }));
+
+        plugin.objects.extend(records);
+
+        plugin
+            .save_path(self.data_dir().join(name))
+            .expect("fixture plugin should be writable");
+
+        self
+    }
+
+    /// Runs lightfixes over `load_order` with default settings and any `extra_args`,
+    /// returning the generated plugin
+    pub fn run(&self, load_order: &[&str], extra_args: &[&str]) -> Plugin {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_s3lightfixes"));
+
+        for plugin in load_order {
+            command.args(["--content", plugin]);
+        }
+
+        let status = command
+            .arg("--data")
+            .arg(self.data_dir())
+            .arg("--output")
+            .arg(self.output_dir())
+            .arg("--no-notifications")
+            .args(extra_args)
+            // lightconfig.toml is read from and written to the working directory without an openmw.cfg
+            .current_dir(&self.root)
+            .status()
+            .expect("lightfixes should start");
+
+        assert!(status.success(), "lightfixes exited with {status}");
+
+        Plugin::from_path(self.output_dir().join(OUTPUT_NAME))
+            .expect("lightfixes should have written a readable plugin")
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.root);
+    }
+}
+
+pub fn masters(plugin: &Plugin) -> Vec<String> {
+    plugin
+        .objects
+        .iter()
+        .find_map(|object| match object {
+            TES3Object::Header(header) => Some(
+                header
+                    .masters
+                    .iter()
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+pub fn find_light<'a>(plugin: &'a Plugin, id: &str) -> Option<&'a Light> {
+    plugin
+        .objects_of_type::<Light>()
+        .find(|light| light.id.eq_ignore_ascii_case(id))
+}
+
+pub fn find_cell<'a>(plugin: &'a Plugin, name: &str) -> Option<&'a Cell> {
+    plugin
+        .objects_of_type::<Cell>()
+        .find(|cell| cell.name.eq_ignore_ascii_case(name))
+}
+
+/// The cell OpenMW ends up with after loading `later` over `earlier`
+/// Every field `later` has replaces the one from `earlier`, and anything it leaves out is kept,
+/// while references are merged by their reference number
+pub fn merge_like_openmw(earlier: &Cell, later: &Cell) -> Cell {
+    let mut merged = earlier.clone();
+
+    merged.flags = later.flags;
+    merged.data = later.data.clone();
+
+    if later.region.is_some() {
+        merged.region = later.region.clone();
+    }
+
+    if later.map_color.is_some() {
+        merged.map_color = later.map_color;
+    }
+
+    if later.water_height.is_some() {
+        merged.water_height = later.water_height;
+    }
+
+    if later.atmosphere_data.is_some() {
+        merged.atmosphere_data = later.atmosphere_data.clone();
+    }
+
+    merged.references.extend(
+        later
+            .references
+            .iter()
+            .map(|(key, reference)| (*key, reference.clone())),
+    );
+
+    merged
+}
+
+/// Hue of a stored light color, in degrees from 0 to 360
+pub fn hue(color: [u8; 4]) -> f32 {
+    let hsv: Hsv = Hsv::from_color(Srgb::new(color[0], color[1], color[2]).into_format::<f32>());
+    hsv.hue.into_positive_degrees()
+}
+
+/// A stable textual form of the generated plugin: its masters, then every light sorted by ID
+/// The header description is left out, as it holds the generation time
+pub fn dump(plugin: &Plugin) -> String {
+    let mut lines = vec![format!("masters: {}", masters(plugin).join(", "))];
+
+    let mut lights: Vec<&Light> = plugin.objects_of_type::<Light>().collect();
+    lights.sort_by_key(|light| light.id.to_ascii_lowercase());
+
+    for light in lights {
+        let [red, green, blue, _] = light.data.color;
+
+        lines.push(format!(
+            "{}: #{red:02X}{green:02X}{blue:02X} radius {} time {} flags {:?}",
+            light.id, light.data.radius, light.data.time, light.data.flags
+        ));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Compares a dump against its golden file, writing the file instead if S3L_BLESS is set
+/// A missing golden file fails, so that a fresh checkout can't pass by recording whatever it produced
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{name}.txt"));
+
+    if var_os("S3L_BLESS").is_some() {
+        create_dir_all(path.parent().expect("golden files live in a directory"))
+            .expect("golden directory should be creatable");
+        write(&path, actual).expect("golden file should be writable");
+        return;
+    }
+
+    assert!(
+        path.exists(),
+        "{} is missing. If {name} is a new test, rerun with S3L_BLESS=1 to write it",
+        path.display()
+    );
+
+    let expected = read_to_string(&path).expect("golden file should be readable");
+
+    assert_eq!(
+        expected,
+        actual,
+        "{name} no longer matches {}. If the change is intended, rerun with S3L_BLESS=1",
+        path.display()
+    );
+}
@@ -0,0 +1,134 @@
+//! Compares the header of the patch against one known to load in every supported OpenMW release
+
+mod common;
+
+use common::{Fixture, light};
+use tes3::esp::{Header, LightFlags, ObjectFlags, TES3Object, types::FileType};
+
+fn header_for(target_engine: &str) -> Header {
+    let fixture = Fixture::new(&format!("header_{}", target_engine.replace('.', "_")));
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![light("torch", [255, 170, 80], 256, LightFlags::DYNAMIC)],
+    );
+
+    let generated = fixture.run(&["Lights.esp"], &["--target-engine", target_engine]);
+
+    generated
+        .objects
+        .into_iter()
+        .find_map(|object| match object {
+            TES3Object::Header(header) => Some(header),
+            _ => None,
+        })
+        .expect("the patch should have a header")
+}
+
+#[test]
+fn headers_match_known_good() {
+    for target_engine in ["0.47", "0.48", "0.49"] {
+        let header = header_for(target_engine);
+
+        // As saved by the Construction Set with Tribunal or Bloodmoon, which OpenMW reads without complaint
+        assert_eq!(header.version, 1.3, "version for {target_engine}");
+        assert_eq!(
+            header.file_type,
+            FileType::Esp,
+            "file type for {target_engine}"
+        );
+        assert_eq!(
+            header.flags,
+            ObjectFlags::default(),
+            "flags for {target_engine}"
+        );
+        assert_eq!(header.num_objects, 1, "record count for {target_engine}");
+        assert_eq!(
+            header
+                .masters
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["Lights.esp"],
+            "masters for {target_engine}"
+        );
+    }
+}
+
+#[test]
+fn config_hash_is_stable_across_table_order() {
+    let config_with_regions = |regions: &[&str]| {
+        let mut light_config = s3lightfixes::LightConfig::default();
+
+        for (index, region) in regions.iter().enumerate() {
+            light_config.regions.insert(
+                region.to_string(),
+                s3lightfixes::ExtraMultipliers {
+                    radius: Some(index as f32),
+                    ..Default::default()
+                },
+            );
+        }
+
+        light_config
+    };
+
+    let regions = ["ashlands region", "bitter coast region", "west gash region"];
+    let hash = |light_config: &s3lightfixes::LightConfig| {
+        s3lightfixes::GenerationMetadata::new(light_config, 0).config_hash
+    };
+
+    // Each HashMap is seeded differently, so the two configs will usually list their regions in different orders
+    let first = hash(&config_with_regions(&regions));
+    assert_eq!(first, hash(&config_with_regions(&regions)));
+
+    let mut with_override = config_with_regions(&regions);
+    with_override.light_regexes.push((
+        regex::Regex::new("^torch").expect("pattern should compile"),
+        Default::default(),
+    ));
+    assert_ne!(
+        first,
+        hash(&with_override),
+        "overrides should change the hash"
+    );
+}
+
+#[test]
+fn esp_copy_leaves_out_openmw_only_masters_and_their_records() {
+    let mut generated = tes3::esp::Plugin::new();
+    generated.objects.push(TES3Object::Header(Header {
+        num_objects: 2,
+        masters: vec![
+            ("Morrowind.esm".to_string(), 1),
+            ("Lanterns.omwaddon".to_string(), 2),
+        ],
+        ..Default::default()
+    }));
+    generated.objects.extend([
+        TES3Object::from(light("torch", [255, 170, 80], 256, LightFlags::DYNAMIC)),
+        TES3Object::from(light("lantern", [255, 200, 120], 128, LightFlags::DYNAMIC)),
+    ]);
+
+    let openmw_only_records = [s3lightfixes::record_key(&generated.objects[2])]
+        .into_iter()
+        .collect();
+    let esp = s3lightfixes::to_esp_plugin(&generated, &openmw_only_records);
+
+    let header = esp
+        .objects
+        .iter()
+        .find_map(|object| match object {
+            TES3Object::Header(header) => Some(header),
+            _ => None,
+        })
+        .expect("the ESP copy should have a header");
+
+    assert_eq!(header.masters, [("Morrowind.esm".to_string(), 1)]);
+    assert_eq!(header.num_objects, 1);
+    assert_eq!(esp.objects.len(), 2);
+    assert!(
+        esp.objects
+            .iter()
+            .all(|object| !matches!(object, TES3Object::Light(light) if light.id == "lantern"))
+    );
+}
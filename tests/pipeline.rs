@@ -0,0 +1,554 @@
+//! Runs the full pipeline over small fixture load orders, from reading plugins to writing the patch
+
+mod common;
+
+use common::{Fixture, assert_golden, dump, find_light, hue, light, masters};
+use palette::FromColor;
+use tes3::esp::{LightFlags, ObjectFlags};
+
+const ORANGE: [u8; 3] = [255, 170, 80];
+const BLUE: [u8; 3] = [80, 120, 255];
+
+#[test]
+fn bucket_selection() {
+    let fixture = Fixture::new("bucket_selection");
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![
+            light("orange_torch", ORANGE, 256, LightFlags::DYNAMIC),
+            light("blue_lamp", BLUE, 256, LightFlags::DYNAMIC),
+        ],
+    );
+
+    let generated = fixture.run(&["Lights.esp"], &[]);
+
+    // Orange lights are standard lights, whose hue is multiplied by standard_hue
+    let orange = find_light(&generated, "orange_torch").expect("orange_torch should be patched");
+    let orange_hue = hue(orange.data.color);
+    assert!(
+        (orange_hue - hue([ORANGE[0], ORANGE[1], ORANGE[2], 0]) * 0.62).abs() < 3.0,
+        "orange_torch should have its hue scaled by standard_hue, got {orange_hue}"
+    );
+
+    // Anything else is colored, and colored_hue leaves the hue alone by default
+    let blue = find_light(&generated, "blue_lamp").expect("blue_lamp should be patched");
+    let blue_hue = hue(blue.data.color);
+    assert!(
+        (blue_hue - hue([BLUE[0], BLUE[1], BLUE[2], 0])).abs() < 3.0,
+        "blue_lamp should keep its hue, got {blue_hue}"
+    );
+
+    assert_golden("bucket_selection", &dump(&generated));
+}
+
+#[test]
+fn negative_lights() {
+    let fixture = Fixture::new("negative_lights");
+    fixture.add_plugin(
+        "Shadows.esp",
+        vec![light(
+            "shadow",
+            [40, 40, 40],
+            300,
+            LightFlags::DYNAMIC | LightFlags::NEGATIVE,
+        )],
+    );
+
+    let generated = fixture.run(&["Shadows.esp"], &[]);
+
+    // OpenMW doesn't support negative lights, so they're switched off entirely
+    let shadow = find_light(&generated, "shadow").expect("shadow should be patched");
+    assert!(!shadow.data.flags.contains(LightFlags::NEGATIVE));
+    assert_eq!(shadow.data.radius, 0);
+    assert_eq!(shadow.data.color, [0, 0, 0, 0]);
+
+    assert_golden("negative_lights", &dump(&generated));
+}
+
+#[test]
+fn identical_copies_keep_the_earliest_master() {
+    let fixture = Fixture::new("identical_copies");
+    let torch = light("shared_torch", ORANGE, 256, LightFlags::DYNAMIC);
+
+    fixture
+        .add_plugin("Base.esp", vec![torch.clone()])
+        .add_plugin("Patch.esp", vec![torch])
+        .add_plugin(
+            "Other.esp",
+            vec![light("other_lamp", BLUE, 128, LightFlags::DYNAMIC)],
+        );
+
+    let generated = fixture.run(&["Base.esp", "Patch.esp", "Other.esp"], &[]);
+
+    // Patch.esp only repeats Base.esp, so depending on it would gain nothing
+    assert_eq!(masters(&generated), ["Base.esp", "Other.esp"]);
+    assert_eq!(
+        generated
+            .objects_of_type::<tes3::esp::Light>()
+            .filter(|light| light.id == "shared_torch")
+            .count(),
+        1
+    );
+
+    assert_golden("identical_copies", &dump(&generated));
+}
+
+#[test]
+fn overridden_lights_come_from_the_last_plugin() {
+    let fixture = Fixture::new("overridden_lights");
+
+    fixture
+        .add_plugin(
+            "Base.esp",
+            vec![light("torch", ORANGE, 256, LightFlags::DYNAMIC)],
+        )
+        .add_plugin(
+            "Override.esp",
+            vec![light("torch", ORANGE, 512, LightFlags::DYNAMIC)],
+        );
+
+    let generated = fixture.run(&["Base.esp", "Override.esp"], &[]);
+
+    assert_eq!(masters(&generated), ["Override.esp"]);
+
+    let torch = find_light(&generated, "torch").expect("torch should be patched");
+    let base_radius = find_light(&fixture.run(&["Base.esp"], &[]), "torch")
+        .expect("torch should be patched")
+        .data
+        .radius;
+    assert_eq!(torch.data.radius, base_radius * 2);
+
+    assert_golden("overridden_lights", &dump(&generated));
+}
+
+#[test]
+fn masters_follow_the_load_order() {
+    let fixture = Fixture::new("master_order");
+
+    fixture
+        .add_plugin(
+            "First.esp",
+            vec![light("first_light", ORANGE, 128, LightFlags::DYNAMIC)],
+        )
+        .add_plugin("NoLights.esp", Vec::new())
+        .add_plugin(
+            "Last.esp",
+            vec![light("last_light", BLUE, 128, LightFlags::DYNAMIC)],
+        );
+
+    let generated = fixture.run(&["First.esp", "NoLights.esp", "Last.esp"], &[]);
+
+    // A plugin nothing was taken from isn't a master
+    assert_eq!(masters(&generated), ["First.esp", "Last.esp"]);
+
+    assert_golden("master_order", &dump(&generated));
+}
+
+#[test]
+fn minimal_masters_rebase_onto_the_base_game() {
+    let fixture = Fixture::new("minimal_masters");
+
+    let mut remodeled = light("torch", ORANGE, 256, LightFlags::DYNAMIC);
+    remodeled.mesh = "l\\new_torch.nif".into();
+
+    fixture
+        .add_plugin(
+            "Morrowind.esm",
+            vec![light("torch", ORANGE, 256, LightFlags::DYNAMIC)],
+        )
+        .add_plugin("Remodel.esp", vec![remodeled]);
+
+    let load_order = ["Morrowind.esm", "Remodel.esp"];
+
+    assert_eq!(masters(&fixture.run(&load_order, &[])), ["Remodel.esp"]);
+
+    // Only the model sets Remodel.esp's torch apart, which lightfixes doesn't change
+    let generated = fixture.run(&load_order, &["--master-strategy", "minimal"]);
+    assert_eq!(masters(&generated), ["Morrowind.esm"]);
+
+    let torch = find_light(&generated, "torch").expect("torch should be patched");
+    assert_eq!(torch.mesh, "l\\new_torch.nif");
+}
+
+#[test]
+fn inspect_describes_the_generated_plugin() {
+    let fixture = Fixture::new("inspect");
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![
+            light("orange_torch", ORANGE, 256, LightFlags::DYNAMIC),
+            light("blue_lamp", BLUE, 256, LightFlags::DYNAMIC),
+        ],
+    );
+
+    fixture.run(&["Lights.esp"], &[]);
+
+    let report = s3lightfixes::inspect_plugin(&fixture.output_dir().join(common::OUTPUT_NAME))
+        .expect("the generated plugin should be readable");
+
+    assert!(report.contains("Masters (1):\n  Lights.esp"), "{report}");
+    assert!(report.contains("LIGH: 2"), "{report}");
+    assert!(report.contains(s3lightfixes::GENERATED_MARKER), "{report}");
+    assert!(
+        report.contains("First 2 of 2 lights:\n  blue_lamp"),
+        "{report}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn linked_plugins_are_loaded_once() {
+    let fixture = Fixture::new("linked_plugins");
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![light("orange_torch", ORANGE, 256, LightFlags::DYNAMIC)],
+    );
+
+    std::os::unix::fs::symlink(
+        fixture.data_dir().join("Lights.esp"),
+        fixture.data_dir().join("Linked.esp"),
+    )
+    .expect("the link should be creatable");
+
+    let generated = fixture.run(&["Lights.esp", "Linked.esp"], &[]);
+
+    // Both entries are the same file, so only the later one is a master
+    assert_eq!(masters(&generated), ["Linked.esp"]);
+}
+
+#[test]
+fn dim_lights_are_raised_to_the_brightness_floor() {
+    let fixture = Fixture::new("brightness_floor");
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![light("dim_torch", [60, 40, 20], 256, LightFlags::DYNAMIC)],
+    );
+
+    let luminance = |args: &[&str]| {
+        let generated = fixture.run(&["Lights.esp"], args);
+        let [red, green, blue, _] = find_light(&generated, "dim_torch")
+            .expect("dim_torch should be patched")
+            .data
+            .color;
+
+        s3lightfixes::color::relative_luminance(palette::Hsv::from_color(
+            palette::Srgb::new(red, green, blue).into_format::<f32>(),
+        ))
+    };
+
+    assert!(luminance(&["--standard-value", "0.3"]) < 0.1);
+
+    // Rounding to 8 bits can land just under the floor
+    let raised = luminance(&[
+        "--standard-value",
+        "0.3",
+        "--min-perceived-brightness",
+        "0.1",
+    ]);
+    assert!(
+        (raised - 0.1).abs() < 0.005,
+        "dim_torch should be raised to the floor, got {raised}"
+    );
+}
+
+#[test]
+fn adaptive_radii_shrink_with_value() {
+    let fixture = Fixture::new("adaptive_radius");
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![
+            light("bright_torch", ORANGE, 256, LightFlags::DYNAMIC),
+            light("dim_torch", [128, 85, 40], 256, LightFlags::DYNAMIC),
+        ],
+    );
+
+    let radii = |args: &[&str]| {
+        let generated = fixture.run(&["Lights.esp"], args);
+        let radius = |id: &str| {
+            find_light(&generated, id)
+                .unwrap_or_else(|| panic!("{id} should be patched"))
+                .data
+                .radius
+        };
+
+        (radius("bright_torch"), radius("dim_torch"))
+    };
+
+    let (bright, dim) = radii(&[]);
+    assert_eq!(bright, dim, "flat radii shouldn't depend on value");
+
+    let (bright, dim) = radii(&["--radius-mode", "adaptive", "--radius-curve", "1.0"]);
+    assert!(
+        dim < bright,
+        "dim_torch should reach less far than bright_torch, got {dim} and {bright}"
+    );
+}
+
+#[test]
+fn user_settings_win_over_a_shared_config() {
+    let fixture = Fixture::new("shared_config");
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![light("orange_torch", ORANGE, 100, LightFlags::DYNAMIC)],
+    );
+
+    let shared_path = fixture.root.join("shared.toml");
+    std::fs::write(&shared_path, "standard_radius = 3.0\nduration_mult = 4.0\n")
+        .expect("the shared config should be writable");
+
+    let user_path = fixture.root.join("lightconfig.toml");
+    std::fs::write(&user_path, "standard_radius = 2.0\n")
+        .expect("the user config should be writable");
+
+    let generated = fixture.run(
+        &["Lights.esp"],
+        &["--light-config", &shared_path.to_string_lossy()],
+    );
+
+    let torch = find_light(&generated, "orange_torch").expect("orange_torch should be patched");
+    assert_eq!(torch.data.radius, 200);
+    assert_eq!(torch.data.time, 2400);
+
+    // Neither file is rewritten, so the shared settings never get copied into the user's
+    assert_eq!(
+        std::fs::read_to_string(&user_path).expect("the user config should still exist"),
+        "standard_radius = 2.0\n"
+    );
+}
+
+#[test]
+fn save_config_writes_the_effective_settings() {
+    let fixture = Fixture::new("save_config");
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![light("orange_torch", ORANGE, 100, LightFlags::DYNAMIC)],
+    );
+
+    let shared_path = fixture.root.join("shared.toml");
+    std::fs::write(&shared_path, "standard_radius = 3.0\nduration_mult = 4.0\n")
+        .expect("the shared config should be writable");
+
+    let saved_path = fixture.root.join("saved.toml");
+    std::fs::write(
+        &saved_path,
+        "# Kept across saves\nstandard_radius = 1.0\nmax_radius = 900\n",
+    )
+    .expect("the saved config should be writable");
+
+    fixture.run(
+        &["Lights.esp"],
+        &[
+            "--light-config",
+            &shared_path.to_string_lossy(),
+            "--standard-radius",
+            "2.5",
+            &format!("--save-config={}", saved_path.to_string_lossy()),
+        ],
+    );
+
+    let saved_contents =
+        std::fs::read_to_string(&saved_path).expect("the saved config should still exist");
+    assert!(saved_contents.contains("# Kept across saves"));
+
+    let saved: s3lightfixes::LightConfig =
+        toml::from_str(&saved_contents).expect("the saved config should parse");
+    assert_eq!(saved.standard_radius, 2.5);
+    assert_eq!(saved.duration_mult, 4.0);
+
+    // max_radius isn't set by either config, so it's cleared rather than left behind
+    assert_eq!(saved.max_radius, None);
+}
+
+#[test]
+fn misspelled_settings_suggest_the_intended_name() {
+    let unknown = s3lightfixes::unknown_settings("standard_vaule = 0.5\nstandard_radius = 2.0\n");
+
+    assert_eq!(unknown.len(), 1, "only the misspelled key is unknown");
+    assert!(
+        unknown[0].contains("`standard_value`"),
+        "standard_vaule should suggest standard_value, got {unknown:?}"
+    );
+}
+
+#[test]
+fn negative_and_large_multipliers_are_reported() {
+    let mut light_config = s3lightfixes::LightConfig::default();
+    assert!(light_config.negative_multipliers().is_empty());
+    assert!(light_config.large_multipliers().is_empty());
+
+    light_config.standard_value = -0.5;
+    light_config.colored_radius = 25.0;
+
+    let negative = light_config.negative_multipliers();
+    assert_eq!(negative.len(), 1);
+    assert!(negative[0].starts_with("standard_value"));
+
+    let large = light_config.large_multipliers();
+    assert_eq!(large.len(), 1);
+    assert!(large[0].starts_with("colored_radius"));
+}
+
+#[test]
+fn change_log_lists_old_and_new_values() {
+    let fixture = Fixture::new("change_log");
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![light("orange_torch", ORANGE, 100, LightFlags::DYNAMIC)],
+    );
+
+    let generated = fixture.run(&["Lights.esp"], &["--write-log=json"]);
+    let patched = find_light(&generated, "orange_torch").expect("orange_torch should be patched");
+
+    let change_log: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(fixture.root.join("lightconfig-changes.json"))
+            .expect("--write-log=json should write the change log"),
+    )
+    .expect("the change log should be valid JSON");
+
+    let change = &change_log["lights"][0];
+    assert_eq!(change["id"], "orange_torch");
+    assert_eq!(change["source"], "Lights.esp");
+    assert_eq!(change["radius"]["before"], 100);
+    assert_eq!(change["radius"]["after"], patched.data.radius);
+}
+
+#[test]
+fn legacy_lighting_lowers_large_radii_when_asked() {
+    use s3lightfixes::openmw_settings::{LightingMethod, LightingSettings};
+
+    let lighting = LightingSettings::parse(
+        "[Camera]\nlighting method = shaders\n\n[Shaders]\n# comment\nlighting method = legacy\nmax lights = 16\n",
+    );
+    assert_eq!(lighting.lighting_method, LightingMethod::Legacy);
+    assert_eq!(lighting.max_lights, 16);
+
+    let mut light_config = s3lightfixes::LightConfig::default();
+    let warnings = light_config.fit_lighting_method(&lighting);
+    assert_eq!(warnings.len(), 2, "both default radii are above 1.0");
+    assert!(
+        light_config.standard_radius > 1.0,
+        "radii are only warned about by default"
+    );
+
+    light_config.fit_lighting_method = true;
+    light_config.fit_lighting_method(&lighting);
+    assert_eq!(light_config.standard_radius, 1.0);
+    assert_eq!(light_config.colored_radius, 1.0);
+
+    // Shader lighting has no such limit
+    let mut light_config = s3lightfixes::LightConfig::default();
+    assert!(
+        light_config
+            .fit_lighting_method(&LightingSettings::default())
+            .is_empty()
+    );
+}
+
+#[test]
+fn profile_selection_is_stripped_from_the_command_line() {
+    let args = [
+        "-c",
+        "vanilla",
+        "--openmw-cfg=overhaul",
+        "-ctesting",
+        "--standard-value",
+        "0.5",
+        "--profiles",
+        "profiles.txt",
+        "-q",
+    ]
+    .map(std::ffi::OsString::from);
+
+    assert_eq!(
+        s3lightfixes::strip_profile_args(args),
+        ["--standard-value", "0.5", "-q"].map(std::ffi::OsString::from)
+    );
+}
+
+#[test]
+fn profile_lists_skip_comments_and_resolve_relative_paths() {
+    let fixture = Fixture::new("profile_list");
+    let list_path = fixture.root.join("profiles.txt");
+    std::fs::write(
+        &list_path,
+        "# Every profile to fix\nvanilla\n\n/absolute/overhaul/openmw.cfg\n",
+    )
+    .expect("profile list should be writable");
+
+    assert_eq!(
+        s3lightfixes::read_profiles(&list_path).expect("profile list should be readable"),
+        [
+            fixture.root.join("vanilla"),
+            std::path::PathBuf::from("/absolute/overhaul/openmw.cfg"),
+        ]
+    );
+}
+
+#[test]
+fn flag_overrides_replace_while_animation_overrides_swap() {
+    let fixture = Fixture::new("flag_overrides");
+    let flags = LightFlags::DYNAMIC | LightFlags::CAN_CARRY | LightFlags::FLICKER;
+
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![
+            light("replaced_torch", ORANGE, 256, flags),
+            light("swapped_torch", ORANGE, 256, flags),
+        ],
+    );
+
+    let generated = fixture.run(
+        &["Lights.esp"],
+        &[
+            "--light",
+            "^replaced_torch$=flag=PULSE",
+            "--light",
+            "^swapped_torch$=animation=PULSE",
+        ],
+    );
+
+    let replaced =
+        find_light(&generated, "replaced_torch").expect("replaced_torch should be patched");
+    assert_eq!(replaced.data.flags, LightFlags::PULSE);
+
+    let swapped = find_light(&generated, "swapped_torch").expect("swapped_torch should be patched");
+    assert_eq!(
+        swapped.data.flags,
+        LightFlags::DYNAMIC | LightFlags::CAN_CARRY | LightFlags::PULSE
+    );
+}
+
+#[test]
+fn record_flags_survive_the_round_trip() {
+    let fixture = Fixture::new("record_flags");
+    let with_flags = |id: &str, flags: ObjectFlags| {
+        let mut light = light(id, ORANGE, 256, LightFlags::DYNAMIC);
+        light.flags = flags;
+        light
+    };
+
+    fixture.add_plugin(
+        "Lights.esp",
+        vec![
+            with_flags("persistent_torch", ObjectFlags::PERSISTENT),
+            // Modified is a Construction Set bookkeeping flag, and isn't carried over unless asked for
+            with_flags(
+                "blocked_torch",
+                ObjectFlags::BLOCKED | ObjectFlags::MODIFIED,
+            ),
+            with_flags("deleted_torch", ObjectFlags::DELETED),
+        ],
+    );
+
+    let generated = fixture.run(&["Lights.esp"], &["--emit-deletions"]);
+
+    let flags_of = |id: &str| {
+        find_light(&generated, id)
+            .unwrap_or_else(|| panic!("{id} should be in the patch"))
+            .flags
+    };
+
+    assert_eq!(flags_of("persistent_torch"), ObjectFlags::PERSISTENT);
+    assert_eq!(flags_of("blocked_torch"), ObjectFlags::BLOCKED);
+    assert_eq!(flags_of("deleted_torch"), ObjectFlags::DELETED);
+}
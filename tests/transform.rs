@@ -0,0 +1,249 @@
+//! Property tests for the color and radius math, run directly against transform_light without any plugins,
+//! along with tests of how each light's context is applied on top
+
+use palette::Hsv;
+use proptest::prelude::*;
+use tes3::esp::{Light, LightData, LightFlags};
+
+use s3lightfixes::{
+    ColorRounding, CustomLightData, ExtraMultipliers, LightClass, LightConfig, Placement, color,
+    light_override::LightFlag,
+    light_table::LightRow,
+    transform::{LightContext, LightOutcome, light_to_hsv, process_light_in, transform_light},
+};
+
+/// Every multiplier at 1.0 and nothing shifted, so lights should come out exactly as they went in
+fn identity_config() -> LightConfig {
+    LightConfig {
+        standard_hue: 1.0,
+        standard_saturation: 1.0,
+        standard_value: 1.0,
+        standard_radius: 1.0,
+        colored_hue: 1.0,
+        colored_saturation: 1.0,
+        colored_value: 1.0,
+        colored_radius: 1.0,
+        ..LightConfig::default()
+    }
+}
+
+fn light_data(color: [u8; 3], radius: u32) -> LightData {
+    LightData {
+        weight: 1.0,
+        value: 5,
+        time: 600,
+        radius,
+        color: [color[0], color[1], color[2], 0],
+        flags: LightFlags::DYNAMIC,
+    }
+}
+
+/// A context placing the light only in exterior cells
+fn exterior_context() -> LightContext {
+    LightContext {
+        placement: Placement {
+            exterior: true,
+            interior: false,
+        },
+        ..LightContext::default()
+    }
+}
+
+/// Shortest distance between two hues, in degrees, going either way around the wheel
+fn hue_distance(a: f32, b: f32) -> f32 {
+    let difference = (a - b).rem_euclid(360.0);
+    difference.min(360.0 - difference)
+}
+
+proptest! {
+    #[test]
+    fn identity_config_leaves_lights_unchanged(color in any::<[u8; 3]>(), radius in 0u32..4096) {
+        let light_config = identity_config();
+        let original = light_data(color, radius);
+
+        let transformed = transform_light(original.clone(), &light_config);
+        prop_assert_eq!(&transformed, &original);
+        prop_assert_eq!(transform_light(transformed, &light_config), original);
+    }
+
+    #[test]
+    fn higher_value_multipliers_never_darken(
+        color in any::<[u8; 3]>(),
+        first in 0.0f32..3.0,
+        second in 0.0f32..3.0,
+    ) {
+        let brightest_channel = |value_mult: f32| {
+            let light_config = LightConfig {
+                standard_value: value_mult,
+                colored_value: value_mult,
+                ..LightConfig::default()
+            };
+
+            let [red, green, blue, _] = transform_light(light_data(color, 256), &light_config).color;
+            red.max(green).max(blue)
+        };
+
+        let (lower, higher) = (first.min(second), first.max(second));
+        prop_assert!(brightest_channel(lower) <= brightest_channel(higher));
+    }
+
+    #[test]
+    fn hue_shifts_wrap_around_the_wheel(
+        hue in 0.0f32..360.0,
+        saturation in 0.6f32..1.0,
+        value in 0.6f32..1.0,
+        shift in -720.0f32..720.0,
+    ) {
+        let [red, green, blue, _] =
+            color::quantize(Hsv::new(hue, saturation, value), ColorRounding::Nearest, "");
+        let original = light_data([red, green, blue], 256);
+
+        let light_config = LightConfig {
+            standard_hue_shift: shift,
+            colored_hue_shift: shift,
+            ..identity_config()
+        };
+
+        let original_hue = light_to_hsv(&original).0.hue.into_positive_degrees();
+        let shifted = transform_light(original, &light_config);
+        let shifted_hue = light_to_hsv(&shifted).0.hue.into_positive_degrees();
+
+        // Rounding the result back to 8 bits moves the hue by a fraction of a degree
+        let expected = (original_hue + shift).rem_euclid(360.0);
+        prop_assert!(
+            hue_distance(shifted_hue, expected) < 1.5,
+            "expected a hue of {expected}, got {shifted_hue}"
+        );
+    }
+}
+
+#[test]
+fn context_multipliers_leave_negative_lights_off() {
+    let light_config = LightConfig {
+        min_radius: Some(64),
+        exterior_radius: 2.0,
+        ..LightConfig::default()
+    };
+
+    let mut shadow = Light {
+        id: "shadow".to_string(),
+        data: LightData {
+            flags: LightFlags::DYNAMIC | LightFlags::NEGATIVE,
+            ..light_data([40, 40, 40], 300)
+        },
+        ..Default::default()
+    };
+
+    let outcome = process_light_in(&light_config, &mut shadow, &exterior_context());
+
+    // min_radius and exterior_radius would otherwise bring the radius back up from 0
+    assert_eq!(outcome, LightOutcome::Negative);
+    assert_eq!(shadow.data.radius, 0);
+    assert_eq!(shadow.data.color, [0, 0, 0, 0]);
+}
+
+#[test]
+fn context_multipliers_leave_fixed_radii_and_values_alone() {
+    let mut light_config = LightConfig {
+        exterior_radius: 2.0,
+        max_radius: Some(200),
+        ..LightConfig::default()
+    };
+
+    light_config.light_regexes.push((
+        regex::Regex::new("^torch$").expect("the pattern should compile"),
+        CustomLightData {
+            radius: Some(300),
+            value: Some(0.5),
+            ..Default::default()
+        },
+    ));
+
+    light_config.regions.insert(
+        "ashlands region".to_string(),
+        ExtraMultipliers {
+            value: Some(2.0),
+            radius: Some(2.0),
+            ..Default::default()
+        },
+    );
+
+    let torch = Light {
+        id: "torch".to_string(),
+        data: light_data([255, 170, 80], 100),
+        ..Default::default()
+    };
+
+    let mut alone = torch.clone();
+    process_light_in(&light_config, &mut alone, &LightContext::default());
+
+    let mut in_context = torch;
+    process_light_in(
+        &light_config,
+        &mut in_context,
+        &LightContext {
+            region: Some("ashlands region".to_string()),
+            ..exterior_context()
+        },
+    );
+
+    // Neither the region, exterior_radius, nor max_radius touch what the override fixed
+    assert_eq!(in_context.data.radius, 300);
+    assert_eq!(in_context.data.color, alone.data.color);
+}
+
+#[test]
+fn imported_table_rows_come_back_unchanged() {
+    let row = LightRow {
+        plugin: "Lights.esp".to_string(),
+        id: "torch".to_string(),
+        original_color: "#FFAA50".to_string(),
+        new_color: "#804000".to_string(),
+        original_radius: 100,
+        new_radius: 300,
+        duration: 1500,
+        flag: LightFlag::NONE,
+    };
+
+    let (pattern, override_data) = row.to_override().expect("the row should be valid");
+
+    let mut light_config = LightConfig {
+        exterior_radius: 2.0,
+        min_radius: Some(400),
+        ..LightConfig::default()
+    };
+
+    light_config.light_regexes.push((
+        regex::Regex::new(&pattern).expect("the pattern should compile"),
+        override_data,
+    ));
+
+    light_config.classes.insert(
+        LightClass::Flame,
+        ExtraMultipliers {
+            hue: Some(0.5),
+            saturation: Some(0.5),
+            value: Some(2.0),
+            radius: Some(2.0),
+        },
+    );
+
+    let mut torch = Light {
+        id: "torch".to_string(),
+        data: light_data([255, 170, 80], 100),
+        ..Default::default()
+    };
+
+    process_light_in(
+        &light_config,
+        &mut torch,
+        &LightContext {
+            class: Some(LightClass::Flame),
+            ..exterior_context()
+        },
+    );
+
+    assert_eq!(color::to_hex(torch.data.color), row.new_color);
+    assert_eq!(torch.data.radius, row.new_radius);
+    assert_eq!(torch.data.time, row.duration);
+}